@@ -0,0 +1,396 @@
+//! Rendering of program accounts as JSON or key/value tables.
+
+use anchor_lang::prelude::Pubkey;
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+use sollend_client::program::{
+    AutoRepay, BorrowerLoanIndex, CreditDelegation, CreditLine, ExposureLedger, Guarantee,
+    IdentityAttestation, LenderProfile, LoanAccount, LoanQuote, LoanState, PairExposure,
+    ProtocolConfig, ReputationAccount, ReputationAttestation, Vouch,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Table,
+}
+
+type Fields = Vec<(&'static str, Value)>;
+
+fn tier_name(tier: u8) -> &'static str {
+    match tier {
+        0 => "A",
+        1 => "B",
+        2 => "C",
+        _ => "D",
+    }
+}
+
+fn state_name(state: &LoanState) -> &'static str {
+    match state {
+        LoanState::Requested => "Requested",
+        LoanState::Funded => "Funded",
+        LoanState::Active => "Active",
+        LoanState::Repaid => "Repaid",
+        LoanState::Defaulted => "Defaulted",
+        LoanState::Cancelled => "Cancelled",
+        LoanState::Expired => "Expired",
+    }
+}
+
+/// Revoked roles and unset lenders are shown as null rather than the default pubkey
+fn optional_key(key: Pubkey) -> Value {
+    if key == Pubkey::default() {
+        Value::Null
+    } else {
+        json!(key.to_string())
+    }
+}
+
+pub fn config_fields(address: &Pubkey, config: &ProtocolConfig) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("version", json!(config.version)),
+        ("authority", json!(config.authority.to_string())),
+        ("oracle_authority", optional_key(config.oracle_authority)),
+        ("pauser", optional_key(config.pauser)),
+        ("fee_manager", optional_key(config.fee_manager)),
+        ("risk_manager", optional_key(config.risk_manager)),
+        (
+            "reputation_steward",
+            optional_key(config.reputation_steward),
+        ),
+        ("identity_attester", optional_key(config.identity_attester)),
+        ("protocol_fee_bps", json!(config.protocol_fee_bps)),
+        ("pause_flags", json!(format!("{:#07b}", config.pause_flags))),
+        ("tier_max_borrow", json!(config.tier_max_borrow)),
+        ("on_time_payment_bonus", json!(config.on_time_payment_bonus)),
+        ("late_payment_penalty", json!(config.late_payment_penalty)),
+        ("default_penalty", json!(config.default_penalty)),
+        ("withdrawal_window", json!(config.withdrawal_window())),
+        ("unwithdrawn_penalty", json!(config.unwithdrawn_penalty)),
+        ("request_ttl", json!(config.request_ttl())),
+        (
+            "tier_max_exposure",
+            json!([0, 1, 2, 3].map(|tier| config.max_exposure(tier))),
+        ),
+        ("max_active_loans", json!(config.active_loan_limit())),
+        ("max_vouches_given", json!(config.vouches_given_limit())),
+        (
+            "max_vouches_received",
+            json!(config.vouches_received_limit()),
+        ),
+        ("total_loans_issued", json!(config.total_loans_issued)),
+        ("total_volume", json!(config.total_volume)),
+        ("total_defaults", json!(config.total_defaults)),
+        ("archived_loans_issued", json!(config.archived_loans_issued)),
+        ("archived_volume", json!(config.archived_volume)),
+        ("archived_defaults", json!(config.archived_defaults)),
+    ]
+}
+
+pub fn reputation_fields(address: &Pubkey, reputation: &ReputationAccount) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("version", json!(reputation.version)),
+        ("owner", json!(reputation.owner.to_string())),
+        ("credit_score", json!(reputation.credit_score)),
+        ("credit_tier", json!(tier_name(reputation.credit_tier))),
+        ("effective_score", json!(reputation.effective_score())),
+        ("total_loans", json!(reputation.total_loans)),
+        ("active_loans", json!(reputation.active_loans)),
+        (
+            "outstanding_principal",
+            json!(reputation.outstanding_principal),
+        ),
+        ("completed_loans", json!(reputation.completed_loans)),
+        ("defaulted_loans", json!(reputation.defaulted_loans)),
+        ("total_borrowed", json!(reputation.total_borrowed)),
+        ("total_repaid", json!(reputation.total_repaid)),
+        ("on_time_payments", json!(reputation.on_time_payments)),
+        ("late_payments", json!(reputation.late_payments)),
+        ("created_at", json!(reputation.created_at)),
+        ("last_updated", json!(reputation.last_updated)),
+        ("is_frozen", json!(reputation.is_frozen)),
+        ("archived_loans", json!(reputation.archived_loans)),
+        ("archived_principal", json!(reputation.archived_principal)),
+        (
+            "archived_interest_paid",
+            json!(reputation.archived_interest_paid),
+        ),
+        (
+            "last_archived_loan_id",
+            json!(reputation.last_archived_loan_id),
+        ),
+        ("last_archived_at", json!(reputation.last_archived_at)),
+        ("vouches_given", json!(reputation.vouches_given)),
+        ("vouches_received", json!(reputation.vouches_received)),
+        ("staked_score", json!(reputation.staked_score)),
+        ("vouched_score", json!(reputation.vouched_score)),
+    ]
+}
+
+pub fn loan_fields(address: &Pubkey, loan: &LoanAccount) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("version", json!(loan.version)),
+        ("borrower", json!(loan.borrower.to_string())),
+        ("loan_id", json!(loan.loan_id)),
+        ("state", json!(state_name(&loan.state))),
+        ("amount", json!(loan.amount)),
+        ("funded_amount", json!(loan.funded_amount)),
+        ("duration_seconds", json!(loan.duration_seconds)),
+        ("max_interest_rate_bps", json!(loan.max_interest_rate_bps)),
+        (
+            "suggested_interest_rate_bps",
+            json!(loan.suggested_interest_rate_bps),
+        ),
+        (
+            "actual_interest_rate_bps",
+            json!(loan.actual_interest_rate_bps),
+        ),
+        ("lender", optional_key(loan.lender.unwrap_or_default())),
+        ("position_mint", optional_key(loan.position_mint)),
+        ("created_at", json!(loan.created_at)),
+        ("expires_at", json!(loan.expires_at)),
+        ("extension_count", json!(loan.extension_count)),
+        ("refinance_count", json!(loan.refinance_count)),
+        ("refinanced_at", json!(loan.refinanced_at)),
+        ("rebate_enabled", json!(loan.rebate_policy.enabled)),
+        (
+            "rebate_min_interest_bps",
+            json!(loan.rebate_policy.min_interest_bps),
+        ),
+        (
+            "rebate_prepayment_fee_bps",
+            json!(loan.rebate_policy.prepayment_fee_bps),
+        ),
+        ("funded_at", json!(loan.funded_at)),
+        ("due_date", json!(loan.due_date)),
+        ("repaid_at", json!(loan.repaid_at)),
+        ("repaid_amount", json!(loan.repaid_amount)),
+        ("withdrawal_deadline", json!(loan.withdrawal_deadline)),
+        ("indexed", json!(loan.indexed)),
+        ("lender_profiled", json!(loan.lender_profiled)),
+        ("concentration_tracked", json!(loan.concentration_tracked)),
+        ("funded_tier", json!(tier_name(loan.funded_tier))),
+        ("guarantee_backing", json!(loan.guarantee_backing)),
+    ]
+}
+
+pub fn loan_index_fields(address: &Pubkey, loan_index: &BorrowerLoanIndex) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("borrower", json!(loan_index.borrower.to_string())),
+        ("next_loan_id", json!(loan_index.next_loan_id)),
+        ("active_loan_ids", json!(loan_index.active_loan_ids)),
+    ]
+}
+
+pub fn lender_profile_fields(address: &Pubkey, lender_profile: &LenderProfile) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("lender", json!(lender_profile.lender.to_string())),
+        ("total_deployed", json!(lender_profile.total_deployed)),
+        ("outstanding", json!(lender_profile.outstanding)),
+        ("interest_earned", json!(lender_profile.interest_earned)),
+        ("losses", json!(lender_profile.losses)),
+        ("loans_funded", json!(lender_profile.loans_funded)),
+        ("loans_repaid", json!(lender_profile.loans_repaid)),
+        ("loans_defaulted", json!(lender_profile.loans_defaulted)),
+        ("loans_by_tier", json!(lender_profile.loans_by_tier)),
+        ("created_at", json!(lender_profile.created_at)),
+        ("last_updated", json!(lender_profile.last_updated)),
+    ]
+}
+
+pub fn exposure_ledger_fields(address: &Pubkey, exposure_ledger: &ExposureLedger) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        (
+            "tier_debt_ceiling",
+            json!(exposure_ledger.tier_debt_ceiling),
+        ),
+        ("tier_outstanding", json!(exposure_ledger.tier_outstanding)),
+        (
+            "total_outstanding",
+            json!(exposure_ledger.total_outstanding()),
+        ),
+        (
+            "max_total_outstanding",
+            json!(exposure_ledger.max_total_outstanding),
+        ),
+        (
+            "max_pair_exposure",
+            json!(exposure_ledger.max_pair_exposure),
+        ),
+    ]
+}
+
+pub fn pair_exposure_fields(address: &Pubkey, pair_exposure: &PairExposure) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("lender", json!(pair_exposure.lender.to_string())),
+        ("borrower", json!(pair_exposure.borrower.to_string())),
+        ("outstanding", json!(pair_exposure.outstanding)),
+        ("active_loans", json!(pair_exposure.active_loans)),
+    ]
+}
+
+pub fn guarantee_fields(address: &Pubkey, guarantee: &Guarantee) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("borrower", json!(guarantee.borrower.to_string())),
+        ("loan_id", json!(guarantee.loan_id)),
+        ("guarantor", json!(guarantee.guarantor.to_string())),
+        ("bond", json!(guarantee.bond)),
+        ("backing", json!(guarantee.backing)),
+        ("attached", json!(guarantee.attached)),
+        ("created_at", json!(guarantee.created_at)),
+    ]
+}
+
+pub fn credit_line_fields(address: &Pubkey, credit_line: &CreditLine) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("lender", json!(credit_line.lender.to_string())),
+        ("borrower", json!(credit_line.borrower.to_string())),
+        ("mint", json!(credit_line.mint.to_string())),
+        ("limit", json!(credit_line.limit)),
+        ("drawn", json!(credit_line.drawn)),
+        ("available", json!(credit_line.available())),
+        ("interest_rate_bps", json!(credit_line.interest_rate_bps)),
+        ("accrued_interest", json!(credit_line.accrued_interest)),
+        ("interest_due", json!(credit_line.interest_due)),
+        ("last_billed", json!(credit_line.last_billed)),
+        ("past_due", json!(credit_line.past_due)),
+        ("last_accrued_at", json!(credit_line.last_accrued_at)),
+        ("statement_ends_at", json!(credit_line.statement_ends_at)),
+        ("statements_closed", json!(credit_line.statements_closed)),
+        ("missed_statements", json!(credit_line.missed_statements)),
+        ("opened_at", json!(credit_line.opened_at)),
+    ]
+}
+
+pub fn credit_delegation_fields(address: &Pubkey, delegation: &CreditDelegation) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("lender", json!(delegation.lender.to_string())),
+        ("borrower", json!(delegation.borrower.to_string())),
+        ("mint", json!(delegation.mint.to_string())),
+        ("allowance", json!(delegation.allowance)),
+        ("interest_rate_bps", json!(delegation.interest_rate_bps)),
+        ("duration_seconds", json!(delegation.duration_seconds)),
+        ("expires_at", json!(delegation.expires_at)),
+        ("total_drawn", json!(delegation.total_drawn)),
+        ("loans_drawn", json!(delegation.loans_drawn)),
+        ("granted_at", json!(delegation.granted_at)),
+    ]
+}
+
+pub fn auto_repay_fields(address: &Pubkey, auto_repay: &AutoRepay) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("loan", json!(auto_repay.loan.to_string())),
+        ("borrower", json!(auto_repay.borrower.to_string())),
+        ("loan_id", json!(auto_repay.loan_id)),
+        ("mint", json!(auto_repay.mint.to_string())),
+        (
+            "borrower_token_account",
+            json!(auto_repay.borrower_token_account.to_string()),
+        ),
+        ("installments", json!(auto_repay.installments)),
+        ("installments_paid", json!(auto_repay.installments_paid)),
+        ("scheduled_total", json!(auto_repay.scheduled_total)),
+        ("collected", json!(auto_repay.collected)),
+        ("keeper_tip", json!(auto_repay.keeper_tip)),
+        ("enabled_at", json!(auto_repay.enabled_at)),
+    ]
+}
+
+pub fn reputation_attestation_fields(
+    address: &Pubkey,
+    attestation: &ReputationAttestation,
+) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("owner", json!(attestation.owner.to_string())),
+        ("credit_score", json!(attestation.credit_score)),
+        ("credit_tier", json!(tier_name(attestation.credit_tier))),
+        ("total_loans", json!(attestation.total_loans)),
+        ("active_loans", json!(attestation.active_loans)),
+        ("completed_loans", json!(attestation.completed_loans)),
+        ("defaulted_loans", json!(attestation.defaulted_loans)),
+        ("is_frozen", json!(attestation.is_frozen)),
+        ("valid_from", json!(attestation.valid_from)),
+        ("valid_until", json!(attestation.valid_until)),
+    ]
+}
+
+pub fn identity_fields(address: &Pubkey, attestation: &IdentityAttestation) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("owner", json!(attestation.owner.to_string())),
+        ("attester", json!(attestation.attester.to_string())),
+        ("issued_at", json!(attestation.issued_at)),
+        ("expires_at", json!(attestation.expires_at)),
+    ]
+}
+
+pub fn vouch_fields(address: &Pubkey, vouch: &Vouch) -> Fields {
+    vec![
+        ("address", json!(address.to_string())),
+        ("voucher", json!(vouch.voucher.to_string())),
+        ("vouchee", json!(vouch.vouchee.to_string())),
+        ("stake", json!(vouch.stake)),
+        ("vouchee_defaults", json!(vouch.vouchee_defaults)),
+        ("created_at", json!(vouch.created_at)),
+    ]
+}
+
+pub fn quote_fields(borrower: &Pubkey, quote: &LoanQuote) -> Fields {
+    vec![
+        ("borrower", json!(borrower.to_string())),
+        ("eligible", json!(quote.eligible)),
+        ("errors", json!(quote.errors)),
+        ("credit_score", json!(quote.credit_score)),
+        ("credit_tier", json!(tier_name(quote.credit_tier))),
+        ("max_borrow", json!(quote.max_borrow)),
+        ("max_exposure", json!(quote.max_exposure)),
+        ("outstanding_principal", json!(quote.outstanding_principal)),
+        (
+            "suggested_interest_rate_bps",
+            json!(quote.suggested_interest_rate_bps),
+        ),
+        ("interest", json!(quote.interest)),
+        ("protocol_fee", json!(quote.protocol_fee)),
+        ("lender_amount", json!(quote.lender_amount)),
+        ("total_repayment", json!(quote.total_repayment)),
+    ]
+}
+
+pub fn print(fields: &Fields, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let object: Map<String, Value> = fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Value::Object(object)).unwrap()
+            );
+        }
+        OutputFormat::Table => {
+            let width = fields.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+            for (key, value) in fields {
+                let value = match value {
+                    Value::String(text) => text.clone(),
+                    Value::Null => "-".to_string(),
+                    other => other.to_string(),
+                };
+                println!("{key:<width$}  {value}");
+            }
+        }
+    }
+}
@@ -0,0 +1,1252 @@
+//! `sollend` - command-line tool for operating and using the Sollend
+//! micro-lending protocol. Every write command signs with `--keypair` and
+//! every read command prints accounts as a table or JSON.
+
+mod display;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token::{self, TokenAccount};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use clap::{Parser, Subcommand, ValueEnum};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use sollend_client::program::{
+    AutoRepay, BorrowerLoanIndex, CreditDelegation, CreditLine, ExposureLedger, Guarantee,
+    IdentityAttestation, LenderProfile, LoanAccount, LoanQuote, PairExposure, ProtocolConfig,
+    RebatePolicy, ReputationAccount, ReputationAttestation, Role, Vouch,
+};
+use sollend_client::{accounts, instructions, pda, quote};
+
+use display::OutputFormat;
+
+#[derive(Parser)]
+#[command(
+    name = "sollend",
+    version,
+    about = "Operate and use the Sollend micro-lending protocol"
+)]
+struct Cli {
+    /// RPC endpoint of the cluster
+    #[arg(
+        long,
+        short = 'u',
+        global = true,
+        env = "SOLLEND_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+
+    /// Keypair that signs and pays for transactions
+    #[arg(
+        long,
+        short = 'k',
+        global = true,
+        env = "SOLLEND_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+
+    /// Output format for read commands
+    #[arg(long, short = 'o', global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Initialize the protocol config, making the signer its owner
+    InitConfig {
+        #[arg(long)]
+        oracle: Pubkey,
+        #[arg(long)]
+        fee_bps: u16,
+    },
+    /// Update the protocol fee (fee manager) and/or pause flags (pauser)
+    UpdateConfig {
+        #[arg(long)]
+        fee_bps: Option<u16>,
+        /// Bitmask of PAUSE_* flags, e.g. 0b11 to halt new requests and funding
+        #[arg(long, value_parser = parse_flags)]
+        pause_flags: Option<u8>,
+    },
+    /// Create the exposure ledger with the default concentration limits (risk manager),
+    /// required before any loan can be funded
+    InitExposureLedger,
+    /// Accept a mint for guarantee bonds (risk manager)
+    AcceptBondMint {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Stop accepting a mint for new guarantee bonds (risk manager)
+    RemoveBondMint {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Assign an operational role (owner only)
+    SetRole {
+        #[arg(value_enum)]
+        role: RoleArg,
+        holder: Pubkey,
+    },
+    /// Revoke an operational role (owner only)
+    RevokeRole {
+        #[arg(value_enum)]
+        role: RoleArg,
+    },
+    /// Attest a wallet's identity, or renew its attestation (identity attester only)
+    AttestIdentity {
+        owner: Pubkey,
+        /// Unix timestamp the attestation lapses at; 0 never expires
+        #[arg(long, default_value_t = 0)]
+        expires_at: i64,
+    },
+    /// Revoke a wallet's identity attestation (identity attester only)
+    RevokeIdentity { owner: Pubkey },
+    /// Create the signer's reputation account
+    CreateReputation,
+    /// Snapshot a reputation into a short-lived attestation (defaults to the signer's)
+    AttestReputation { owner: Option<Pubkey> },
+    /// Create the signer's loan index, which assigns loan IDs
+    CreateLoanIndex {
+        /// First ID to assign; pass one above any loan ID already used by hand
+        #[arg(long, default_value_t = 0)]
+        next_loan_id: u64,
+    },
+    /// Create the signer's lender profile, required before funding or refinancing a loan
+    CreateLenderProfile,
+    /// Pledge a bond as the signer behind a borrower's next loan request; the borrower co-signs
+    GuaranteeLoan {
+        #[arg(long)]
+        borrower: Pubkey,
+        /// Bond in token base units
+        #[arg(long)]
+        bond: u64,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Keypair of the borrower accepting the guarantee
+        #[arg(long)]
+        borrower_keypair: String,
+    },
+    /// Request a loan as the signer under the next ID from their loan index
+    RequestLoan {
+        /// Amount in token base units
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        duration: i64,
+        #[arg(long)]
+        max_rate_bps: u16,
+        /// Back the request with the guarantee pledged for it
+        #[arg(long)]
+        guaranteed: bool,
+    },
+    /// Fund a loan request as the signer, creating the escrow token account if needed
+    FundLoan {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        rate_bps: u16,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Let the borrower repay early for the accrued interest only
+        #[arg(long)]
+        rebate: bool,
+        /// Minimum share of the full interest charged on early repayment
+        #[arg(long, requires = "rebate", default_value_t = 0)]
+        min_interest_bps: u16,
+        /// Fee on the principal for repaying early
+        #[arg(long, requires = "rebate", default_value_t = 0)]
+        prepayment_fee_bps: u16,
+    },
+    /// Withdraw a funded loan into the signer's token account
+    WithdrawLoan {
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Repay an active loan from the signer's token account
+    RepayLoan {
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Token account receiving the protocol fee
+        #[arg(long)]
+        treasury: Pubkey,
+    },
+    /// Extend one of the signer's active loans; the lender co-signs
+    ExtendLoan {
+        #[arg(long)]
+        loan_id: u64,
+        /// Seconds added to the due date
+        #[arg(long)]
+        seconds: i64,
+        /// Interest added on top of the current rate
+        #[arg(long)]
+        rate_bps: u16,
+        /// Keypair of the lender approving the extension
+        #[arg(long)]
+        lender_keypair: String,
+    },
+    /// Pay off an active loan's lender and take it over at a new rate; the borrower co-signs
+    Refinance {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        rate_bps: u16,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Token account receiving the protocol fee
+        #[arg(long)]
+        treasury: Pubkey,
+        /// Keypair of the borrower agreeing to the new terms
+        #[arg(long)]
+        borrower_keypair: String,
+        /// Let the borrower repay early for the accrued interest only
+        #[arg(long)]
+        rebate: bool,
+        /// Minimum share of the full interest charged on early repayment
+        #[arg(long, requires = "rebate", default_value_t = 0)]
+        min_interest_bps: u16,
+        /// Fee on the principal for repaying early
+        #[arg(long, requires = "rebate", default_value_t = 0)]
+        prepayment_fee_bps: u16,
+    },
+    /// Cancel one of the signer's unfunded loan requests
+    CancelLoan {
+        #[arg(long)]
+        loan_id: u64,
+    },
+    /// Close an expired, unfunded loan request (rent goes to the borrower)
+    ExpireRequest {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+    },
+    /// Reclaim funding a borrower never withdrew, once the withdrawal window has passed
+    ReclaimFunding {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Close a repaid, defaulted or expired loan, refunding rent to the borrower and lender
+    CloseLoan {
+        /// Defaults to the signer
+        #[arg(long)]
+        borrower: Option<Pubkey>,
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Return a guarantee's bond to the guarantor once its loan no longer needs it
+    ReleaseGuarantee {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Pay a defaulted loan's guarantee bond to the holder of its lender position
+    ClaimGuarantee {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Mark an overdue loan as defaulted (oracle only)
+    MarkDefault {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+    },
+    /// Unfreeze a defaulted borrower's reputation (reputation steward only)
+    Unfreeze { owner: Pubkey },
+    /// Stake some of your credit score on another user's reputation
+    Vouch {
+        vouchee: Pubkey,
+        /// Score points to stake
+        #[arg(long)]
+        stake: u16,
+    },
+    /// Withdraw a vouch while the vouchee has no loans outstanding
+    RevokeVouch { vouchee: Pubkey },
+    /// Burn a vouch's stake from the voucher's score after the vouchee defaulted
+    SlashVouch {
+        #[arg(long)]
+        voucher: Pubkey,
+        #[arg(long)]
+        vouchee: Pubkey,
+    },
+    /// Open a credit line as the signer for a tier A or B borrower; the borrower co-signs
+    OpenCreditLine {
+        #[arg(long)]
+        borrower: Pubkey,
+        /// Limit in token base units, committed from the signer's token account
+        #[arg(long)]
+        limit: u64,
+        /// Interest charged on the drawn balance per statement period
+        #[arg(long)]
+        rate_bps: u16,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Keypair of the borrower accepting the line
+        #[arg(long)]
+        borrower_keypair: String,
+    },
+    /// Draw from a credit line into the signer's token account
+    DrawCreditLine {
+        #[arg(long)]
+        lender: Pubkey,
+        /// Amount in token base units
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Repay a credit line from the signer's token account
+    RepayCreditLine {
+        #[arg(long)]
+        lender: Pubkey,
+        /// Amount in token base units; defaults to paying the line off
+        #[arg(long)]
+        amount: Option<u64>,
+        /// Token account receiving the protocol fee
+        #[arg(long)]
+        treasury: Pubkey,
+    },
+    /// Close a credit line's statement once its period is over
+    CloseStatement {
+        #[arg(long)]
+        lender: Pubkey,
+        #[arg(long)]
+        borrower: Pubkey,
+    },
+    /// Close one of the signer's credit lines with nothing owed, returning the undrawn limit
+    CloseCreditLine {
+        #[arg(long)]
+        borrower: Pubkey,
+    },
+    /// Let a borrower draw loans from the signer's token account, approving the
+    /// delegation's delegate PDA on it for the allowance
+    DelegateCredit {
+        #[arg(long)]
+        borrower: Pubkey,
+        /// Total the borrower may draw, in token base units
+        #[arg(long)]
+        allowance: u64,
+        #[arg(long)]
+        rate_bps: u16,
+        /// Term of each loan drawn, in seconds
+        #[arg(long)]
+        duration: i64,
+        /// Unix timestamp after which nothing more can be drawn
+        #[arg(long)]
+        expires_at: i64,
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Withdraw the signer's credit delegation to a borrower
+    RevokeCreditDelegation {
+        #[arg(long)]
+        borrower: Pubkey,
+    },
+    /// Draw a loan as the signer against a lender's credit delegation
+    DrawDelegatedCredit {
+        #[arg(long)]
+        lender: Pubkey,
+        /// Amount in token base units
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Let keepers repay one of the signer's active loans in installments, approving the
+    /// schedule's delegate PDA on the signer's token account for the repayment and tips
+    EnableAutoRepay {
+        #[arg(long)]
+        loan_id: u64,
+        /// Even installments to split the repayment into; 1 repays in full at once
+        #[arg(long, default_value_t = 1)]
+        installments: u8,
+        /// Tip paid to the keeper per installment, in token base units
+        #[arg(long, default_value_t = 0)]
+        keeper_tip: u64,
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Collect the next installment of a borrower's auto-repay schedule as the keeper
+    ExecuteAutoRepay {
+        #[arg(long)]
+        borrower: Pubkey,
+        #[arg(long)]
+        loan_id: u64,
+        /// Token account receiving the protocol fee; must be owned by the protocol authority
+        #[arg(long)]
+        treasury: Pubkey,
+    },
+    /// Stop auto-repay on one of the signer's loans, returning what was collected
+    DisableAutoRepay {
+        #[arg(long)]
+        loan_id: u64,
+    },
+    /// Quote a loan by simulating `quote_loan` (borrower defaults to the signer)
+    Quote {
+        amount: u64,
+        /// Duration in seconds
+        duration: i64,
+        #[arg(long)]
+        borrower: Option<Pubkey>,
+    },
+    /// Print a program account
+    #[command(subcommand)]
+    Show(ShowCommand),
+}
+
+#[derive(Subcommand)]
+enum ShowCommand {
+    /// The protocol config
+    Config,
+    /// A reputation account (defaults to the signer's)
+    Reputation { owner: Option<Pubkey> },
+    /// A loan account (borrower defaults to the signer)
+    Loan {
+        loan_id: u64,
+        #[arg(long)]
+        borrower: Option<Pubkey>,
+    },
+    /// A borrower's loan index and open loan IDs (defaults to the signer's)
+    LoanIndex { borrower: Option<Pubkey> },
+    /// A lender's portfolio (defaults to the signer's)
+    LenderProfile { lender: Option<Pubkey> },
+    /// The guarantee behind a loan (borrower defaults to the signer)
+    Guarantee {
+        loan_id: u64,
+        #[arg(long)]
+        borrower: Option<Pubkey>,
+    },
+    /// The protocol-wide debt ceilings and what is outstanding against them
+    ExposureLedger,
+    /// A lender's outstanding principal with one borrower (lender defaults to the signer)
+    PairExposure {
+        borrower: Pubkey,
+        #[arg(long)]
+        lender: Option<Pubkey>,
+    },
+    /// A reputation's latest attestation (defaults to the signer's)
+    ReputationAttestation { owner: Option<Pubkey> },
+    /// A credit line to a borrower (lender defaults to the signer)
+    CreditLine {
+        borrower: Pubkey,
+        #[arg(long)]
+        lender: Option<Pubkey>,
+    },
+    /// A credit delegation to a borrower (lender defaults to the signer)
+    CreditDelegation {
+        borrower: Pubkey,
+        #[arg(long)]
+        lender: Option<Pubkey>,
+    },
+    /// A loan's auto-repay schedule (borrower defaults to the signer)
+    AutoRepay {
+        loan_id: u64,
+        #[arg(long)]
+        borrower: Option<Pubkey>,
+    },
+    /// A wallet's identity attestation (defaults to the signer's)
+    Identity { owner: Option<Pubkey> },
+    /// A vouch for a user (voucher defaults to the signer)
+    Vouch {
+        vouchee: Pubkey,
+        #[arg(long)]
+        voucher: Option<Pubkey>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RoleArg {
+    Pauser,
+    FeeManager,
+    RiskManager,
+    ReputationSteward,
+    Oracle,
+    IdentityAttester,
+}
+
+impl From<RoleArg> for Role {
+    fn from(role: RoleArg) -> Self {
+        match role {
+            RoleArg::Pauser => Role::Pauser,
+            RoleArg::FeeManager => Role::FeeManager,
+            RoleArg::RiskManager => Role::RiskManager,
+            RoleArg::ReputationSteward => Role::ReputationSteward,
+            RoleArg::Oracle => Role::Oracle,
+            RoleArg::IdentityAttester => Role::IdentityAttester,
+        }
+    }
+}
+
+/// Accepts decimal, `0x` hex or `0b` binary flag masks
+fn parse_flags(value: &str) -> Result<u8, String> {
+    let parsed = if let Some(bits) = value.strip_prefix("0b") {
+        u8::from_str_radix(bits, 2)
+    } else if let Some(hex) = value.strip_prefix("0x") {
+        u8::from_str_radix(hex, 16)
+    } else {
+        value.parse()
+    };
+    parsed.map_err(|err| err.to_string())
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+fn load_keypair(path: &str) -> Result<Keypair> {
+    let path = expand_home(path);
+    read_keypair_file(&path).map_err(|err| anyhow!("failed to read keypair {path}: {err}"))
+}
+
+struct Session {
+    rpc: RpcClient,
+    keypair_path: String,
+    output: OutputFormat,
+}
+
+impl Session {
+    /// Loaded on demand so read commands work without a keypair
+    fn signer(&self) -> Result<Keypair> {
+        load_keypair(&self.keypair_path)
+    }
+
+    fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        let data = self
+            .rpc
+            .get_account_data(address)
+            .with_context(|| format!("failed to fetch account {address}"))?;
+        accounts::decode(&data).map_err(|err| anyhow!("failed to decode account {address}: {err}"))
+    }
+
+    /// Whether the protocol's identity gate is on, so reputations and loan requests
+    /// must pass the wallet's identity attestation
+    fn requires_identity(&self) -> Result<bool> {
+        let config: ProtocolConfig = self.fetch(&pda::config_address().0)?;
+        Ok(config.requires_identity())
+    }
+
+    /// Warn before approving `relationship`'s delegate on a token account whose
+    /// approval currently belongs to another delegate; an account has only one
+    fn warn_replaced_delegate(&self, token_account: &Pubkey, relationship: &Pubkey) -> Result<()> {
+        let Some(account) = self
+            .rpc
+            .get_account_with_commitment(token_account, self.rpc.commitment())?
+            .value
+        else {
+            return Ok(());
+        };
+        let token_account = TokenAccount::try_deserialize(&mut account.data.as_slice())?;
+        let delegate = pda::delegate_authority_address(relationship).0;
+        if let COption::Some(approved) = token_account.delegate {
+            if approved != delegate && token_account.delegated_amount > 0 {
+                eprintln!(
+                    "Warning: replaces the approval of {approved} for {} tokens",
+                    token_account.delegated_amount
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Current holder of a loan's lender position and the token account holding it;
+    /// loans funded before positions existed pay the recorded lender directly
+    fn position(&self, loan: &LoanAccount) -> Result<(Pubkey, Option<Pubkey>)> {
+        if loan.position_mint == Pubkey::default() {
+            let lender = loan
+                .lender
+                .ok_or_else(|| anyhow!("loan {} has no lender", loan.loan_id))?;
+            return Ok((lender, None));
+        }
+        let holding = self
+            .rpc
+            .get_token_largest_accounts(&loan.position_mint)?
+            .into_iter()
+            .find(|balance| balance.amount.amount == "1")
+            .ok_or_else(|| anyhow!("no account holds position {}", loan.position_mint))?;
+        let address: Pubkey = holding.address.parse()?;
+        let data = self
+            .rpc
+            .get_account_data(&address)
+            .with_context(|| format!("failed to fetch position account {address}"))?;
+        let account = TokenAccount::try_deserialize(&mut data.as_slice())?;
+        Ok((account.owner, Some(address)))
+    }
+
+    fn send(
+        &self,
+        signer: &Keypair,
+        cosigners: &[Keypair],
+        instructions: &[Instruction],
+    ) -> Result<()> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let signers: Vec<&Keypair> = std::iter::once(signer).chain(cosigners).collect();
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &signers,
+            blockhash,
+        );
+        let signature = self.rpc.send_and_confirm_transaction(&transaction)?;
+        println!("Signature: {signature}");
+        Ok(())
+    }
+
+    /// Simulated with the borrower as fee payer, so no signature is needed
+    fn quote(&self, borrower: Option<Pubkey>, amount: u64, duration: i64) -> Result<()> {
+        let borrower = match borrower {
+            Some(borrower) => borrower,
+            None => self.signer()?.pubkey(),
+        };
+        let exists = |address: &Pubkey| -> Result<bool> {
+            Ok(self
+                .rpc
+                .get_account_with_commitment(address, self.rpc.commitment())?
+                .value
+                .is_some())
+        };
+        let reputation_exists = exists(&pda::reputation_address(&borrower).0)?;
+        let attested = exists(&pda::identity_address(&borrower).0)?;
+        let instruction =
+            instructions::quote_loan(&borrower, reputation_exists, attested, amount, duration);
+        let transaction = Transaction::new_unsigned(Message::new(&[instruction], Some(&borrower)));
+        let result = self.rpc.simulate_transaction(&transaction)?.value;
+        if let Some(err) = result.err {
+            bail!("quote simulation failed: {err}");
+        }
+        let return_data = result
+            .return_data
+            .ok_or_else(|| anyhow!("quote_loan returned no data"))?;
+        let data = BASE64_STANDARD.decode(&return_data.data.0)?;
+        let quote = LoanQuote::deserialize(&mut data.as_slice())?;
+        display::print(&display::quote_fields(&borrower, &quote), self.output);
+        Ok(())
+    }
+
+    fn show(&self, command: ShowCommand) -> Result<()> {
+        let fields = match command {
+            ShowCommand::Config => {
+                let (address, _) = pda::config_address();
+                let config: ProtocolConfig = self.fetch(&address)?;
+                display::config_fields(&address, &config)
+            }
+            ShowCommand::Reputation { owner } => {
+                let owner = match owner {
+                    Some(owner) => owner,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::reputation_address(&owner);
+                let reputation: ReputationAccount = self.fetch(&address)?;
+                display::reputation_fields(&address, &reputation)
+            }
+            ShowCommand::Loan { loan_id, borrower } => {
+                let borrower = match borrower {
+                    Some(borrower) => borrower,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::loan_address(&borrower, loan_id);
+                let loan: LoanAccount = self.fetch(&address)?;
+                display::loan_fields(&address, &loan)
+            }
+            ShowCommand::LoanIndex { borrower } => {
+                let borrower = match borrower {
+                    Some(borrower) => borrower,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::loan_index_address(&borrower);
+                let loan_index: BorrowerLoanIndex = self.fetch(&address)?;
+                display::loan_index_fields(&address, &loan_index)
+            }
+            ShowCommand::LenderProfile { lender } => {
+                let lender = match lender {
+                    Some(lender) => lender,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::lender_profile_address(&lender);
+                let lender_profile: LenderProfile = self.fetch(&address)?;
+                display::lender_profile_fields(&address, &lender_profile)
+            }
+            ShowCommand::Guarantee { loan_id, borrower } => {
+                let borrower = match borrower {
+                    Some(borrower) => borrower,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::guarantee_address(&borrower, loan_id);
+                let guarantee: Guarantee = self.fetch(&address)?;
+                display::guarantee_fields(&address, &guarantee)
+            }
+            ShowCommand::ExposureLedger => {
+                let (address, _) = pda::exposure_ledger_address();
+                let exposure_ledger: ExposureLedger = self.fetch(&address)?;
+                display::exposure_ledger_fields(&address, &exposure_ledger)
+            }
+            ShowCommand::PairExposure { borrower, lender } => {
+                let lender = match lender {
+                    Some(lender) => lender,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::pair_exposure_address(&lender, &borrower);
+                let pair_exposure: PairExposure = self.fetch(&address)?;
+                display::pair_exposure_fields(&address, &pair_exposure)
+            }
+            ShowCommand::ReputationAttestation { owner } => {
+                let owner = match owner {
+                    Some(owner) => owner,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::reputation_attestation_address(&owner);
+                let attestation: ReputationAttestation = self.fetch(&address)?;
+                display::reputation_attestation_fields(&address, &attestation)
+            }
+            ShowCommand::CreditLine { borrower, lender } => {
+                let lender = match lender {
+                    Some(lender) => lender,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::credit_line_address(&lender, &borrower);
+                let credit_line: CreditLine = self.fetch(&address)?;
+                display::credit_line_fields(&address, &credit_line)
+            }
+            ShowCommand::CreditDelegation { borrower, lender } => {
+                let lender = match lender {
+                    Some(lender) => lender,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::credit_delegation_address(&lender, &borrower);
+                let delegation: CreditDelegation = self.fetch(&address)?;
+                display::credit_delegation_fields(&address, &delegation)
+            }
+            ShowCommand::AutoRepay { loan_id, borrower } => {
+                let borrower = match borrower {
+                    Some(borrower) => borrower,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::auto_repay_address(&borrower, loan_id);
+                let auto_repay: AutoRepay = self.fetch(&address)?;
+                display::auto_repay_fields(&address, &auto_repay)
+            }
+            ShowCommand::Identity { owner } => {
+                let owner = match owner {
+                    Some(owner) => owner,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::identity_address(&owner);
+                let attestation: IdentityAttestation = self.fetch(&address)?;
+                display::identity_fields(&address, &attestation)
+            }
+            ShowCommand::Vouch { vouchee, voucher } => {
+                let voucher = match voucher {
+                    Some(voucher) => voucher,
+                    None => self.signer()?.pubkey(),
+                };
+                let (address, _) = pda::vouch_address(&voucher, &vouchee);
+                let vouch: Vouch = self.fetch(&address)?;
+                display::vouch_fields(&address, &vouch)
+            }
+        };
+        display::print(&fields, self.output);
+        Ok(())
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let ctx = Session {
+        rpc: RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        keypair_path: expand_home(&cli.keypair),
+        output: cli.output,
+    };
+    let command = match cli.command {
+        Command::Show(command) => return ctx.show(command),
+        Command::Quote {
+            amount,
+            duration,
+            borrower,
+        } => return ctx.quote(borrower, amount, duration),
+        command => command,
+    };
+    let signer = ctx.signer()?;
+    let me = signer.pubkey();
+    let mut cosigners = Vec::new();
+
+    let instructions = match command {
+        Command::InitConfig { oracle, fee_bps } => {
+            vec![instructions::initialize_config(&me, oracle, fee_bps)]
+        }
+        Command::UpdateConfig {
+            fee_bps,
+            pause_flags,
+        } => {
+            vec![instructions::update_config(&me, fee_bps, pause_flags)]
+        }
+        Command::InitExposureLedger => vec![instructions::initialize_exposure_ledger(&me)],
+        Command::AcceptBondMint { mint } => vec![instructions::accept_bond_mint(&me, &mint)],
+        Command::RemoveBondMint { mint } => vec![instructions::remove_bond_mint(&me, &mint)],
+        Command::SetRole { role, holder } => {
+            vec![instructions::set_role(&me, role.into(), holder)]
+        }
+        Command::RevokeRole { role } => vec![instructions::revoke_role(&me, role.into())],
+        Command::AttestIdentity { owner, expires_at } => {
+            vec![instructions::attest_identity(&me, &owner, expires_at)]
+        }
+        Command::RevokeIdentity { owner } => vec![instructions::revoke_identity(&me, &owner)],
+        Command::CreateReputation => {
+            vec![instructions::create_reputation(
+                &me,
+                ctx.requires_identity()?,
+            )]
+        }
+        Command::AttestReputation { owner } => {
+            vec![instructions::attest_reputation(&me, &owner.unwrap_or(me))]
+        }
+        Command::CreateLoanIndex { next_loan_id } => {
+            vec![instructions::create_loan_index(&me, next_loan_id)]
+        }
+        Command::CreateLenderProfile => vec![instructions::create_lender_profile(&me)],
+        Command::GuaranteeLoan {
+            borrower,
+            bond,
+            mint,
+            borrower_keypair,
+        } => {
+            let loan_index: BorrowerLoanIndex = ctx
+                .fetch(&pda::loan_index_address(&borrower).0)
+                .context("the borrower has no loan index")?;
+            println!("Loan ID: {}", loan_index.next_loan_id);
+            cosigners.push(load_keypair(&borrower_keypair)?);
+            vec![instructions::guarantee_loan(
+                &me,
+                &borrower,
+                loan_index.next_loan_id,
+                &get_associated_token_address(&me, &mint),
+                &mint,
+                bond,
+            )]
+        }
+        Command::RequestLoan {
+            amount,
+            duration,
+            max_rate_bps,
+            guaranteed,
+        } => {
+            let loan_index: BorrowerLoanIndex = ctx
+                .fetch(&pda::loan_index_address(&me).0)
+                .context("run `sollend create-loan-index` first")?;
+            println!("Loan ID: {}", loan_index.next_loan_id);
+            vec![instructions::create_loan_request(
+                &me,
+                loan_index.next_loan_id,
+                amount,
+                duration,
+                max_rate_bps,
+                guaranteed,
+                ctx.requires_identity()?,
+            )]
+        }
+        Command::FundLoan {
+            borrower,
+            loan_id,
+            rate_bps,
+            mint,
+            rebate,
+            min_interest_bps,
+            prepayment_fee_bps,
+        } => {
+            let (escrow, _) = pda::escrow_address(&borrower, loan_id);
+            let rebate_policy = RebatePolicy {
+                enabled: rebate,
+                min_interest_bps,
+                prepayment_fee_bps,
+            };
+            vec![
+                create_associated_token_account_idempotent(&me, &escrow, &mint, &token::ID),
+                instructions::fund_loan(
+                    &me,
+                    &borrower,
+                    loan_id,
+                    &get_associated_token_address(&me, &mint),
+                    &pda::escrow_token_address(&borrower, loan_id, &mint),
+                    rate_bps,
+                    rebate_policy,
+                ),
+            ]
+        }
+        Command::WithdrawLoan { loan_id, mint } => vec![
+            create_associated_token_account_idempotent(&me, &me, &mint, &token::ID),
+            instructions::withdraw_loan(
+                &me,
+                loan_id,
+                &get_associated_token_address(&me, &mint),
+                &pda::escrow_token_address(&me, loan_id, &mint),
+            ),
+        ],
+        Command::RepayLoan {
+            loan_id,
+            mint,
+            treasury,
+        } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&me, loan_id).0)?;
+            let (holder, position) = ctx.position(&loan)?;
+            vec![
+                create_associated_token_account_idempotent(&me, &holder, &mint, &token::ID),
+                instructions::repay_loan(
+                    &loan,
+                    &get_associated_token_address(&me, &mint),
+                    &get_associated_token_address(&holder, &mint),
+                    position.as_ref(),
+                    &treasury,
+                ),
+            ]
+        }
+        Command::ExtendLoan {
+            loan_id,
+            seconds,
+            rate_bps,
+            lender_keypair,
+        } => {
+            let lender = load_keypair(&lender_keypair)?;
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&me, loan_id).0)?;
+            let (_, position) = ctx.position(&loan)?;
+            let ix = instructions::request_extension(
+                &me,
+                &lender.pubkey(),
+                loan_id,
+                position.as_ref(),
+                seconds,
+                rate_bps,
+            );
+            cosigners.push(lender);
+            vec![ix]
+        }
+        Command::Refinance {
+            borrower,
+            loan_id,
+            rate_bps,
+            mint,
+            treasury,
+            borrower_keypair,
+            rebate,
+            min_interest_bps,
+            prepayment_fee_bps,
+        } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&borrower, loan_id).0)?;
+            let (holder, position) = ctx.position(&loan)?;
+            cosigners.push(load_keypair(&borrower_keypair)?);
+            let rebate_policy = RebatePolicy {
+                enabled: rebate,
+                min_interest_bps,
+                prepayment_fee_bps,
+            };
+            vec![
+                create_associated_token_account_idempotent(&me, &holder, &mint, &token::ID),
+                instructions::refinance(
+                    &loan,
+                    &mint,
+                    &me,
+                    &get_associated_token_address(&me, &mint),
+                    &get_associated_token_address(&holder, &mint),
+                    position.as_ref(),
+                    &treasury,
+                    rate_bps,
+                    rebate_policy,
+                ),
+            ]
+        }
+        Command::CancelLoan { loan_id } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&me, loan_id).0)?;
+            vec![instructions::cancel_loan_request(
+                &me,
+                loan_id,
+                loan.indexed,
+            )]
+        }
+        Command::ExpireRequest { borrower, loan_id } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&borrower, loan_id).0)?;
+            vec![instructions::expire_request(
+                &borrower,
+                loan_id,
+                loan.indexed,
+            )]
+        }
+        Command::ReclaimFunding {
+            borrower,
+            loan_id,
+            mint,
+        } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&borrower, loan_id).0)?;
+            let (_, position) = ctx.position(&loan)?;
+            vec![instructions::reclaim_funding(
+                &me,
+                &loan,
+                &get_associated_token_address(&me, &mint),
+                position.as_ref(),
+                &pda::escrow_token_address(&borrower, loan_id, &mint),
+            )]
+        }
+        Command::CloseLoan {
+            borrower,
+            loan_id,
+            mint,
+        } => {
+            let borrower = borrower.unwrap_or(me);
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&borrower, loan_id).0)?;
+            let escrow_payer = loan
+                .escrow_rent_payer()
+                .ok_or_else(|| anyhow!("loan {loan_id} has no lender"))?;
+            vec![instructions::close_loan(
+                &borrower,
+                loan_id,
+                &escrow_payer,
+                &pda::escrow_token_address(&borrower, loan_id, &mint),
+                loan.guarantee_backing > 0,
+            )]
+        }
+        Command::ReleaseGuarantee {
+            borrower,
+            loan_id,
+            mint,
+        } => {
+            let guarantee: Guarantee = ctx.fetch(&pda::guarantee_address(&borrower, loan_id).0)?;
+            let guarantor = guarantee.guarantor;
+            vec![
+                create_associated_token_account_idempotent(&me, &guarantor, &mint, &token::ID),
+                instructions::release_guarantee(
+                    &guarantor,
+                    &borrower,
+                    loan_id,
+                    &get_associated_token_address(&guarantor, &mint),
+                    &mint,
+                ),
+            ]
+        }
+        Command::ClaimGuarantee {
+            borrower,
+            loan_id,
+            mint,
+        } => {
+            let guarantee: Guarantee = ctx.fetch(&pda::guarantee_address(&borrower, loan_id).0)?;
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&borrower, loan_id).0)?;
+            let (holder, position) = ctx.position(&loan)?;
+            vec![
+                create_associated_token_account_idempotent(&me, &holder, &mint, &token::ID),
+                instructions::claim_guarantee(
+                    &loan,
+                    &guarantee.guarantor,
+                    &mint,
+                    &get_associated_token_address(&holder, &mint),
+                    position.as_ref(),
+                ),
+            ]
+        }
+        Command::MarkDefault { borrower, loan_id } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&borrower, loan_id).0)?;
+            vec![instructions::mark_default(&me, &loan)]
+        }
+        Command::Unfreeze { owner } => vec![instructions::unfreeze_reputation(&me, &owner)],
+        Command::Vouch { vouchee, stake } => vec![instructions::issue_vouch(&me, &vouchee, stake)],
+        Command::RevokeVouch { vouchee } => vec![instructions::revoke_vouch(&me, &vouchee)],
+        Command::SlashVouch { voucher, vouchee } => {
+            vec![instructions::slash_vouch(&voucher, &vouchee)]
+        }
+        Command::OpenCreditLine {
+            borrower,
+            limit,
+            rate_bps,
+            mint,
+            borrower_keypair,
+        } => {
+            cosigners.push(load_keypair(&borrower_keypair)?);
+            vec![instructions::open_credit_line(
+                &me,
+                &borrower,
+                &get_associated_token_address(&me, &mint),
+                &mint,
+                ctx.requires_identity()?,
+                limit,
+                rate_bps,
+            )]
+        }
+        Command::DrawCreditLine { lender, amount } => {
+            let credit_line: CreditLine = ctx.fetch(&pda::credit_line_address(&lender, &me).0)?;
+            let mint = credit_line.mint;
+            vec![
+                create_associated_token_account_idempotent(&me, &me, &mint, &token::ID),
+                instructions::draw_credit_line(
+                    &credit_line,
+                    &get_associated_token_address(&me, &mint),
+                    ctx.requires_identity()?,
+                    amount,
+                ),
+            ]
+        }
+        Command::RepayCreditLine {
+            lender,
+            amount,
+            treasury,
+        } => {
+            let credit_line: CreditLine = ctx.fetch(&pda::credit_line_address(&lender, &me).0)?;
+            let mint = credit_line.mint;
+            vec![
+                create_associated_token_account_idempotent(&me, &lender, &mint, &token::ID),
+                instructions::repay_credit_line(
+                    &credit_line,
+                    &get_associated_token_address(&me, &mint),
+                    &get_associated_token_address(&lender, &mint),
+                    &treasury,
+                    amount.unwrap_or(u64::MAX),
+                ),
+            ]
+        }
+        Command::CloseStatement { lender, borrower } => {
+            vec![instructions::close_statement(&lender, &borrower)]
+        }
+        Command::CloseCreditLine { borrower } => {
+            let credit_line: CreditLine = ctx.fetch(&pda::credit_line_address(&me, &borrower).0)?;
+            vec![instructions::close_credit_line(
+                &credit_line,
+                &get_associated_token_address(&me, &credit_line.mint),
+            )]
+        }
+        Command::DelegateCredit {
+            borrower,
+            allowance,
+            rate_bps,
+            duration,
+            expires_at,
+            mint,
+        } => {
+            let token_account = get_associated_token_address(&me, &mint);
+            let (delegation, _) = pda::credit_delegation_address(&me, &borrower);
+            ctx.warn_replaced_delegate(&token_account, &delegation)?;
+            vec![
+                instructions::delegate_credit(
+                    &me, &borrower, &mint, allowance, rate_bps, duration, expires_at,
+                ),
+                instructions::approve_delegate(&me, &token_account, &delegation, allowance),
+            ]
+        }
+        Command::RevokeCreditDelegation { borrower } => {
+            vec![instructions::revoke_credit_delegation(&me, &borrower)]
+        }
+        Command::DrawDelegatedCredit { lender, amount } => {
+            let delegation: CreditDelegation =
+                ctx.fetch(&pda::credit_delegation_address(&lender, &me).0)?;
+            let loan_index: BorrowerLoanIndex = ctx
+                .fetch(&pda::loan_index_address(&me).0)
+                .context("run `sollend create-loan-index` first")?;
+            println!("Loan ID: {}", loan_index.next_loan_id);
+            let mint = delegation.mint;
+            vec![
+                create_associated_token_account_idempotent(&me, &me, &mint, &token::ID),
+                instructions::draw_delegated_credit(
+                    &delegation,
+                    loan_index.next_loan_id,
+                    &get_associated_token_address(&me, &mint),
+                    &get_associated_token_address(&lender, &mint),
+                    ctx.requires_identity()?,
+                    amount,
+                ),
+            ]
+        }
+        Command::EnableAutoRepay {
+            loan_id,
+            installments,
+            keeper_tip,
+            mint,
+        } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&me, loan_id).0)?;
+            let scheduled_total =
+                quote::repayment_quote(loan.amount, loan.actual_interest_rate_bps, 0).total;
+            let tips = keeper_tip.saturating_mul(installments.into());
+            let token_account = get_associated_token_address(&me, &mint);
+            let (schedule, _) = pda::auto_repay_address(&me, loan_id);
+            ctx.warn_replaced_delegate(&token_account, &schedule)?;
+            vec![
+                instructions::enable_auto_repay(
+                    &me,
+                    loan_id,
+                    &token_account,
+                    &mint,
+                    installments,
+                    keeper_tip,
+                ),
+                instructions::approve_delegate(
+                    &me,
+                    &token_account,
+                    &schedule,
+                    scheduled_total.saturating_add(tips),
+                ),
+            ]
+        }
+        Command::ExecuteAutoRepay {
+            borrower,
+            loan_id,
+            treasury,
+        } => {
+            let loan: LoanAccount = ctx.fetch(&pda::loan_address(&borrower, loan_id).0)?;
+            let auto_repay: AutoRepay =
+                ctx.fetch(&pda::auto_repay_address(&borrower, loan_id).0)?;
+            let (holder, position) = ctx.position(&loan)?;
+            let mint = auto_repay.mint;
+            vec![
+                create_associated_token_account_idempotent(&me, &me, &mint, &token::ID),
+                create_associated_token_account_idempotent(&me, &holder, &mint, &token::ID),
+                instructions::execute_auto_repay(
+                    &me,
+                    &loan,
+                    &auto_repay,
+                    &get_associated_token_address(&me, &mint),
+                    &get_associated_token_address(&holder, &mint),
+                    position.as_ref(),
+                    &treasury,
+                ),
+            ]
+        }
+        Command::DisableAutoRepay { loan_id } => {
+            let auto_repay: AutoRepay = ctx.fetch(&pda::auto_repay_address(&me, loan_id).0)?;
+            vec![instructions::disable_auto_repay(
+                &auto_repay,
+                &auto_repay.borrower_token_account,
+            )]
+        }
+        Command::Show(_) | Command::Quote { .. } => unreachable!("read commands return early"),
+    };
+    ctx.send(&signer, &cosigners, &instructions)
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("Error: {err:#}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,79 @@
+//! Decoders for raw account data fetched over RPC.
+
+use anchor_lang::{AccountDeserialize, Discriminator, Result};
+use sollend_micro_protocol::{
+    EscrowAccount, LoanAccount, LoanAudit, ProtocolConfig, ReputationAccount,
+};
+
+/// Any account owned by the program, identified by its discriminator
+pub enum SollendAccount {
+    Config(ProtocolConfig),
+    Reputation(ReputationAccount),
+    Loan(LoanAccount),
+    Escrow(EscrowAccount),
+    Audit(LoanAudit),
+}
+
+/// Decodes a single account type, checking its discriminator
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_config(data: &[u8]) -> Result<ProtocolConfig> {
+    decode(data)
+}
+
+pub fn decode_reputation(data: &[u8]) -> Result<ReputationAccount> {
+    decode(data)
+}
+
+pub fn decode_loan(data: &[u8]) -> Result<LoanAccount> {
+    decode(data)
+}
+
+pub fn decode_escrow(data: &[u8]) -> Result<EscrowAccount> {
+    decode(data)
+}
+
+/// Decodes whichever program account `data` holds
+pub fn decode_any(data: &[u8]) -> Result<SollendAccount> {
+    let discriminator = data.get(..8).unwrap_or_default();
+    if discriminator == ProtocolConfig::DISCRIMINATOR {
+        decode(data).map(SollendAccount::Config)
+    } else if discriminator == ReputationAccount::DISCRIMINATOR {
+        decode(data).map(SollendAccount::Reputation)
+    } else if discriminator == LoanAccount::DISCRIMINATOR {
+        decode(data).map(SollendAccount::Loan)
+    } else if discriminator == EscrowAccount::DISCRIMINATOR {
+        decode(data).map(SollendAccount::Escrow)
+    } else if discriminator == LoanAudit::DISCRIMINATOR {
+        decode(data).map(SollendAccount::Audit)
+    } else {
+        Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::AccountSerialize;
+
+    #[test]
+    fn decode_any_dispatches_on_discriminator() {
+        let escrow = EscrowAccount {
+            loan_id: 9,
+            borrower: Pubkey::new_unique(),
+            bump: 255,
+        };
+        let mut data = Vec::new();
+        escrow.try_serialize(&mut data).unwrap();
+
+        match decode_any(&data) {
+            Ok(SollendAccount::Escrow(decoded)) => assert_eq!(decoded.loan_id, 9),
+            _ => panic!("expected an escrow account"),
+        }
+        assert!(decode_loan(&data).is_err());
+        assert!(decode_any(&data[..4]).is_err());
+    }
+}
@@ -0,0 +1,1569 @@
+//! Typed instruction builders, one per program instruction.
+//!
+//! Builders derive every PDA themselves; callers only supply wallets, token
+//! accounts and instruction arguments.
+
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token;
+use sollend_micro_protocol::{
+    accounts, instruction, AutoRepay, CreditDelegation, CreditLine, LoanAccount, RebatePolicy,
+    Role, ID,
+};
+
+use crate::pda::{
+    accepted_bond_mint_address, audit_address, auto_repay_address, bond_vault_address,
+    config_address, credit_delegation_address, credit_line_address, delegate_authority_address,
+    escrow_address, escrow_token_address, event_authority_address, exposure_ledger_address,
+    guarantee_address, identity_address, lender_profile_address, line_vault_address, loan_address,
+    loan_index_address, pair_exposure_address, position_mint_address, repay_vault_address,
+    reputation_address, reputation_attestation_address, vouch_address,
+};
+
+/// The loan index account for instructions that settle a loan; only loans
+/// created through the index (`LoanAccount::indexed`) are tracked in it
+fn settled_loan_index(borrower: &Pubkey, indexed: bool) -> Option<Pubkey> {
+    indexed.then(|| loan_index_address(borrower).0)
+}
+
+/// The recorded lender's profile for instructions that take a loan out of their
+/// portfolio; loans funded before lender profiles existed were never recorded
+fn settled_lender_profile(loan: &LoanAccount) -> Option<Pubkey> {
+    loan.lender_profiled
+        .then(|| lender_profile_address(&loan.lender.unwrap_or_default()).0)
+}
+
+/// The recorded lender's pair exposure to the borrower, for instructions that move
+/// a loan off the concentration limits; loans funded before the limits were never counted
+fn settled_pair_exposure(loan: &LoanAccount) -> Option<Pubkey> {
+    loan.concentration_tracked
+        .then(|| pair_exposure_address(&loan.lender.unwrap_or_default(), &loan.borrower).0)
+}
+
+/// The exposure ledger, passed alongside `settled_pair_exposure`
+fn settled_exposure_ledger(loan: &LoanAccount) -> Option<Pubkey> {
+    loan.concentration_tracked
+        .then(|| exposure_ledger_address().0)
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub fn initialize_config(
+    authority: &Pubkey,
+    oracle_authority: Pubkey,
+    protocol_fee_bps: u16,
+) -> Instruction {
+    build(
+        accounts::InitializeConfig {
+            config: config_address().0,
+            authority: *authority,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::InitializeConfig {
+            oracle_authority,
+            protocol_fee_bps,
+        },
+    )
+}
+
+/// `signer` must hold the fee manager role to change the fee and the pauser role to change pause flags
+pub fn update_config(
+    signer: &Pubkey,
+    protocol_fee_bps: Option<u16>,
+    pause_flags: Option<u8>,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            config: config_address().0,
+            signer: *signer,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::UpdateConfig {
+            protocol_fee_bps,
+            pause_flags,
+        },
+    )
+}
+
+pub fn update_risk_params(
+    risk_manager: &Pubkey,
+    tier_max_borrow: Option<[u64; 4]>,
+    on_time_payment_bonus: Option<i16>,
+    late_payment_penalty: Option<i16>,
+    default_penalty: Option<i16>,
+    unwithdrawn_penalty: Option<i16>,
+) -> Instruction {
+    build(
+        accounts::UpdateRiskParams {
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::UpdateRiskParams {
+            tier_max_borrow,
+            on_time_payment_bonus,
+            late_payment_penalty,
+            default_penalty,
+            unwithdrawn_penalty,
+        },
+    )
+}
+
+pub fn update_loan_windows(
+    risk_manager: &Pubkey,
+    withdrawal_window_seconds: Option<i64>,
+    request_ttl_seconds: Option<i64>,
+) -> Instruction {
+    build(
+        accounts::UpdateRiskParams {
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::UpdateLoanWindows {
+            withdrawal_window_seconds,
+            request_ttl_seconds,
+        },
+    )
+}
+
+/// Creates the exposure ledger with the default concentration limits
+pub fn initialize_exposure_ledger(risk_manager: &Pubkey) -> Instruction {
+    build(
+        accounts::InitializeExposureLedger {
+            exposure_ledger: exposure_ledger_address().0,
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::InitializeExposureLedger {},
+    )
+}
+
+pub fn update_concentration_limits(
+    risk_manager: &Pubkey,
+    tier_debt_ceiling: Option<[u64; 4]>,
+    max_total_outstanding: Option<u64>,
+    max_pair_exposure: Option<u64>,
+) -> Instruction {
+    build(
+        accounts::UpdateConcentrationLimits {
+            exposure_ledger: exposure_ledger_address().0,
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::UpdateConcentrationLimits {
+            tier_debt_ceiling,
+            max_total_outstanding,
+            max_pair_exposure,
+        },
+    )
+}
+
+/// The risk manager pays the rent for the whitelist entry
+pub fn accept_bond_mint(risk_manager: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptBondMint {
+            accepted_bond_mint: accepted_bond_mint_address(mint).0,
+            mint: *mint,
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::AcceptBondMint {},
+    )
+}
+
+pub fn remove_bond_mint(risk_manager: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        accounts::RemoveBondMint {
+            accepted_bond_mint: accepted_bond_mint_address(mint).0,
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::RemoveBondMint {},
+    )
+}
+
+pub fn update_exposure_limits(
+    risk_manager: &Pubkey,
+    tier_max_exposure: Option<[u64; 4]>,
+    max_active_loans: Option<u32>,
+) -> Instruction {
+    build(
+        accounts::UpdateRiskParams {
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::UpdateExposureLimits {
+            tier_max_exposure,
+            max_active_loans,
+        },
+    )
+}
+
+pub fn update_vouch_limits(
+    risk_manager: &Pubkey,
+    max_vouches_given: Option<u8>,
+    max_vouches_received: Option<u8>,
+) -> Instruction {
+    build(
+        accounts::UpdateRiskParams {
+            config: config_address().0,
+            risk_manager: *risk_manager,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::UpdateVouchLimits {
+            max_vouches_given,
+            max_vouches_received,
+        },
+    )
+}
+
+fn manage_role_accounts(authority: &Pubkey) -> accounts::ManageRole {
+    accounts::ManageRole {
+        config: config_address().0,
+        authority: *authority,
+        event_authority: event_authority_address().0,
+        program: ID,
+    }
+}
+
+pub fn set_role(authority: &Pubkey, role: Role, holder: Pubkey) -> Instruction {
+    build(
+        manage_role_accounts(authority),
+        instruction::SetRole { role, holder },
+    )
+}
+
+pub fn revoke_role(authority: &Pubkey, role: Role) -> Instruction {
+    build(
+        manage_role_accounts(authority),
+        instruction::RevokeRole { role },
+    )
+}
+
+/// `expires_at` of 0 never expires; attesting an already attested wallet renews it
+pub fn attest_identity(attester: &Pubkey, owner: &Pubkey, expires_at: i64) -> Instruction {
+    build(
+        accounts::AttestIdentity {
+            identity_attestation: identity_address(owner).0,
+            config: config_address().0,
+            attester: *attester,
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::AttestIdentity { expires_at },
+    )
+}
+
+pub fn revoke_identity(attester: &Pubkey, owner: &Pubkey) -> Instruction {
+    build(
+        accounts::RevokeIdentity {
+            identity_attestation: identity_address(owner).0,
+            config: config_address().0,
+            attester: *attester,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::RevokeIdentity {},
+    )
+}
+
+/// The identity attestation for wallets that must pass the protocol's identity
+/// gate (`ProtocolConfig::requires_identity`)
+fn identity_attestation(owner: &Pubkey, attested: bool) -> Option<Pubkey> {
+    attested.then(|| identity_address(owner).0)
+}
+
+/// Pass `attested = true` while the protocol requires an identity attestation
+pub fn create_reputation(owner: &Pubkey, attested: bool) -> Instruction {
+    build(
+        accounts::CreateReputation {
+            reputation: reputation_address(owner).0,
+            identity_attestation: identity_attestation(owner, attested),
+            config: config_address().0,
+            owner: *owner,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CreateReputation {},
+    )
+}
+
+/// `next_loan_id` starts the counter; borrowers who already created loans by
+/// hand pass an ID above any they used, and the program rejects a taken one
+pub fn create_loan_index(borrower: &Pubkey, next_loan_id: u64) -> Instruction {
+    build(
+        accounts::CreateLoanIndex {
+            loan_index: loan_index_address(borrower).0,
+            next_loan: loan_address(borrower, next_loan_id).0,
+            borrower: *borrower,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CreateLoanIndex { next_loan_id },
+    )
+}
+
+/// `loan_id` must be the loan index's current `next_loan_id`, which the
+/// program assigns to the new loan
+pub fn create_lender_profile(lender: &Pubkey) -> Instruction {
+    build(
+        accounts::CreateLenderProfile {
+            lender_profile: lender_profile_address(lender).0,
+            lender: *lender,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CreateLenderProfile {},
+    )
+}
+
+/// Needs both the guarantor's and the borrower's signatures. The guarantee is
+/// pledged against the borrower's next loan ID, which `loan_id` must be, and
+/// `bond_mint` must be accepted by the risk manager
+pub fn guarantee_loan(
+    guarantor: &Pubkey,
+    borrower: &Pubkey,
+    loan_id: u64,
+    guarantor_token_account: &Pubkey,
+    bond_mint: &Pubkey,
+    bond: u64,
+) -> Instruction {
+    build(
+        accounts::GuaranteeLoan {
+            guarantee: guarantee_address(borrower, loan_id).0,
+            loan_index: loan_index_address(borrower).0,
+            guarantor_reputation: reputation_address(guarantor).0,
+            config: config_address().0,
+            borrower: *borrower,
+            guarantor: *guarantor,
+            guarantor_token_account: *guarantor_token_account,
+            bond_mint: *bond_mint,
+            accepted_bond_mint: accepted_bond_mint_address(bond_mint).0,
+            bond_vault: bond_vault_address(borrower, loan_id, bond_mint),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::GuaranteeLoan { bond },
+    )
+}
+
+/// Pass `guaranteed = true` to make the request with the guarantee pledged for `loan_id`,
+/// and `attested = true` while the protocol requires an identity attestation
+pub fn create_loan_request(
+    borrower: &Pubkey,
+    loan_id: u64,
+    amount: u64,
+    duration_seconds: i64,
+    max_interest_rate_bps: u16,
+    guaranteed: bool,
+    attested: bool,
+) -> Instruction {
+    build(
+        accounts::CreateLoanRequest {
+            loan_index: loan_index_address(borrower).0,
+            loan: loan_address(borrower, loan_id).0,
+            borrower_reputation: reputation_address(borrower).0,
+            guarantee: guaranteed.then(|| guarantee_address(borrower, loan_id).0),
+            identity_attestation: identity_attestation(borrower, attested),
+            config: config_address().0,
+            borrower: *borrower,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CreateLoanRequest {
+            amount,
+            duration_seconds,
+            max_interest_rate_bps,
+        },
+    )
+}
+
+pub fn fund_loan(
+    lender: &Pubkey,
+    borrower: &Pubkey,
+    loan_id: u64,
+    lender_token_account: &Pubkey,
+    escrow_token_account: &Pubkey,
+    interest_rate_bps: u16,
+    rebate_policy: RebatePolicy,
+) -> Instruction {
+    let (position_mint, _) = position_mint_address(borrower, loan_id, 0);
+    build(
+        accounts::FundLoan {
+            loan: loan_address(borrower, loan_id).0,
+            escrow: escrow_address(borrower, loan_id).0,
+            borrower_reputation: reputation_address(borrower).0,
+            config: config_address().0,
+            borrower: *borrower,
+            lender: *lender,
+            lender_profile: lender_profile_address(lender).0,
+            exposure_ledger: exposure_ledger_address().0,
+            pair_exposure: pair_exposure_address(lender, borrower).0,
+            lender_token_account: *lender_token_account,
+            escrow_token_account: *escrow_token_account,
+            position_mint,
+            lender_position_account: get_associated_token_address(lender, &position_mint),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::FundLoan {
+            interest_rate_bps,
+            rebate_policy,
+        },
+    )
+}
+
+pub fn withdraw_loan(
+    borrower: &Pubkey,
+    loan_id: u64,
+    borrower_token_account: &Pubkey,
+    escrow_token_account: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::WithdrawLoan {
+            loan: loan_address(borrower, loan_id).0,
+            escrow: escrow_address(borrower, loan_id).0,
+            config: config_address().0,
+            borrower: *borrower,
+            borrower_token_account: *borrower_token_account,
+            escrow_token_account: *escrow_token_account,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::WithdrawLoan {},
+    )
+}
+
+/// `lender_token_account` belongs to whoever holds the lender position, and
+/// `position_token_account` is the account holding it (`None` for loans funded
+/// before positions existed)
+pub fn repay_loan(
+    loan: &LoanAccount,
+    borrower_token_account: &Pubkey,
+    lender_token_account: &Pubkey,
+    position_token_account: Option<&Pubkey>,
+    protocol_treasury: &Pubkey,
+) -> Instruction {
+    let borrower = &loan.borrower;
+    build(
+        accounts::RepayLoan {
+            loan: loan_address(borrower, loan.loan_id).0,
+            borrower_reputation: reputation_address(borrower).0,
+            loan_index: settled_loan_index(borrower, loan.indexed),
+            lender_profile: settled_lender_profile(loan),
+            exposure_ledger: settled_exposure_ledger(loan),
+            pair_exposure: settled_pair_exposure(loan),
+            config: config_address().0,
+            borrower: *borrower,
+            borrower_token_account: *borrower_token_account,
+            lender_token_account: *lender_token_account,
+            position_token_account: position_token_account.copied(),
+            protocol_treasury: *protocol_treasury,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::RepayLoan {},
+    )
+}
+
+pub fn mark_default(oracle_authority: &Pubkey, loan: &LoanAccount) -> Instruction {
+    let borrower = &loan.borrower;
+    build(
+        accounts::MarkDefault {
+            loan: loan_address(borrower, loan.loan_id).0,
+            borrower_reputation: reputation_address(borrower).0,
+            loan_index: settled_loan_index(borrower, loan.indexed),
+            lender_profile: settled_lender_profile(loan),
+            exposure_ledger: settled_exposure_ledger(loan),
+            pair_exposure: settled_pair_exposure(loan),
+            config: config_address().0,
+            oracle_authority: *oracle_authority,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::MarkDefault {},
+    )
+}
+
+pub fn unfreeze_reputation(reputation_steward: &Pubkey, owner: &Pubkey) -> Instruction {
+    build(
+        accounts::UnfreezeReputation {
+            reputation: reputation_address(owner).0,
+            config: config_address().0,
+            reputation_steward: *reputation_steward,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::UnfreezeReputation {},
+    )
+}
+
+pub fn issue_vouch(voucher: &Pubkey, vouchee: &Pubkey, stake: u16) -> Instruction {
+    build(
+        accounts::IssueVouch {
+            vouch: vouch_address(voucher, vouchee).0,
+            voucher_reputation: reputation_address(voucher).0,
+            vouchee_reputation: reputation_address(vouchee).0,
+            config: config_address().0,
+            voucher: *voucher,
+            vouchee: *vouchee,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::IssueVouch { stake },
+    )
+}
+
+pub fn revoke_vouch(voucher: &Pubkey, vouchee: &Pubkey) -> Instruction {
+    build(
+        accounts::RevokeVouch {
+            vouch: vouch_address(voucher, vouchee).0,
+            voucher_reputation: reputation_address(voucher).0,
+            vouchee_reputation: reputation_address(vouchee).0,
+            vouchee_loan_index: loan_index_address(vouchee).0,
+            voucher: *voucher,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::RevokeVouch {},
+    )
+}
+
+/// Anyone can send this once the vouchee has defaulted
+pub fn slash_vouch(voucher: &Pubkey, vouchee: &Pubkey) -> Instruction {
+    build(
+        accounts::SlashVouch {
+            vouch: vouch_address(voucher, vouchee).0,
+            voucher_reputation: reputation_address(voucher).0,
+            vouchee_reputation: reputation_address(vouchee).0,
+            voucher: *voucher,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::SlashVouch {},
+    )
+}
+
+pub fn cancel_loan_request(borrower: &Pubkey, loan_id: u64, indexed: bool) -> Instruction {
+    build(
+        accounts::CancelLoanRequest {
+            loan: loan_address(borrower, loan_id).0,
+            loan_index: settled_loan_index(borrower, indexed),
+            borrower: *borrower,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CancelLoanRequest {},
+    )
+}
+
+/// Needs both the borrower's and the position holder's signatures
+pub fn request_extension(
+    borrower: &Pubkey,
+    lender: &Pubkey,
+    loan_id: u64,
+    position_token_account: Option<&Pubkey>,
+    extension_seconds: i64,
+    extension_interest_rate_bps: u16,
+) -> Instruction {
+    build(
+        accounts::RequestExtension {
+            loan: loan_address(borrower, loan_id).0,
+            borrower: *borrower,
+            lender: *lender,
+            position_token_account: position_token_account.copied(),
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::RequestExtension {
+            extension_seconds,
+            extension_interest_rate_bps,
+        },
+    )
+}
+
+/// Needs both the borrower's and the new lender's signatures. Takes the loan
+/// itself since the new position mint depends on its `refinance_count`; the
+/// payoff must be in `mint`, the mint the loan was funded in
+#[allow(clippy::too_many_arguments)]
+pub fn refinance(
+    loan: &LoanAccount,
+    mint: &Pubkey,
+    new_lender: &Pubkey,
+    new_lender_token_account: &Pubkey,
+    lender_token_account: &Pubkey,
+    position_token_account: Option<&Pubkey>,
+    protocol_treasury: &Pubkey,
+    interest_rate_bps: u16,
+    rebate_policy: RebatePolicy,
+) -> Instruction {
+    let (borrower, loan_id) = (&loan.borrower, loan.loan_id);
+    let (new_position_mint, _) = position_mint_address(borrower, loan_id, loan.refinance_count + 1);
+    build(
+        accounts::Refinance {
+            loan: loan_address(borrower, loan_id).0,
+            escrow: escrow_address(borrower, loan_id).0,
+            escrow_token_account: escrow_token_address(borrower, loan_id, mint),
+            borrower_reputation: reputation_address(borrower).0,
+            config: config_address().0,
+            borrower: *borrower,
+            new_lender: *new_lender,
+            new_lender_profile: lender_profile_address(new_lender).0,
+            exposure_ledger: exposure_ledger_address().0,
+            new_pair_exposure: pair_exposure_address(new_lender, borrower).0,
+            new_lender_token_account: *new_lender_token_account,
+            lender_token_account: *lender_token_account,
+            position_token_account: position_token_account.copied(),
+            lender_profile: settled_lender_profile(loan),
+            pair_exposure: settled_pair_exposure(loan),
+            new_position_mint,
+            new_lender_position_account: get_associated_token_address(
+                new_lender,
+                &new_position_mint,
+            ),
+            protocol_treasury: *protocol_treasury,
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::Refinance {
+            interest_rate_bps,
+            rebate_policy,
+        },
+    )
+}
+
+pub fn expire_request(borrower: &Pubkey, loan_id: u64, indexed: bool) -> Instruction {
+    build(
+        accounts::ExpireRequest {
+            loan: loan_address(borrower, loan_id).0,
+            loan_index: settled_loan_index(borrower, indexed),
+            config: config_address().0,
+            borrower: *borrower,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::ExpireRequest {},
+    )
+}
+
+pub fn reclaim_funding(
+    lender: &Pubkey,
+    loan: &LoanAccount,
+    lender_token_account: &Pubkey,
+    position_token_account: Option<&Pubkey>,
+    escrow_token_account: &Pubkey,
+) -> Instruction {
+    let (borrower, loan_id) = (&loan.borrower, loan.loan_id);
+    build(
+        accounts::ReclaimFunding {
+            loan: loan_address(borrower, loan_id).0,
+            escrow: escrow_address(borrower, loan_id).0,
+            borrower_reputation: reputation_address(borrower).0,
+            loan_index: settled_loan_index(borrower, loan.indexed),
+            lender_profile: settled_lender_profile(loan),
+            exposure_ledger: settled_exposure_ledger(loan),
+            pair_exposure: settled_pair_exposure(loan),
+            config: config_address().0,
+            lender: *lender,
+            lender_token_account: *lender_token_account,
+            position_token_account: position_token_account.copied(),
+            escrow_token_account: *escrow_token_account,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::ReclaimFunding {},
+    )
+}
+
+/// Pass `guaranteed = true` for loans with a guarantor (`LoanAccount::guarantee_backing`
+/// above zero), whose guarantee must already be released or claimed
+/// `escrow_payer` is the loan's `escrow_rent_payer()`, which gets the escrow's rent back
+pub fn close_loan(
+    borrower: &Pubkey,
+    loan_id: u64,
+    escrow_payer: &Pubkey,
+    escrow_token_account: &Pubkey,
+    guaranteed: bool,
+) -> Instruction {
+    let escrow = escrow_address(borrower, loan_id).0;
+    build(
+        accounts::CloseLoan {
+            loan: loan_address(borrower, loan_id).0,
+            escrow,
+            escrow_token_account: *escrow_token_account,
+            borrower_reputation: reputation_address(borrower).0,
+            guarantee: guaranteed.then(|| guarantee_address(borrower, loan_id).0),
+            config: config_address().0,
+            borrower: *borrower,
+            escrow_payer: *escrow_payer,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CloseLoan {},
+    )
+}
+
+/// Anyone can send this; the bond goes back to `guarantor_token_account`
+pub fn release_guarantee(
+    guarantor: &Pubkey,
+    borrower: &Pubkey,
+    loan_id: u64,
+    guarantor_token_account: &Pubkey,
+    bond_mint: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::ReleaseGuarantee {
+            guarantee: guarantee_address(borrower, loan_id).0,
+            loan: loan_address(borrower, loan_id).0,
+            bond_vault: bond_vault_address(borrower, loan_id, bond_mint),
+            guarantor_token_account: *guarantor_token_account,
+            guarantor: *guarantor,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::ReleaseGuarantee {},
+    )
+}
+
+/// Anyone can send this once the loan has defaulted. `holder_token_account`
+/// belongs to whoever holds the lender position, as in `repay_loan`
+pub fn claim_guarantee(
+    loan: &LoanAccount,
+    guarantor: &Pubkey,
+    bond_mint: &Pubkey,
+    holder_token_account: &Pubkey,
+    position_token_account: Option<&Pubkey>,
+) -> Instruction {
+    let (borrower, loan_id) = (&loan.borrower, loan.loan_id);
+    build(
+        accounts::ClaimGuarantee {
+            guarantee: guarantee_address(borrower, loan_id).0,
+            loan: loan_address(borrower, loan_id).0,
+            guarantor_reputation: reputation_address(guarantor).0,
+            bond_vault: bond_vault_address(borrower, loan_id, bond_mint),
+            holder_token_account: *holder_token_account,
+            position_token_account: position_token_account.copied(),
+            guarantor: *guarantor,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::ClaimGuarantee {},
+    )
+}
+
+/// Lender and borrower both sign; the lender funds the limit and pays the rent.
+/// Pass `attested = true` while the protocol requires an identity attestation
+pub fn open_credit_line(
+    lender: &Pubkey,
+    borrower: &Pubkey,
+    lender_token_account: &Pubkey,
+    mint: &Pubkey,
+    attested: bool,
+    limit: u64,
+    interest_rate_bps: u16,
+) -> Instruction {
+    build(
+        accounts::OpenCreditLine {
+            credit_line: credit_line_address(lender, borrower).0,
+            borrower_reputation: reputation_address(borrower).0,
+            identity_attestation: identity_attestation(borrower, attested),
+            config: config_address().0,
+            borrower: *borrower,
+            lender: *lender,
+            lender_token_account: *lender_token_account,
+            mint: *mint,
+            line_vault: line_vault_address(lender, borrower, mint),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::OpenCreditLine {
+            limit,
+            interest_rate_bps,
+        },
+    )
+}
+
+/// Pass `attested = true` while the protocol requires an identity attestation; the
+/// borrower pays the rent for the lender pair's exposure account on a first draw
+pub fn draw_credit_line(
+    credit_line: &CreditLine,
+    borrower_token_account: &Pubkey,
+    attested: bool,
+    amount: u64,
+) -> Instruction {
+    let (lender, borrower) = (&credit_line.lender, &credit_line.borrower);
+    build(
+        accounts::DrawCreditLine {
+            credit_line: credit_line_address(lender, borrower).0,
+            borrower_reputation: reputation_address(borrower).0,
+            identity_attestation: identity_attestation(borrower, attested),
+            config: config_address().0,
+            exposure_ledger: exposure_ledger_address().0,
+            pair_exposure: pair_exposure_address(lender, borrower).0,
+            borrower: *borrower,
+            borrower_token_account: *borrower_token_account,
+            line_vault: line_vault_address(lender, borrower, &credit_line.mint),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::DrawCreditLine { amount },
+    )
+}
+
+/// `lender_token_account` must belong to the line's lender; amounts above what
+/// is owed are capped, so `u64::MAX` pays the line off
+pub fn repay_credit_line(
+    credit_line: &CreditLine,
+    borrower_token_account: &Pubkey,
+    lender_token_account: &Pubkey,
+    protocol_treasury: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (lender, borrower) = (&credit_line.lender, &credit_line.borrower);
+    build(
+        accounts::RepayCreditLine {
+            credit_line: credit_line_address(lender, borrower).0,
+            borrower_reputation: reputation_address(borrower).0,
+            config: config_address().0,
+            exposure_ledger: exposure_ledger_address().0,
+            pair_exposure: pair_exposure_address(lender, borrower).0,
+            borrower: *borrower,
+            borrower_token_account: *borrower_token_account,
+            line_vault: line_vault_address(lender, borrower, &credit_line.mint),
+            lender_token_account: *lender_token_account,
+            protocol_treasury: *protocol_treasury,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::RepayCreditLine { amount },
+    )
+}
+
+/// Anyone can send this once the line's statement period is over
+pub fn close_statement(lender: &Pubkey, borrower: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseStatement {
+            credit_line: credit_line_address(lender, borrower).0,
+            borrower_reputation: reputation_address(borrower).0,
+            config: config_address().0,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CloseStatement {},
+    )
+}
+
+pub fn close_credit_line(credit_line: &CreditLine, lender_token_account: &Pubkey) -> Instruction {
+    let (lender, borrower) = (&credit_line.lender, &credit_line.borrower);
+    build(
+        accounts::CloseCreditLine {
+            credit_line: credit_line_address(lender, borrower).0,
+            line_vault: line_vault_address(lender, borrower, &credit_line.mint),
+            lender_token_account: *lender_token_account,
+            lender: *lender,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::CloseCreditLine {},
+    )
+}
+
+/// The lender also needs to `approve_delegate` on the token account loans will
+/// be drawn from
+pub fn delegate_credit(
+    lender: &Pubkey,
+    borrower: &Pubkey,
+    mint: &Pubkey,
+    allowance: u64,
+    interest_rate_bps: u16,
+    duration_seconds: i64,
+    expires_at: i64,
+) -> Instruction {
+    build(
+        accounts::DelegateCredit {
+            credit_delegation: credit_delegation_address(lender, borrower).0,
+            config: config_address().0,
+            borrower: *borrower,
+            lender: *lender,
+            mint: *mint,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::DelegateCredit {
+            allowance,
+            interest_rate_bps,
+            duration_seconds,
+            expires_at,
+        },
+    )
+}
+
+pub fn revoke_credit_delegation(lender: &Pubkey, borrower: &Pubkey) -> Instruction {
+    build(
+        accounts::RevokeCreditDelegation {
+            credit_delegation: credit_delegation_address(lender, borrower).0,
+            lender: *lender,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::RevokeCreditDelegation {},
+    )
+}
+
+/// `loan_id` must be the borrower's `next_loan_id`; `lender_token_account` is
+/// the lender's account that approved the delegation's delegate
+pub fn draw_delegated_credit(
+    credit_delegation: &CreditDelegation,
+    loan_id: u64,
+    borrower_token_account: &Pubkey,
+    lender_token_account: &Pubkey,
+    attested: bool,
+    amount: u64,
+) -> Instruction {
+    let (lender, borrower) = (&credit_delegation.lender, &credit_delegation.borrower);
+    let (escrow, _) = escrow_address(borrower, loan_id);
+    let (delegation, _) = credit_delegation_address(lender, borrower);
+    build(
+        accounts::DrawDelegatedCredit {
+            credit_delegation: delegation,
+            loan_index: loan_index_address(borrower).0,
+            loan: loan_address(borrower, loan_id).0,
+            escrow,
+            escrow_token_account: get_associated_token_address(&escrow, &credit_delegation.mint),
+            borrower_reputation: reputation_address(borrower).0,
+            identity_attestation: identity_attestation(borrower, attested),
+            config: config_address().0,
+            lender_profile: lender_profile_address(lender).0,
+            exposure_ledger: exposure_ledger_address().0,
+            pair_exposure: pair_exposure_address(lender, borrower).0,
+            borrower: *borrower,
+            borrower_token_account: *borrower_token_account,
+            lender_token_account: *lender_token_account,
+            mint: credit_delegation.mint,
+            delegate_authority: delegate_authority_address(&delegation).0,
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::DrawDelegatedCredit { amount },
+    )
+}
+
+/// SPL `approve` letting the delegate PDA of `relationship` (a credit delegation
+/// or auto-repay schedule) move up to `amount` out of `token_account`. An account
+/// has one delegate, so this replaces any earlier approval
+pub fn approve_delegate(
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    relationship: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    token::spl_token::instruction::approve(
+        &token::ID,
+        token_account,
+        &delegate_authority_address(relationship).0,
+        owner,
+        &[],
+        amount,
+    )
+    .expect("approve has no multisig signers to check")
+}
+
+/// The borrower also needs to `approve_delegate` on `borrower_token_account`
+/// for the schedule's total plus a keeper tip per installment
+pub fn enable_auto_repay(
+    borrower: &Pubkey,
+    loan_id: u64,
+    borrower_token_account: &Pubkey,
+    mint: &Pubkey,
+    installments: u8,
+    keeper_tip: u64,
+) -> Instruction {
+    build(
+        accounts::EnableAutoRepay {
+            auto_repay: auto_repay_address(borrower, loan_id).0,
+            repay_vault: repay_vault_address(borrower, loan_id, mint),
+            loan: loan_address(borrower, loan_id).0,
+            borrower: *borrower,
+            borrower_token_account: *borrower_token_account,
+            mint: *mint,
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::EnableAutoRepay {
+            installments,
+            keeper_tip,
+        },
+    )
+}
+
+/// Signed by the keeper alone; the last installment also needs the lender's side
+/// of the loan, as for `repay_loan`
+pub fn execute_auto_repay(
+    keeper: &Pubkey,
+    loan: &LoanAccount,
+    auto_repay: &AutoRepay,
+    keeper_token_account: &Pubkey,
+    lender_token_account: &Pubkey,
+    position_token_account: Option<&Pubkey>,
+    protocol_treasury: &Pubkey,
+) -> Instruction {
+    let borrower = &loan.borrower;
+    let (schedule, _) = auto_repay_address(borrower, loan.loan_id);
+    build(
+        accounts::ExecuteAutoRepay {
+            auto_repay: schedule,
+            repay_vault: repay_vault_address(borrower, loan.loan_id, &auto_repay.mint),
+            loan: loan_address(borrower, loan.loan_id).0,
+            borrower_reputation: reputation_address(borrower).0,
+            loan_index: settled_loan_index(borrower, loan.indexed),
+            lender_profile: settled_lender_profile(loan),
+            exposure_ledger: settled_exposure_ledger(loan),
+            pair_exposure: settled_pair_exposure(loan),
+            config: config_address().0,
+            borrower: *borrower,
+            borrower_token_account: auto_repay.borrower_token_account,
+            lender_token_account: *lender_token_account,
+            position_token_account: position_token_account.copied(),
+            protocol_treasury: *protocol_treasury,
+            keeper: *keeper,
+            keeper_token_account: *keeper_token_account,
+            delegate_authority: delegate_authority_address(&schedule).0,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::ExecuteAutoRepay {},
+    )
+}
+
+pub fn disable_auto_repay(auto_repay: &AutoRepay, borrower_token_account: &Pubkey) -> Instruction {
+    let borrower = &auto_repay.borrower;
+    build(
+        accounts::DisableAutoRepay {
+            auto_repay: auto_repay_address(borrower, auto_repay.loan_id).0,
+            repay_vault: repay_vault_address(borrower, auto_repay.loan_id, &auto_repay.mint),
+            borrower: *borrower,
+            borrower_token_account: *borrower_token_account,
+            token_program: token::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::DisableAutoRepay {},
+    )
+}
+
+/// Meant to be simulated; the `LoanQuote` comes back as return data. Pass
+/// `reputation_exists = false` to quote a borrower who has not yet created
+/// their reputation account, and `attested = true` when the borrower has an
+/// identity attestation.
+pub fn quote_loan(
+    borrower: &Pubkey,
+    reputation_exists: bool,
+    attested: bool,
+    amount: u64,
+    duration_seconds: i64,
+) -> Instruction {
+    build(
+        accounts::QuoteLoan {
+            borrower_reputation: reputation_exists.then(|| reputation_address(borrower).0),
+            config: config_address().0,
+            identity_attestation: identity_attestation(borrower, attested),
+            borrower: *borrower,
+        },
+        instruction::QuoteLoan {
+            amount,
+            duration_seconds,
+        },
+    )
+}
+
+/// Anyone can send this; `payer` covers the attestation's rent
+pub fn attest_reputation(payer: &Pubkey, owner: &Pubkey) -> Instruction {
+    build(
+        accounts::AttestReputation {
+            attestation: reputation_attestation_address(owner).0,
+            reputation: reputation_address(owner).0,
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::AttestReputation {},
+    )
+}
+
+/// Fails unless `borrower` is unfrozen and at `required_tier` or better; the tier
+/// comes back as return data. Other programs call it through the `cpi` feature
+pub fn check_credit_tier(borrower: &Pubkey, required_tier: u8) -> Instruction {
+    build(
+        accounts::CheckCreditTier {
+            reputation: reputation_address(borrower).0,
+            borrower: *borrower,
+        },
+        instruction::CheckCreditTier { required_tier },
+    )
+}
+
+/// The auditor must be the protocol owner or the risk manager
+pub fn start_audit(auditor: &Pubkey) -> Instruction {
+    build(
+        accounts::StartAudit {
+            audit: audit_address(auditor).0,
+            config: config_address().0,
+            auditor: *auditor,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::StartAudit {},
+    )
+}
+
+/// `loans` are sorted here, since the program requires strictly ascending keys
+pub fn audit_loans(auditor: &Pubkey, loans: &[Pubkey]) -> Instruction {
+    let mut ix = build(
+        accounts::AuditLoans {
+            audit: audit_address(auditor).0,
+            auditor: *auditor,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::AuditLoans {},
+    );
+    let mut loans = loans.to_vec();
+    loans.sort();
+    ix.accounts.extend(
+        loans
+            .iter()
+            .map(|loan| AccountMeta::new_readonly(*loan, false)),
+    );
+    ix
+}
+
+pub fn finish_audit(auditor: &Pubkey) -> Instruction {
+    build(
+        accounts::FinishAudit {
+            audit: audit_address(auditor).0,
+            config: config_address().0,
+            auditor: *auditor,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::FinishAudit {},
+    )
+}
+
+pub fn migrate_config(authority: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateConfig {
+            config: config_address().0,
+            authority: *authority,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::MigrateConfig {},
+    )
+}
+
+pub fn migrate_reputation(payer: &Pubkey, owner: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateReputation {
+            reputation: reputation_address(owner).0,
+            owner: *owner,
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::MigrateReputation {},
+    )
+}
+
+pub fn migrate_loan(payer: &Pubkey, borrower: &Pubkey, loan_id: u64) -> Instruction {
+    build(
+        accounts::MigrateLoan {
+            loan: loan_address(borrower, loan_id).0,
+            borrower: *borrower,
+            payer: *payer,
+            system_program: system_program::ID,
+            event_authority: event_authority_address().0,
+            program: ID,
+        },
+        instruction::MigrateLoan { loan_id },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{AnchorDeserialize, Discriminator, Space};
+
+    #[test]
+    fn fund_loan_targets_the_derived_accounts() {
+        let lender = Pubkey::new_unique();
+        let borrower = Pubkey::new_unique();
+        let ix = fund_loan(
+            &lender,
+            &borrower,
+            3,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            900,
+            RebatePolicy::default(),
+        );
+
+        assert_eq!(ix.program_id, ID);
+        assert_eq!(ix.accounts[0].pubkey, loan_address(&borrower, 3).0);
+        assert_eq!(ix.accounts[1].pubkey, escrow_address(&borrower, 3).0);
+        let position_mint = position_mint_address(&borrower, 3, 0).0;
+        assert!(ix
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == get_associated_token_address(&lender, &position_mint)));
+        assert!(ix
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == lender && meta.is_signer));
+        assert_eq!(&ix.data[..8], instruction::FundLoan::DISCRIMINATOR);
+        assert_eq!(ix.data[8..10], 900u16.to_le_bytes());
+    }
+
+    #[test]
+    fn quote_loan_without_reputation_passes_the_program_id() {
+        let borrower = Pubkey::new_unique();
+        let with = quote_loan(&borrower, true, true, 1_000, 86_400);
+        let without = quote_loan(&borrower, false, false, 1_000, 86_400);
+
+        assert_eq!(with.accounts[0].pubkey, reputation_address(&borrower).0);
+        assert_eq!(with.accounts[2].pubkey, identity_address(&borrower).0);
+        assert_eq!(without.accounts[0].pubkey, ID);
+        assert_eq!(without.accounts[2].pubkey, ID);
+        assert!(!without.accounts.iter().any(|meta| meta.is_signer));
+    }
+
+    #[test]
+    fn only_indexed_loans_pass_the_loan_index() {
+        let borrower = Pubkey::new_unique();
+        let indexed = cancel_loan_request(&borrower, 1, true);
+        let untracked = cancel_loan_request(&borrower, 1, false);
+
+        assert_eq!(indexed.accounts[1].pubkey, loan_index_address(&borrower).0);
+        assert_eq!(untracked.accounts[1].pubkey, ID);
+    }
+
+    #[test]
+    fn only_profiled_loans_pass_the_lender_profile() {
+        let lender = Pubkey::new_unique();
+        let mut loan = LoanAccount::deserialize(&mut &[0; LoanAccount::INIT_SPACE][..]).unwrap();
+        loan.borrower = Pubkey::new_unique();
+        loan.lender = Some(lender);
+        let untracked = mark_default(&Pubkey::new_unique(), &loan);
+        loan.lender_profiled = true;
+        let profiled = mark_default(&Pubkey::new_unique(), &loan);
+
+        assert_eq!(untracked.accounts[3].pubkey, ID);
+        assert_eq!(
+            profiled.accounts[3].pubkey,
+            lender_profile_address(&lender).0
+        );
+    }
+
+    #[test]
+    fn only_tracked_loans_pass_the_concentration_accounts() {
+        let lender = Pubkey::new_unique();
+        let mut loan = LoanAccount::deserialize(&mut &[0; LoanAccount::INIT_SPACE][..]).unwrap();
+        loan.borrower = Pubkey::new_unique();
+        loan.lender = Some(lender);
+        let untracked = mark_default(&Pubkey::new_unique(), &loan);
+        loan.concentration_tracked = true;
+        let tracked = mark_default(&Pubkey::new_unique(), &loan);
+
+        assert_eq!(untracked.accounts[4].pubkey, ID);
+        assert_eq!(untracked.accounts[5].pubkey, ID);
+        assert_eq!(tracked.accounts[4].pubkey, exposure_ledger_address().0);
+        assert_eq!(
+            tracked.accounts[5].pubkey,
+            pair_exposure_address(&lender, &loan.borrower).0
+        );
+    }
+
+    #[test]
+    fn guaranteed_requests_pass_the_pledged_guarantee() {
+        let borrower = Pubkey::new_unique();
+        let plain = create_loan_request(&borrower, 4, 1_000, 86_400, 900, false, false);
+        let guaranteed = create_loan_request(&borrower, 4, 1_000, 86_400, 900, true, false);
+
+        assert_eq!(plain.accounts[3].pubkey, ID);
+        assert_eq!(
+            guaranteed.accounts[3].pubkey,
+            guarantee_address(&borrower, 4).0
+        );
+        let bond_mint = Pubkey::new_unique();
+        let pledge = guarantee_loan(
+            &Pubkey::new_unique(),
+            &borrower,
+            4,
+            &Pubkey::new_unique(),
+            &bond_mint,
+            500,
+        );
+        assert_eq!(pledge.accounts[0].pubkey, guaranteed.accounts[3].pubkey);
+        assert!(pledge
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == accepted_bond_mint_address(&bond_mint).0));
+        assert!(pledge
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == borrower && meta.is_signer));
+    }
+
+    #[test]
+    fn only_the_voucher_signs_vouches() {
+        let (voucher, vouchee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let issue = issue_vouch(&voucher, &vouchee, 25);
+        let slash = slash_vouch(&voucher, &vouchee);
+
+        assert_eq!(
+            issue.accounts[0].pubkey,
+            vouch_address(&voucher, &vouchee).0
+        );
+        assert_eq!(slash.accounts[0].pubkey, issue.accounts[0].pubkey);
+        assert!(issue
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == voucher && meta.is_signer));
+        assert!(!issue
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == vouchee && meta.is_signer));
+        // Slashing is permissionless; the voucher only receives the rent
+        assert!(slash.accounts.iter().all(|meta| !meta.is_signer));
+    }
+
+    #[test]
+    fn only_the_borrower_draws_and_anyone_closes_statements() {
+        let mut line = CreditLine::deserialize(&mut &[0; CreditLine::INIT_SPACE][..]).unwrap();
+        line.lender = Pubkey::new_unique();
+        line.borrower = Pubkey::new_unique();
+        line.mint = Pubkey::new_unique();
+        let open = open_credit_line(
+            &line.lender,
+            &line.borrower,
+            &Pubkey::new_unique(),
+            &line.mint,
+            false,
+            1_000,
+            700,
+        );
+        let draw = draw_credit_line(&line, &Pubkey::new_unique(), false, 400);
+        let statement = close_statement(&line.lender, &line.borrower);
+
+        assert_eq!(
+            open.accounts[0].pubkey,
+            credit_line_address(&line.lender, &line.borrower).0
+        );
+        assert_eq!(draw.accounts[0].pubkey, open.accounts[0].pubkey);
+        assert!(draw.accounts.iter().any(|meta| meta.pubkey
+            == line_vault_address(&line.lender, &line.borrower, &line.mint)
+            && meta.is_writable));
+        assert!(!draw
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == line.lender && meta.is_signer));
+        assert!(statement.accounts.iter().all(|meta| !meta.is_signer));
+    }
+
+    #[test]
+    fn delegated_draws_are_signed_by_the_borrower_alone() {
+        let mut delegation =
+            CreditDelegation::deserialize(&mut &[0; CreditDelegation::INIT_SPACE][..]).unwrap();
+        delegation.lender = Pubkey::new_unique();
+        delegation.borrower = Pubkey::new_unique();
+        delegation.mint = Pubkey::new_unique();
+        let lender_token_account = Pubkey::new_unique();
+        let draw = draw_delegated_credit(
+            &delegation,
+            6,
+            &Pubkey::new_unique(),
+            &lender_token_account,
+            false,
+            500,
+        );
+        let (address, _) = credit_delegation_address(&delegation.lender, &delegation.borrower);
+        let approve = approve_delegate(&delegation.lender, &lender_token_account, &address, 500);
+
+        assert_eq!(
+            draw.accounts[0].pubkey,
+            credit_delegation_address(&delegation.lender, &delegation.borrower).0
+        );
+        assert_eq!(
+            draw.accounts[2].pubkey,
+            loan_address(&delegation.borrower, 6).0
+        );
+        // Only the borrower signs; the lender's approval stands in for their signature
+        assert!(draw
+            .accounts
+            .iter()
+            .all(|meta| meta.is_signer == (meta.pubkey == delegation.borrower)));
+        assert_eq!(
+            approve.accounts[1].pubkey,
+            delegate_authority_address(&address).0
+        );
+        assert!(draw
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == approve.accounts[1].pubkey));
+    }
+
+    #[test]
+    fn keepers_execute_auto_repay_without_the_borrower() {
+        let mut loan = LoanAccount::deserialize(&mut &[0; LoanAccount::INIT_SPACE][..]).unwrap();
+        loan.borrower = Pubkey::new_unique();
+        loan.loan_id = 4;
+        let mut auto_repay = AutoRepay::deserialize(&mut &[0; AutoRepay::INIT_SPACE][..]).unwrap();
+        auto_repay.borrower = loan.borrower;
+        auto_repay.loan_id = loan.loan_id;
+        auto_repay.mint = Pubkey::new_unique();
+        auto_repay.borrower_token_account = Pubkey::new_unique();
+        let keeper = Pubkey::new_unique();
+        let ix = execute_auto_repay(
+            &keeper,
+            &loan,
+            &auto_repay,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            None,
+            &Pubkey::new_unique(),
+        );
+
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            auto_repay_address(&loan.borrower, 4).0
+        );
+        assert_eq!(
+            ix.accounts[1].pubkey,
+            get_associated_token_address(&ix.accounts[0].pubkey, &auto_repay.mint)
+        );
+        // The borrower's approval stands in for their signature
+        assert!(ix
+            .accounts
+            .iter()
+            .all(|meta| meta.is_signer == (meta.pubkey == keeper)));
+        assert!(ix
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == auto_repay.borrower_token_account && meta.is_writable));
+    }
+
+    #[test]
+    fn attested_wallets_pass_their_identity_attestation() {
+        let owner = Pubkey::new_unique();
+        let attestation = identity_address(&owner).0;
+
+        assert!(!create_reputation(&owner, false)
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == attestation));
+        assert_eq!(
+            create_reputation(&owner, true).accounts[1].pubkey,
+            attestation
+        );
+        let request = create_loan_request(&owner, 1, 1_000, 86_400, 900, false, true);
+        assert_eq!(request.accounts[4].pubkey, attestation);
+    }
+
+    #[test]
+    fn tier_checks_need_no_signatures_or_writes() {
+        let borrower = Pubkey::new_unique();
+        let check = check_credit_tier(&borrower, 1);
+
+        assert_eq!(check.accounts[0].pubkey, reputation_address(&borrower).0);
+        assert!(check
+            .accounts
+            .iter()
+            .all(|meta| !meta.is_signer && !meta.is_writable));
+    }
+
+    #[test]
+    fn audit_loans_sorts_remaining_accounts() {
+        let auditor = Pubkey::new_unique();
+        let loans = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let ix = audit_loans(&auditor, &loans);
+
+        // Loans follow the audit, the auditor and the event CPI accounts
+        let tallied: Vec<Pubkey> = ix.accounts[4..].iter().map(|meta| meta.pubkey).collect();
+        let mut expected = loans.to_vec();
+        expected.sort();
+        assert_eq!(tallied, expected);
+    }
+}
@@ -0,0 +1,12 @@
+//! Rust client SDK for the Sollend micro-lending protocol.
+//!
+//! Wraps the program crate (built with its `cpi` feature) with typed
+//! instruction builders, PDA derivation, account decoders and off-chain
+//! quoting helpers, so Rust services don't need the TypeScript tooling.
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+
+pub use sollend_micro_protocol::{self as program, ID as PROGRAM_ID};
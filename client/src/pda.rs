@@ -0,0 +1,193 @@
+//! PDA derivation for every account the program owns or signs with.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address;
+use sollend_micro_protocol::{
+    AUDIT_SEED, AUTO_REPAY_SEED, BOND_MINT_SEED, CONFIG_SEED, CREDIT_DELEGATION_SEED,
+    CREDIT_LINE_SEED, DELEGATE_SEED, ESCROW_SEED, EXPOSURE_LEDGER_SEED, GUARANTEE_SEED, ID,
+    IDENTITY_SEED, LENDER_PROFILE_SEED, LOAN_INDEX_SEED, LOAN_SEED, PAIR_EXPOSURE_SEED,
+    POSITION_SEED, REPUTATION_ATTESTATION_SEED, REPUTATION_SEED, VOUCH_SEED,
+};
+
+/// Seed Anchor uses for the `emit_cpi!` event authority
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+pub fn config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], &ID)
+}
+
+pub fn reputation_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REPUTATION_SEED, owner.as_ref()], &ID)
+}
+
+pub fn loan_index_address(borrower: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LOAN_INDEX_SEED, borrower.as_ref()], &ID)
+}
+
+pub fn lender_profile_address(lender: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LENDER_PROFILE_SEED, lender.as_ref()], &ID)
+}
+
+pub fn exposure_ledger_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EXPOSURE_LEDGER_SEED], &ID)
+}
+
+pub fn pair_exposure_address(lender: &Pubkey, borrower: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PAIR_EXPOSURE_SEED, lender.as_ref(), borrower.as_ref()],
+        &ID,
+    )
+}
+
+pub fn loan_address(borrower: &Pubkey, loan_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LOAN_SEED, borrower.as_ref(), loan_id.to_le_bytes().as_ref()],
+        &ID,
+    )
+}
+
+pub fn escrow_address(borrower: &Pubkey, loan_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            ESCROW_SEED,
+            borrower.as_ref(),
+            loan_id.to_le_bytes().as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// Associated token account of the escrow PDA, which holds the lender's funds
+pub fn escrow_token_address(borrower: &Pubkey, loan_id: u64, mint: &Pubkey) -> Pubkey {
+    let (escrow, _) = escrow_address(borrower, loan_id);
+    get_associated_token_address(&escrow, mint)
+}
+
+/// Mint of a loan's lender position; `index` is the loan's `refinance_count` when it was
+/// minted, since every refinance issues a fresh position
+pub fn position_mint_address(borrower: &Pubkey, loan_id: u64, index: u8) -> (Pubkey, u8) {
+    let (loan, _) = loan_address(borrower, loan_id);
+    Pubkey::find_program_address(
+        &[POSITION_SEED, loan.as_ref(), index.to_le_bytes().as_ref()],
+        &ID,
+    )
+}
+
+/// Guarantee behind a borrower's loan, pledged before the loan request is made
+pub fn guarantee_address(borrower: &Pubkey, loan_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            GUARANTEE_SEED,
+            borrower.as_ref(),
+            loan_id.to_le_bytes().as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// Whitelist entry for a mint the risk manager accepts for guarantee bonds
+pub fn accepted_bond_mint_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BOND_MINT_SEED, mint.as_ref()], &ID)
+}
+
+/// Associated token account of the guarantee PDA, which holds the guarantor's bond
+pub fn bond_vault_address(borrower: &Pubkey, loan_id: u64, mint: &Pubkey) -> Pubkey {
+    let (guarantee, _) = guarantee_address(borrower, loan_id);
+    get_associated_token_address(&guarantee, mint)
+}
+
+/// A voucher's vouch for a vouchee; each pair has at most one
+pub fn vouch_address(voucher: &Pubkey, vouchee: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VOUCH_SEED, voucher.as_ref(), vouchee.as_ref()], &ID)
+}
+
+/// A lender's credit line to a borrower; each pair has at most one
+pub fn credit_line_address(lender: &Pubkey, borrower: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CREDIT_LINE_SEED, lender.as_ref(), borrower.as_ref()], &ID)
+}
+
+/// Associated token account of the credit line PDA, which holds the undrawn limit
+pub fn line_vault_address(lender: &Pubkey, borrower: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let (credit_line, _) = credit_line_address(lender, borrower);
+    get_associated_token_address(&credit_line, mint)
+}
+
+/// A lender's credit delegation to a borrower; each pair has at most one
+pub fn credit_delegation_address(lender: &Pubkey, borrower: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[CREDIT_DELEGATION_SEED, lender.as_ref(), borrower.as_ref()],
+        &ID,
+    )
+}
+
+/// Program PDA that token owners approve as SPL delegate for one credit
+/// delegation or auto-repay schedule, given that account's address
+pub fn delegate_authority_address(relationship: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DELEGATE_SEED, relationship.as_ref()], &ID)
+}
+
+/// A loan's automatic repayment schedule; each loan has at most one
+pub fn auto_repay_address(borrower: &Pubkey, loan_id: u64) -> (Pubkey, u8) {
+    let (loan, _) = loan_address(borrower, loan_id);
+    Pubkey::find_program_address(&[AUTO_REPAY_SEED, loan.as_ref()], &ID)
+}
+
+/// Associated token account of the auto-repay PDA, which holds collected installments
+pub fn repay_vault_address(borrower: &Pubkey, loan_id: u64, mint: &Pubkey) -> Pubkey {
+    let (auto_repay, _) = auto_repay_address(borrower, loan_id);
+    get_associated_token_address(&auto_repay, mint)
+}
+
+/// Short-lived snapshot of an owner's reputation, refreshed by `attest_reputation`
+pub fn reputation_attestation_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REPUTATION_ATTESTATION_SEED, owner.as_ref()], &ID)
+}
+
+/// Identity attestation the protocol's attester issued for a wallet
+pub fn identity_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[IDENTITY_SEED, owner.as_ref()], &ID)
+}
+
+pub fn audit_address(auditor: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[AUDIT_SEED, auditor.as_ref()], &ID)
+}
+
+pub fn event_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loan_and_escrow_addresses_depend_on_loan_id() {
+        let borrower = Pubkey::new_unique();
+
+        assert_ne!(loan_address(&borrower, 1).0, loan_address(&borrower, 2).0);
+        assert_ne!(loan_address(&borrower, 1).0, escrow_address(&borrower, 1).0);
+        assert_eq!(loan_address(&borrower, 7), loan_address(&borrower, 7));
+    }
+
+    #[test]
+    fn refinancing_moves_to_a_new_position_mint() {
+        let borrower = Pubkey::new_unique();
+
+        assert_ne!(
+            position_mint_address(&borrower, 1, 0).0,
+            position_mint_address(&borrower, 1, 1).0
+        );
+        assert_ne!(
+            position_mint_address(&borrower, 1, 0).0,
+            position_mint_address(&borrower, 2, 0).0
+        );
+    }
+
+    #[test]
+    fn reputation_address_is_per_owner() {
+        let (first, _) = reputation_address(&Pubkey::new_unique());
+        let (second, _) = reputation_address(&Pubkey::new_unique());
+
+        assert_ne!(first, second);
+    }
+}
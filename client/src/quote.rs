@@ -0,0 +1,121 @@
+//! Off-chain replicas of the program's pricing math, for quoting loans
+//! without a round trip to the cluster. Keep in sync with the helpers in
+//! `programs/sollend_micro_protocol/src/lib.rs`.
+
+use sollend_micro_protocol::{
+    LoanAccount, ProtocolConfig, BASE_RATE, CREDIT_TIER_A, CREDIT_TIER_B, CREDIT_TIER_C,
+    CREDIT_TIER_D, TIER_A_PREMIUM, TIER_A_THRESHOLD, TIER_B_PREMIUM, TIER_B_THRESHOLD,
+    TIER_C_PREMIUM, TIER_C_THRESHOLD, TIER_D_PREMIUM,
+};
+
+pub fn calculate_credit_tier(credit_score: u16) -> u8 {
+    if credit_score >= TIER_A_THRESHOLD {
+        CREDIT_TIER_A
+    } else if credit_score >= TIER_B_THRESHOLD {
+        CREDIT_TIER_B
+    } else if credit_score >= TIER_C_THRESHOLD {
+        CREDIT_TIER_C
+    } else {
+        CREDIT_TIER_D
+    }
+}
+
+/// Minimum interest rate (bps) `create_loan_request` accepts for a tier and duration
+pub fn calculate_interest_rate(credit_tier: u8, duration_seconds: i64) -> u16 {
+    let risk_premium = match credit_tier {
+        CREDIT_TIER_A => TIER_A_PREMIUM,
+        CREDIT_TIER_B => TIER_B_PREMIUM,
+        CREDIT_TIER_C => TIER_C_PREMIUM,
+        _ => TIER_D_PREMIUM,
+    };
+
+    // 10 bps per full 30 days; negative durations add nothing and huge ones saturate
+    let duration_months = (duration_seconds.max(0) / 86400 / 30).min(u16::MAX as i64) as u16;
+    let duration_factor = duration_months.saturating_mul(10);
+
+    BASE_RATE
+        .saturating_add(risk_premium)
+        .saturating_add(duration_factor)
+}
+
+pub fn max_borrow_amount(config: &ProtocolConfig, credit_tier: u8) -> u64 {
+    config.tier_max_borrow[credit_tier.min(CREDIT_TIER_D) as usize]
+}
+
+/// Breakdown of what `repay_loan` transfers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepaymentQuote {
+    pub principal: u64,
+    pub interest: u64,
+    pub protocol_fee: u64,
+    pub lender_amount: u64,
+    pub total: u64,
+}
+
+pub fn repayment_quote(
+    amount: u64,
+    interest_rate_bps: u16,
+    protocol_fee_bps: u16,
+) -> RepaymentQuote {
+    let interest = (amount as u128 * interest_rate_bps as u128 / 10000) as u64;
+    split_repayment(amount, interest, protocol_fee_bps)
+}
+
+/// What repaying `loan` at `now` would cost, including any early repayment rebate
+pub fn loan_repayment_quote(loan: &LoanAccount, protocol_fee_bps: u16, now: i64) -> RepaymentQuote {
+    split_repayment(loan.amount, loan.interest_due(now), protocol_fee_bps)
+}
+
+fn split_repayment(amount: u64, interest: u64, protocol_fee_bps: u16) -> RepaymentQuote {
+    let protocol_fee = (interest as u128 * protocol_fee_bps as u128 / 10000) as u64;
+
+    RepaymentQuote {
+        principal: amount,
+        interest,
+        protocol_fee,
+        lender_amount: amount + interest - protocol_fee,
+        total: amount + interest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_follow_score_thresholds() {
+        assert_eq!(calculate_credit_tier(1000), CREDIT_TIER_A);
+        assert_eq!(calculate_credit_tier(800), CREDIT_TIER_A);
+        assert_eq!(calculate_credit_tier(799), CREDIT_TIER_B);
+        assert_eq!(calculate_credit_tier(600), CREDIT_TIER_B);
+        assert_eq!(calculate_credit_tier(500), CREDIT_TIER_C);
+        assert_eq!(calculate_credit_tier(399), CREDIT_TIER_D);
+        assert_eq!(calculate_credit_tier(0), CREDIT_TIER_D);
+    }
+
+    #[test]
+    fn interest_rate_adds_tier_premium_and_duration() {
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_A, 5), 500);
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_C, 86400 * 30), 1010);
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_D, 86400 * 365), 1620);
+        // Partial months don't count
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_B, 86400 * 59), 710);
+        // Durations the program rejects still price without overflowing
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_C, -86400 * 365), 1000);
+        assert_eq!(
+            calculate_interest_rate(CREDIT_TIER_D, 86400 * 365 * 1000),
+            u16::MAX
+        );
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_A, i64::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn repayment_quote_splits_interest_with_treasury() {
+        let quote = repayment_quote(10_000_000_000, 1000, 150);
+
+        assert_eq!(quote.interest, 1_000_000_000);
+        assert_eq!(quote.protocol_fee, 15_000_000);
+        assert_eq!(quote.lender_amount + quote.protocol_fee, quote.total);
+        assert_eq!(quote.total, 11_000_000_000);
+    }
+}
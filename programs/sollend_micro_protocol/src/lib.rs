@@ -1,5 +1,10 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::spl_token::instruction::AuthorityType;
+use anchor_spl::token::{self, CloseAccount, Mint, MintTo, SetAuthority, Token, TokenAccount, Transfer};
 
 declare_id!("vig2EZuki3nM9feg1VWj7QkyzTkafYvAH4WmT4AX9uj");
 
@@ -8,6 +13,21 @@ pub const REPUTATION_SEED: &[u8] = b"reputation";
 pub const LOAN_SEED: &[u8] = b"loan";
 pub const ESCROW_SEED: &[u8] = b"escrow";
 pub const CONFIG_SEED: &[u8] = b"config";
+pub const AUDIT_SEED: &[u8] = b"audit";
+pub const POSITION_SEED: &[u8] = b"position";
+pub const LOAN_INDEX_SEED: &[u8] = b"loan_index";
+pub const LENDER_PROFILE_SEED: &[u8] = b"lender_profile";
+pub const EXPOSURE_LEDGER_SEED: &[u8] = b"exposure_ledger";
+pub const PAIR_EXPOSURE_SEED: &[u8] = b"pair_exposure";
+pub const GUARANTEE_SEED: &[u8] = b"guarantee";
+pub const BOND_MINT_SEED: &[u8] = b"bond_mint";
+pub const VOUCH_SEED: &[u8] = b"vouch";
+pub const IDENTITY_SEED: &[u8] = b"identity";
+pub const REPUTATION_ATTESTATION_SEED: &[u8] = b"reputation_attestation";
+pub const CREDIT_LINE_SEED: &[u8] = b"credit_line";
+pub const CREDIT_DELEGATION_SEED: &[u8] = b"credit_delegation";
+pub const DELEGATE_SEED: &[u8] = b"delegate"; // SPL token delegate the program transfers with, one per delegation or auto-repay
+pub const AUTO_REPAY_SEED: &[u8] = b"auto_repay";
 
 pub const CREDIT_TIER_A: u8 = 0;
 pub const CREDIT_TIER_B: u8 = 1;
@@ -27,6 +47,16 @@ pub const TIER_C_THRESHOLD: u16 = 400;
 pub const ON_TIME_PAYMENT_BONUS: i16 = 50;
 pub const LATE_PAYMENT_PENALTY: i16 = -30;
 pub const DEFAULT_PENALTY: i16 = -150;
+pub const GUARANTOR_DEFAULT_PENALTY: i16 = -75; // Applied when a guaranteed loan's bond is claimed
+pub const UNWITHDRAWN_PENALTY: i16 = 0; // Funded loans the borrower never withdrew
+
+// Time a borrower has to withdraw a funded loan before the lender can reclaim it
+pub const DEFAULT_WITHDRAWAL_WINDOW: i64 = 7 * 86400; // 7 days
+// Time an unfunded loan request stays open before anyone can expire it
+pub const DEFAULT_REQUEST_TTL: i64 = 14 * 86400; // 14 days
+// Time a reputation attestation can be relied on before it has to be refreshed
+pub const REPUTATION_ATTESTATION_TTL: i64 = 86400; // 1 day
+pub const CREDIT_LINE_STATEMENT_PERIOD: i64 = 30 * 86400; // 30 days
 
 // Base interest rates (in basis points, 100 = 1%)
 pub const BASE_RATE: u16 = 500; // 5%
@@ -41,6 +71,73 @@ pub const TIER_B_MAX_BORROW: u64 = 50_000_000_000; // 50 tokens
 pub const TIER_C_MAX_BORROW: u64 = 25_000_000_000; // 25 tokens
 pub const TIER_D_MAX_BORROW: u64 = 10_000_000_000; // 10 tokens
 
+// Worst tier a guarantor may be in when pledging a bond
+pub const MIN_GUARANTOR_TIER: u8 = CREDIT_TIER_B;
+
+// Credit lines are only open to the top tiers
+pub const MAX_CREDIT_LINE_TIER: u8 = CREDIT_TIER_B;
+
+// Automatic repayment: installments a schedule may split into, the most a borrower may tip
+// keepers per installment, and how far ahead of its share of the term each one can be collected
+pub const MAX_AUTO_REPAY_INSTALLMENTS: u8 = 12;
+pub const MAX_KEEPER_TIP: u64 = 10_000_000; // 0.01 tokens
+pub const AUTO_REPAY_WINDOW: i64 = 86400; // 1 day
+
+// Vouching: who may vouch, how much score one vouch stakes, and default caps per user
+pub const MIN_VOUCHER_SCORE: u16 = TIER_A_THRESHOLD; // Judged on the voucher's own score
+pub const MAX_VOUCH_STAKE: u16 = 50;
+pub const DEFAULT_MAX_VOUCHES_GIVEN: u8 = 5;
+pub const DEFAULT_MAX_VOUCHES_RECEIVED: u8 = 3;
+
+// Outstanding principal limits per tier, across all of a borrower's funded loans
+pub const TIER_A_MAX_EXPOSURE: u64 = 300_000_000_000; // 300 tokens
+pub const TIER_B_MAX_EXPOSURE: u64 = 150_000_000_000; // 150 tokens
+pub const TIER_C_MAX_EXPOSURE: u64 = 75_000_000_000; // 75 tokens
+pub const TIER_D_MAX_EXPOSURE: u64 = 20_000_000_000; // 20 tokens
+pub const DEFAULT_TIER_MAX_EXPOSURE: [u64; 4] = [
+    TIER_A_MAX_EXPOSURE,
+    TIER_B_MAX_EXPOSURE,
+    TIER_C_MAX_EXPOSURE,
+    TIER_D_MAX_EXPOSURE,
+];
+// Funded or active loans a borrower may hold at once, unless the risk manager configures otherwise
+pub const DEFAULT_MAX_ACTIVE_LOANS: u32 = 5;
+
+// Protocol-wide outstanding principal limits, per borrower tier at funding and in total
+pub const TIER_A_DEBT_CEILING: u64 = 1_000_000_000_000_000; // 1,000,000 tokens
+pub const TIER_B_DEBT_CEILING: u64 = 500_000_000_000_000; // 500,000 tokens
+pub const TIER_C_DEBT_CEILING: u64 = 250_000_000_000_000; // 250,000 tokens
+pub const TIER_D_DEBT_CEILING: u64 = 50_000_000_000_000; // 50,000 tokens
+pub const DEFAULT_MAX_TOTAL_OUTSTANDING: u64 = 1_500_000_000_000_000; // 1,500,000 tokens
+// Outstanding principal a single lender may have with a single borrower
+pub const DEFAULT_MAX_PAIR_EXPOSURE: u64 = 100_000_000_000; // 100 tokens
+
+// Pause flags (bitmask over ProtocolConfig.pause_flags)
+pub const PAUSE_NEW_REQUESTS: u8 = 1 << 0;
+pub const PAUSE_FUNDING: u8 = 1 << 1;
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 2;
+pub const PAUSE_REPAYMENTS: u8 = 1 << 3;
+pub const PAUSE_DEFAULTS: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 =
+    PAUSE_NEW_REQUESTS | PAUSE_FUNDING | PAUSE_WITHDRAWALS | PAUSE_REPAYMENTS | PAUSE_DEFAULTS;
+
+// A loan can be extended at most this many times
+pub const MAX_LOAN_EXTENSIONS: u8 = 3;
+pub const MAX_EXTENSION_RATE_BPS: u16 = 2000; // Interest one extension may add (20%)
+
+// Open loans a borrower's loan index can track at once
+pub const MAX_ACTIVE_LOAN_IDS: usize = 16;
+
+// Account layout versions (v0 = legacy layouts without a version byte)
+pub const CONFIG_VERSION: u8 = 1;
+pub const REPUTATION_VERSION: u8 = 1;
+pub const LOAN_VERSION: u8 = 1;
+
+// Zeroed tail padding so new fields can be added without a realloc
+pub const CONFIG_RESERVED_BYTES: usize = 16;
+pub const REPUTATION_RESERVED_BYTES: usize = 78;
+pub const LOAN_RESERVED_BYTES: usize = 21;
+
 #[program]
 pub mod sollend_micro_protocol {
     use super::*;
@@ -52,47 +149,391 @@ pub mod sollend_micro_protocol {
         protocol_fee_bps: u16,
     ) -> Result<()> {
         let config = &mut ctx.accounts.config;
-        config.authority = ctx.accounts.authority.key();
+        let authority = ctx.accounts.authority.key();
+        config.version = CONFIG_VERSION;
+        config.authority = authority;
         config.oracle_authority = oracle_authority;
         config.protocol_fee_bps = protocol_fee_bps;
         config.total_loans_issued = 0;
         config.total_volume = 0;
         config.total_defaults = 0;
-        config.is_paused = false;
+        config.pause_flags = 0;
         
+        // The owner starts out holding every operational role
+        config.pauser = authority;
+        config.fee_manager = authority;
+        config.risk_manager = authority;
+        config.reputation_steward = authority;
+        // The identity gate stays off until the owner assigns an attester
+        config.identity_attester = Pubkey::default();
+        
+        // Risk parameters start at the protocol defaults
+        config.tier_max_borrow = [
+            TIER_A_MAX_BORROW,
+            TIER_B_MAX_BORROW,
+            TIER_C_MAX_BORROW,
+            TIER_D_MAX_BORROW,
+        ];
+        config.on_time_payment_bonus = ON_TIME_PAYMENT_BONUS;
+        config.late_payment_penalty = LATE_PAYMENT_PENALTY;
+        config.default_penalty = DEFAULT_PENALTY;
+        config.withdrawal_window_seconds = DEFAULT_WITHDRAWAL_WINDOW;
+        config.unwithdrawn_penalty = UNWITHDRAWN_PENALTY;
+        config.request_ttl_seconds = DEFAULT_REQUEST_TTL;
+        config.archived_loans_issued = 0;
+        config.archived_volume = 0;
+        config.archived_defaults = 0;
+        config.tier_max_exposure = DEFAULT_TIER_MAX_EXPOSURE;
+        config.max_active_loans = DEFAULT_MAX_ACTIVE_LOANS;
+        config.max_vouches_given = DEFAULT_MAX_VOUCHES_GIVEN;
+        config.max_vouches_received = DEFAULT_MAX_VOUCHES_RECEIVED;
+        config.reserved = [0; CONFIG_RESERVED_BYTES];
+        
+        emit_cpi!(ConfigInitialized {
+            authority,
+            oracle_authority,
+            protocol_fee_bps,
+        });
         msg!("Protocol config initialized");
         Ok(())
     }
 
-    /// Update protocol configuration (admin only)
+    /// Update protocol configuration (fee manager / pauser)
     pub fn update_config(
         ctx: Context<UpdateConfig>,
-        oracle_authority: Option<Pubkey>,
         protocol_fee_bps: Option<u16>,
-        is_paused: Option<bool>,
+        pause_flags: Option<u8>,
     ) -> Result<()> {
         let config = &mut ctx.accounts.config;
+        let signer = ctx.accounts.signer.key();
+        
+        // Check the update changes something, so every ConfigUpdated comes from a role holder
+        require!(
+            protocol_fee_bps.is_some() || pause_flags.is_some(),
+            ErrorCode::EmptyConfigUpdate
+        );
         
-        if let Some(oracle) = oracle_authority {
-            config.oracle_authority = oracle;
-        }
         if let Some(fee) = protocol_fee_bps {
+            require!(config.has_role(Role::FeeManager, &signer), ErrorCode::MissingRole);
             require!(fee <= 1000, ErrorCode::InvalidFee); // Max 10%
             config.protocol_fee_bps = fee;
         }
-        if let Some(paused) = is_paused {
-            config.is_paused = paused;
+        if let Some(flags) = pause_flags {
+            require!(config.has_role(Role::Pauser, &signer), ErrorCode::MissingRole);
+            require!(flags & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+            config.pause_flags = flags;
         }
         
+        emit_cpi!(ConfigUpdated {
+            signer,
+            protocol_fee_bps: config.protocol_fee_bps,
+            pause_flags: config.pause_flags,
+        });
         msg!("Protocol config updated");
         Ok(())
     }
 
+    /// Update borrow limits and scoring adjustments (risk manager only)
+    pub fn update_risk_params(
+        ctx: Context<UpdateRiskParams>,
+        tier_max_borrow: Option<[u64; 4]>,
+        on_time_payment_bonus: Option<i16>,
+        late_payment_penalty: Option<i16>,
+        default_penalty: Option<i16>,
+        unwithdrawn_penalty: Option<i16>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        
+        if let Some(limits) = tier_max_borrow {
+            // Better tiers must never be allowed to borrow less than worse ones
+            require!(
+                limits.windows(2).all(|pair| pair[0] >= pair[1]),
+                ErrorCode::InvalidRiskParams
+            );
+            config.tier_max_borrow = limits;
+        }
+        if let Some(bonus) = on_time_payment_bonus {
+            require!(bonus >= 0, ErrorCode::InvalidRiskParams);
+            config.on_time_payment_bonus = bonus;
+        }
+        if let Some(penalty) = late_payment_penalty {
+            require!(penalty <= 0, ErrorCode::InvalidRiskParams);
+            config.late_payment_penalty = penalty;
+        }
+        if let Some(penalty) = default_penalty {
+            require!(penalty <= 0, ErrorCode::InvalidRiskParams);
+            config.default_penalty = penalty;
+        }
+        if let Some(penalty) = unwithdrawn_penalty {
+            require!(penalty <= 0, ErrorCode::InvalidRiskParams);
+            config.unwithdrawn_penalty = penalty;
+        }
+        
+        emit_cpi!(RiskParamsUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            tier_max_borrow: config.tier_max_borrow,
+            on_time_payment_bonus: config.on_time_payment_bonus,
+            late_payment_penalty: config.late_payment_penalty,
+            default_penalty: config.default_penalty,
+            unwithdrawn_penalty: config.unwithdrawn_penalty,
+        });
+        msg!("Risk parameters updated");
+        Ok(())
+    }
+
+    /// Update how long requests stay open and funded loans wait for withdrawal (risk manager only)
+    pub fn update_loan_windows(
+        ctx: Context<UpdateRiskParams>,
+        withdrawal_window_seconds: Option<i64>,
+        request_ttl_seconds: Option<i64>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        
+        if let Some(window) = withdrawal_window_seconds {
+            require!(window > 0, ErrorCode::InvalidRiskParams);
+            config.withdrawal_window_seconds = window;
+        }
+        if let Some(ttl) = request_ttl_seconds {
+            require!(ttl > 0, ErrorCode::InvalidRiskParams);
+            config.request_ttl_seconds = ttl;
+        }
+        
+        emit_cpi!(LoanWindowsUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            withdrawal_window_seconds: config.withdrawal_window(),
+            request_ttl_seconds: config.request_ttl(),
+        });
+        msg!("Loan windows updated");
+        Ok(())
+    }
+
+    /// Update how much principal a borrower may have outstanding per tier, and across
+    /// how many loans at once (risk manager only)
+    pub fn update_exposure_limits(
+        ctx: Context<UpdateRiskParams>,
+        tier_max_exposure: Option<[u64; 4]>,
+        max_active_loans: Option<u32>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        
+        if let Some(limits) = tier_max_exposure {
+            // Every tier must fit at least one maximum-size loan, and better tiers
+            // must never be allowed less exposure than worse ones
+            require!(
+                limits.iter().zip(config.tier_max_borrow).all(|(limit, max_borrow)| *limit >= max_borrow)
+                    && limits.windows(2).all(|pair| pair[0] >= pair[1]),
+                ErrorCode::InvalidRiskParams
+            );
+            config.tier_max_exposure = limits;
+        }
+        if let Some(max_loans) = max_active_loans {
+            // The borrower's loan index has to be able to track every one of them
+            require!(
+                max_loans > 0 && max_loans as usize <= MAX_ACTIVE_LOAN_IDS,
+                ErrorCode::InvalidRiskParams
+            );
+            config.max_active_loans = max_loans;
+        }
+        
+        emit_cpi!(ExposureLimitsUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            tier_max_exposure: config.tier_max_exposure,
+            max_active_loans: config.active_loan_limit(),
+        });
+        msg!("Exposure limits updated");
+        Ok(())
+    }
+
+    /// Create the exposure ledger with the default concentration limits (risk manager only)
+    pub fn initialize_exposure_ledger(ctx: Context<InitializeExposureLedger>) -> Result<()> {
+        let exposure_ledger = &mut ctx.accounts.exposure_ledger;
+        
+        exposure_ledger.tier_debt_ceiling = [
+            TIER_A_DEBT_CEILING,
+            TIER_B_DEBT_CEILING,
+            TIER_C_DEBT_CEILING,
+            TIER_D_DEBT_CEILING,
+        ];
+        exposure_ledger.tier_outstanding = [0; 4];
+        exposure_ledger.max_total_outstanding = DEFAULT_MAX_TOTAL_OUTSTANDING;
+        exposure_ledger.max_pair_exposure = DEFAULT_MAX_PAIR_EXPOSURE;
+        exposure_ledger.bump = ctx.bumps.exposure_ledger;
+        
+        emit_cpi!(ConcentrationLimitsUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            tier_debt_ceiling: exposure_ledger.tier_debt_ceiling,
+            max_total_outstanding: exposure_ledger.max_total_outstanding,
+            max_pair_exposure: exposure_ledger.max_pair_exposure,
+        });
+        msg!("Exposure ledger initialized");
+        Ok(())
+    }
+
+    /// Update the lender concentration and protocol-wide debt limits (risk manager only)
+    pub fn update_concentration_limits(
+        ctx: Context<UpdateConcentrationLimits>,
+        tier_debt_ceiling: Option<[u64; 4]>,
+        max_total_outstanding: Option<u64>,
+        max_pair_exposure: Option<u64>,
+    ) -> Result<()> {
+        let exposure_ledger = &mut ctx.accounts.exposure_ledger;
+        
+        // A zero tier ceiling closes that tier to new funding; the other limits must leave room
+        if let Some(ceilings) = tier_debt_ceiling {
+            exposure_ledger.tier_debt_ceiling = ceilings;
+        }
+        if let Some(cap) = max_total_outstanding {
+            require!(cap > 0, ErrorCode::InvalidRiskParams);
+            exposure_ledger.max_total_outstanding = cap;
+        }
+        if let Some(cap) = max_pair_exposure {
+            require!(cap > 0, ErrorCode::InvalidRiskParams);
+            exposure_ledger.max_pair_exposure = cap;
+        }
+        
+        emit_cpi!(ConcentrationLimitsUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            tier_debt_ceiling: exposure_ledger.tier_debt_ceiling,
+            max_total_outstanding: exposure_ledger.max_total_outstanding,
+            max_pair_exposure: exposure_ledger.max_pair_exposure,
+        });
+        msg!("Concentration limits updated");
+        Ok(())
+    }
+
+    /// Accept a mint for guarantee bonds. Backing counts toward borrowing limits in raw
+    /// token units, so only mints worth those units should be accepted (risk manager only)
+    pub fn accept_bond_mint(ctx: Context<AcceptBondMint>) -> Result<()> {
+        let accepted_bond_mint = &mut ctx.accounts.accepted_bond_mint;
+        
+        accepted_bond_mint.mint = ctx.accounts.mint.key();
+        accepted_bond_mint.bump = ctx.bumps.accepted_bond_mint;
+        
+        emit_cpi!(BondMintUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            mint: accepted_bond_mint.mint,
+            accepted: true,
+        });
+        msg!("Bond mint accepted: {}", accepted_bond_mint.mint);
+        Ok(())
+    }
+
+    /// Stop accepting a mint for new guarantee bonds; bonds already pledged in it are
+    /// unaffected (risk manager only)
+    pub fn remove_bond_mint(ctx: Context<RemoveBondMint>) -> Result<()> {
+        let mint = ctx.accounts.accepted_bond_mint.mint;
+        
+        emit_cpi!(BondMintUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            mint,
+            accepted: false,
+        });
+        msg!("Bond mint removed: {}", mint);
+        Ok(())
+    }
+
+    /// Update how many vouches a user may give and receive at once (risk manager only)
+    pub fn update_vouch_limits(
+        ctx: Context<UpdateRiskParams>,
+        max_vouches_given: Option<u8>,
+        max_vouches_received: Option<u8>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        
+        // Lowered caps only block new vouches; existing ones stay in place
+        if let Some(limit) = max_vouches_given {
+            require!(limit > 0, ErrorCode::InvalidRiskParams);
+            config.max_vouches_given = limit;
+        }
+        if let Some(limit) = max_vouches_received {
+            require!(limit > 0, ErrorCode::InvalidRiskParams);
+            config.max_vouches_received = limit;
+        }
+        
+        emit_cpi!(VouchLimitsUpdated {
+            risk_manager: ctx.accounts.risk_manager.key(),
+            max_vouches_given: config.vouches_given_limit(),
+            max_vouches_received: config.vouches_received_limit(),
+        });
+        msg!("Vouch limits updated");
+        Ok(())
+    }
+
+    /// Assign an operational role to a key (owner only)
+    pub fn set_role(ctx: Context<ManageRole>, role: Role, holder: Pubkey) -> Result<()> {
+        require!(holder != Pubkey::default(), ErrorCode::InvalidRoleHolder);
+        ctx.accounts.config.set_role_holder(role, holder);
+        
+        emit_cpi!(RoleUpdated { role, holder });
+        msg!("Role {:?} assigned to: {}", role, holder);
+        Ok(())
+    }
+
+    /// Revoke an operational role, leaving it unassigned (owner only)
+    pub fn revoke_role(ctx: Context<ManageRole>, role: Role) -> Result<()> {
+        ctx.accounts.config.set_role_holder(role, Pubkey::default());
+        
+        emit_cpi!(RoleUpdated {
+            role,
+            holder: Pubkey::default(),
+        });
+        msg!("Role {:?} revoked", role);
+        Ok(())
+    }
+
+    /// Attest that a wallet belongs to a verified person, or renew its attestation.
+    /// Required to create a reputation and request loans while an attester is assigned
+    /// (identity attester only)
+    pub fn attest_identity(ctx: Context<AttestIdentity>, expires_at: i64) -> Result<()> {
+        let attestation = &mut ctx.accounts.identity_attestation;
+        let clock = Clock::get()?;
+        
+        // Check the attestation is still live when issued; 0 means it never expires
+        require!(
+            expires_at == 0 || expires_at > clock.unix_timestamp,
+            ErrorCode::InvalidAttestationExpiry
+        );
+        
+        attestation.owner = ctx.accounts.owner.key();
+        attestation.attester = ctx.accounts.attester.key();
+        attestation.issued_at = clock.unix_timestamp;
+        attestation.expires_at = expires_at;
+        attestation.bump = ctx.bumps.identity_attestation;
+        
+        emit_cpi!(IdentityAttested {
+            owner: attestation.owner,
+            attester: attestation.attester,
+            expires_at,
+        });
+        msg!("Identity attested for: {}", attestation.owner);
+        Ok(())
+    }
+
+    /// Withdraw a wallet's identity attestation, closing it to the attester (identity attester only)
+    pub fn revoke_identity(ctx: Context<RevokeIdentity>) -> Result<()> {
+        let attestation = &ctx.accounts.identity_attestation;
+        
+        emit_cpi!(IdentityRevoked {
+            owner: attestation.owner,
+            attester: ctx.accounts.attester.key(),
+        });
+        msg!("Identity attestation revoked for: {}", attestation.owner);
+        Ok(())
+    }
+
     /// Create a Reputation NFT (Soulbound Token) for a new borrower
     pub fn create_reputation(ctx: Context<CreateReputation>) -> Result<()> {
         let reputation = &mut ctx.accounts.reputation;
         let clock = Clock::get()?;
         
+        // Check the owner is attested, if the protocol requires it
+        check_identity(
+            &ctx.accounts.config,
+            ctx.accounts.identity_attestation.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        
+        reputation.version = REPUTATION_VERSION;
         reputation.owner = ctx.accounts.owner.key();
         reputation.credit_score = INITIAL_CREDIT_SCORE;
         reputation.credit_tier = calculate_credit_tier(INITIAL_CREDIT_SCORE);
@@ -108,16 +549,128 @@ pub mod sollend_micro_protocol {
         reputation.last_updated = clock.unix_timestamp;
         reputation.is_frozen = false;
         reputation.bump = ctx.bumps.reputation;
+        reputation.archived_loans = 0;
+        reputation.archived_principal = 0;
+        reputation.archived_interest_paid = 0;
+        reputation.last_archived_loan_id = 0;
+        reputation.last_archived_at = 0;
+        reputation.reserved = [0; REPUTATION_RESERVED_BYTES];
         
+        emit_cpi!(ReputationCreated {
+            owner: reputation.owner,
+            credit_score: reputation.credit_score,
+            credit_tier: reputation.credit_tier,
+            created_at: reputation.created_at,
+        });
         msg!("Reputation NFT created for: {}", ctx.accounts.owner.key());
         msg!("Initial credit score: {}", INITIAL_CREDIT_SCORE);
         Ok(())
     }
 
-    /// Create a loan request
+    /// Create the borrower's loan index, which assigns loan IDs and lists open loans.
+    /// Borrowers who already picked loan IDs by hand start the counter above them
+    pub fn create_loan_index(ctx: Context<CreateLoanIndex>, next_loan_id: u64) -> Result<()> {
+        let loan_index = &mut ctx.accounts.loan_index;
+        
+        // Check the counter doesn't start on a hand-picked loan's ID
+        check_loan_id_unused(&ctx.accounts.next_loan)?;
+        
+        loan_index.borrower = ctx.accounts.borrower.key();
+        loan_index.next_loan_id = next_loan_id;
+        loan_index.active_loan_ids = Vec::new();
+        loan_index.bump = ctx.bumps.loan_index;
+        
+        emit_cpi!(LoanIndexCreated {
+            borrower: loan_index.borrower,
+            next_loan_id,
+        });
+        msg!("Loan index created for: {}", loan_index.borrower);
+        Ok(())
+    }
+
+    /// Create the lender's profile, which tracks their lending portfolio
+    pub fn create_lender_profile(ctx: Context<CreateLenderProfile>) -> Result<()> {
+        let lender_profile = &mut ctx.accounts.lender_profile;
+        let clock = Clock::get()?;
+        
+        lender_profile.lender = ctx.accounts.lender.key();
+        lender_profile.total_deployed = 0;
+        lender_profile.outstanding = 0;
+        lender_profile.interest_earned = 0;
+        lender_profile.losses = 0;
+        lender_profile.loans_funded = 0;
+        lender_profile.loans_repaid = 0;
+        lender_profile.loans_defaulted = 0;
+        lender_profile.loans_by_tier = [0; 4];
+        lender_profile.created_at = clock.unix_timestamp;
+        lender_profile.last_updated = clock.unix_timestamp;
+        lender_profile.bump = ctx.bumps.lender_profile;
+        
+        emit_cpi!(LenderProfileCreated {
+            lender: lender_profile.lender,
+            created_at: lender_profile.created_at,
+        });
+        msg!("Lender profile created for: {}", lender_profile.lender);
+        Ok(())
+    }
+
+    /// Pledge a bond behind the borrower's next loan request. The bond backs the loan up
+    /// to what the guarantor could borrow themselves, which is added to the borrower's
+    /// limits for that request. Bonds must be in a mint the risk manager has accepted
+    /// (borrower and guarantor co-sign)
+    pub fn guarantee_loan(ctx: Context<GuaranteeLoan>, bond: u64) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let guarantor_reputation = &ctx.accounts.guarantor_reputation;
+        let guarantee = &mut ctx.accounts.guarantee;
+        let clock = Clock::get()?;
+        
+        // Check new requests are not paused
+        require!(!config.is_paused(PAUSE_NEW_REQUESTS), ErrorCode::ProtocolPaused);
+        
+        // Check the guarantor is someone else in good standing
+        require!(
+            ctx.accounts.guarantor.key() != ctx.accounts.borrower.key()
+                && !guarantor_reputation.is_frozen
+                && guarantor_reputation.credit_tier <= MIN_GUARANTOR_TIER,
+            ErrorCode::GuarantorIneligible
+        );
+        require!(bond > 0, ErrorCode::InvalidBond);
+        let backing = bond.min(get_max_borrow_amount(config, guarantor_reputation.credit_tier));
+        require!(backing > 0, ErrorCode::GuarantorIneligible);
+        
+        // Transfer the bond from the guarantor to the vault
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.guarantor_token_account.to_account_info(),
+            to: ctx.accounts.bond_vault.to_account_info(),
+            authority: ctx.accounts.guarantor.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, bond)?;
+        
+        guarantee.borrower = ctx.accounts.borrower.key();
+        guarantee.loan_id = ctx.accounts.loan_index.next_loan_id;
+        guarantee.guarantor = ctx.accounts.guarantor.key();
+        guarantee.bond = bond;
+        guarantee.backing = backing;
+        guarantee.attached = false;
+        guarantee.created_at = clock.unix_timestamp;
+        guarantee.bump = ctx.bumps.guarantee;
+        
+        emit_cpi!(GuaranteePledged {
+            borrower: guarantee.borrower,
+            loan_id: guarantee.loan_id,
+            guarantor: guarantee.guarantor,
+            bond,
+            backing,
+        });
+        msg!("Guarantee pledged for loan {}: {} tokens", guarantee.loan_id, bond);
+        Ok(())
+    }
+
+    /// Create a loan request under the next ID from the borrower's loan index
     pub fn create_loan_request(
         ctx: Context<CreateLoanRequest>,
-        loan_id: u64,
         amount: u64,
         duration_seconds: i64,
         max_interest_rate_bps: u16,
@@ -125,22 +678,34 @@ pub mod sollend_micro_protocol {
         let config = &ctx.accounts.config;
         let reputation = &ctx.accounts.borrower_reputation;
         let loan = &mut ctx.accounts.loan;
+        let loan_index = &mut ctx.accounts.loan_index;
         let clock = Clock::get()?;
         
-        // Check protocol is not paused
-        require!(!config.is_paused, ErrorCode::ProtocolPaused);
+        // Check new requests are not paused
+        require!(!config.is_paused(PAUSE_NEW_REQUESTS), ErrorCode::ProtocolPaused);
         
         // Check reputation is not frozen
         require!(!reputation.is_frozen, ErrorCode::ReputationFrozen);
         
-        // Check borrowing limit based on credit tier
-        let max_borrow = get_max_borrow_amount(reputation.credit_tier);
+        // Check the borrower is still attested, if the protocol requires it
+        check_identity(
+            config,
+            ctx.accounts.identity_attestation.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        
+        // Check borrowing limit based on credit tier, raised by a guarantee pledged for this loan
+        let backing = ctx.accounts.guarantee.as_ref().map_or(0, |guarantee| guarantee.backing);
+        let max_borrow = get_max_borrow_amount(config, reputation.credit_tier) + backing;
         require!(amount <= max_borrow, ErrorCode::ExceedsMaxBorrowAmount);
         
+        // Check the borrower's aggregate limits
+        check_exposure(config, reputation, amount, backing)?;
+        
         // Check reasonable duration (5 seconds to 1 year for testing, 1 day minimum for production)
         // Note: In production, change minimum to 86400 (1 day)
         require!(
-            duration_seconds >= 5 && duration_seconds <= 31536000,
+            (5..=31536000).contains(&duration_seconds),
             ErrorCode::InvalidDuration
         );
         
@@ -156,6 +721,10 @@ pub mod sollend_micro_protocol {
             ErrorCode::InterestRateTooLow
         );
         
+        // Take the ID the loan PDA was derived from and track it as open
+        let loan_id = loan_index.assign()?;
+        
+        loan.version = LOAN_VERSION;
         loan.borrower = ctx.accounts.borrower.key();
         loan.loan_id = loan_id;
         loan.amount = amount;
@@ -171,8 +740,33 @@ pub mod sollend_micro_protocol {
         loan.repaid_at = 0;
         loan.lender = None;
         loan.repaid_amount = 0;
+        loan.withdrawal_deadline = 0;
+        loan.expires_at = clock.unix_timestamp + config.request_ttl();
+        loan.extension_count = 0;
+        loan.refinance_count = 0;
+        loan.refinanced_at = 0;
+        loan.rebate_policy = RebatePolicy::default();
+        loan.position_mint = Pubkey::default();
+        loan.indexed = true;
+        loan.guarantee_backing = backing;
         loan.bump = ctx.bumps.loan;
+        loan.reserved = [0; LOAN_RESERVED_BYTES];
+        
+        // The bond now stays locked until this loan settles
+        if let Some(guarantee) = ctx.accounts.guarantee.as_deref_mut() {
+            guarantee.attached = true;
+        }
         
+        emit_cpi!(LoanRequested {
+            borrower: loan.borrower,
+            loan_id,
+            amount,
+            duration_seconds,
+            max_interest_rate_bps,
+            suggested_interest_rate_bps: min_interest,
+            credit_tier: reputation.credit_tier,
+            expires_at: loan.expires_at,
+        });
         msg!("Loan request created: {} tokens", amount);
         msg!("Suggested interest rate: {} bps", min_interest);
         Ok(())
@@ -182,24 +776,47 @@ pub mod sollend_micro_protocol {
     pub fn fund_loan(
         ctx: Context<FundLoan>,
         interest_rate_bps: u16,
+        rebate_policy: RebatePolicy,
     ) -> Result<()> {
-        let config = &ctx.accounts.config;
+        let config = &mut ctx.accounts.config;
         let loan = &mut ctx.accounts.loan;
         let reputation = &mut ctx.accounts.borrower_reputation;
         let clock = Clock::get()?;
         
-        // Check protocol is not paused
-        require!(!config.is_paused, ErrorCode::ProtocolPaused);
+        // Check funding is not paused
+        require!(!config.is_paused(PAUSE_FUNDING), ErrorCode::ProtocolPaused);
         
         // Check loan is in requested state
         require!(loan.state == LoanState::Requested, ErrorCode::InvalidLoanState);
         
+        // Check the request has not expired
+        require!(
+            clock.unix_timestamp <= loan.request_expiry(config),
+            ErrorCode::LoanRequestExpired
+        );
+        
         // Check interest rate is within borrower's max
         require!(
             interest_rate_bps <= loan.max_interest_rate_bps,
             ErrorCode::InterestRateTooHigh
         );
         
+        // Check the early repayment terms
+        require!(
+            rebate_policy.min_interest_bps <= 10000 && rebate_policy.prepayment_fee_bps <= 10000,
+            ErrorCode::InvalidRebatePolicy
+        );
+
+        // Check the borrower's aggregate limits again; other requests may have been funded since
+        check_exposure(config, reputation, loan.amount, loan.guarantee_backing)?;
+
+        // Check the lender's exposure to this borrower and the protocol-wide debt limits
+        let exposure_ledger = &mut ctx.accounts.exposure_ledger;
+        let pair_exposure = &mut ctx.accounts.pair_exposure;
+        pair_exposure.open(ctx.accounts.lender.key(), loan.borrower, ctx.bumps.pair_exposure);
+        pair_exposure.record_funding(loan.amount, exposure_ledger.max_pair_exposure)?;
+        exposure_ledger.record_funding(reputation.credit_tier, loan.amount)?;
+        
         // Initialize escrow account
         let escrow = &mut ctx.accounts.escrow;
         escrow.loan_id = loan.loan_id;
@@ -216,25 +833,57 @@ pub mod sollend_micro_protocol {
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
         token::transfer(cpi_ctx, loan.amount)?;
         
+        // Mint the lender's position token; whoever holds it is repaid
+        mint_position(
+            &ctx.accounts.token_program,
+            &ctx.accounts.position_mint,
+            &ctx.accounts.lender_position_account,
+            &ctx.accounts.escrow,
+        )?;
+        
         // Update loan state
         loan.lender = Some(ctx.accounts.lender.key());
+        loan.escrow_payer = ctx.accounts.lender.key();
         loan.actual_interest_rate_bps = interest_rate_bps;
         loan.funded_amount = loan.amount;
         loan.state = LoanState::Funded;
         loan.funded_at = clock.unix_timestamp;
         loan.due_date = clock.unix_timestamp + loan.duration_seconds;
+        loan.withdrawal_deadline = clock.unix_timestamp + config.withdrawal_window();
+        loan.rebate_policy = rebate_policy;
+        loan.position_mint = ctx.accounts.position_mint.key();
+        loan.lender_profiled = true;
+        loan.concentration_tracked = true;
+        loan.funded_tier = reputation.credit_tier;
         
         // Update reputation stats
         reputation.active_loans += 1;
         reputation.total_loans += 1;
         reputation.total_borrowed += loan.amount;
+        reputation.outstanding_principal += loan.amount;
         reputation.last_updated = clock.unix_timestamp;
         
+        // Update the lender's portfolio
+        ctx.accounts.lender_profile.record_funding(
+            loan.amount,
+            reputation.credit_tier,
+            clock.unix_timestamp
+        );
+        
         // Update config stats
-        let config_mut = &mut ctx.accounts.config_account;
-        config_mut.total_loans_issued += 1;
-        config_mut.total_volume += loan.amount;
+        config.total_loans_issued += 1;
+        config.total_volume += loan.amount;
         
+        emit_cpi!(LoanFunded {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            lender: ctx.accounts.lender.key(),
+            amount: loan.amount,
+            interest_rate_bps,
+            funded_at: loan.funded_at,
+            due_date: loan.due_date,
+            position_mint: loan.position_mint,
+        });
         msg!("Loan funded by: {}", ctx.accounts.lender.key());
         msg!("Interest rate: {} bps", interest_rate_bps);
         Ok(())
@@ -242,8 +891,12 @@ pub mod sollend_micro_protocol {
 
     /// Withdraw loan funds (borrower action)
     pub fn withdraw_loan(ctx: Context<WithdrawLoan>) -> Result<()> {
+        let config = &ctx.accounts.config;
         let loan = &mut ctx.accounts.loan;
         
+        // Check withdrawals are not paused
+        require!(!config.is_paused(PAUSE_WITHDRAWALS), ErrorCode::ProtocolPaused);
+        
         // Check loan is funded
         require!(loan.state == LoanState::Funded, ErrorCode::InvalidLoanState);
         
@@ -270,6 +923,11 @@ pub mod sollend_micro_protocol {
         // Update loan state
         loan.state = LoanState::Active;
         
+        emit_cpi!(LoanWithdrawn {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            amount: loan.amount,
+        });
         msg!("Loan funds withdrawn: {} tokens", loan.amount);
         Ok(())
     }
@@ -281,21 +939,24 @@ pub mod sollend_micro_protocol {
         let reputation = &mut ctx.accounts.borrower_reputation;
         let clock = Clock::get()?;
         
+        // Check repayments are not paused
+        require!(!config.is_paused(PAUSE_REPAYMENTS), ErrorCode::ProtocolPaused);
+        
         // Check loan is active
         require!(loan.state == LoanState::Active, ErrorCode::InvalidLoanState);
         
-        // Calculate total repayment (principal + interest - protocol fee)
-        let interest_amount = (loan.amount as u128)
-            .checked_mul(loan.actual_interest_rate_bps as u128)
-            .unwrap()
-            .checked_div(10000)
-            .unwrap() as u64;
+        // Check the repayment goes to whoever holds the lender's position
+        let holder = loan.position_holder(ctx.accounts.position_token_account.as_deref())?;
+        require!(
+            ctx.accounts.lender_token_account.owner == holder,
+            ErrorCode::LenderMismatch
+        );
         
-        let protocol_fee = (interest_amount as u128)
-            .checked_mul(config.protocol_fee_bps as u128)
-            .unwrap()
-            .checked_div(10000)
-            .unwrap() as u64;
+        // Calculate total repayment (principal + interest - protocol fee); early
+        // repayments only owe what the loan's rebate policy charges
+        let full_interest = calculate_interest(loan.amount, loan.actual_interest_rate_bps);
+        let interest_amount = loan.interest_due(clock.unix_timestamp);
+        let protocol_fee = calculate_protocol_fee(interest_amount, config.protocol_fee_bps);
         
         let lender_amount = loan.amount + interest_amount - protocol_fee;
         let total_repayment = loan.amount + interest_amount;
@@ -321,39 +982,36 @@ pub mod sollend_micro_protocol {
             token::transfer(cpi_ctx_fee, protocol_fee)?;
         }
         
-        // Update loan state
-        loan.state = LoanState::Repaid;
-        loan.repaid_at = clock.unix_timestamp;
-        loan.repaid_amount = total_repayment;
-        
-        // Determine if payment is on time or late
-        let is_late = clock.unix_timestamp > loan.due_date;
-        
-        // Update reputation
-        reputation.active_loans = reputation.active_loans.saturating_sub(1);
-        reputation.completed_loans += 1;
-        reputation.total_repaid += total_repayment;
-        
-        if is_late {
-            reputation.late_payments += 1;
-            reputation.credit_score = apply_credit_adjustment(
-                reputation.credit_score,
-                LATE_PAYMENT_PENALTY
-            );
-            msg!("Late payment - credit score decreased");
-        } else {
-            reputation.on_time_payments += 1;
-            reputation.credit_score = apply_credit_adjustment(
-                reputation.credit_score,
-                ON_TIME_PAYMENT_BONUS
-            );
-            msg!("On-time payment - credit score increased");
+        // Close the loan out of the borrower's index, the lender's portfolio and the
+        // concentration limits
+        release_loan_id(loan, ctx.accounts.loan_index.as_deref_mut())?;
+        if let Some(lender_profile) = settled_lender_profile(loan, ctx.accounts.lender_profile.as_deref_mut())? {
+            lender_profile.record_repayment(loan.amount, interest_amount - protocol_fee, clock.unix_timestamp);
         }
+        release_concentration(
+            loan,
+            ctx.accounts.exposure_ledger.as_deref_mut(),
+            ctx.accounts.pair_exposure.as_deref_mut()
+        )?;
         
-        // Update credit tier based on new score
-        reputation.credit_tier = calculate_credit_tier(reputation.credit_score);
-        reputation.last_updated = clock.unix_timestamp;
+        // Mark the loan repaid and score the borrower on whether it was on time
+        let previous_score = reputation.credit_score;
+        let previous_tier = reputation.credit_tier;
+        let is_late = record_repayment(config, loan, reputation, total_repayment, clock.unix_timestamp);
         
+        emit_cpi!(LoanRepaid {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            lender: holder,
+            principal: loan.amount,
+            interest: interest_amount,
+            rebate: full_interest - interest_amount,
+            protocol_fee,
+            lender_amount,
+            is_late,
+            repaid_at: loan.repaid_at,
+            score_change: ScoreChange::new(previous_score, previous_tier, reputation),
+        });
         msg!("Loan repaid: {} tokens (principal) + {} tokens (interest)", loan.amount, interest_amount);
         msg!("New credit score: {}, tier: {}", reputation.credit_score, reputation.credit_tier);
         Ok(())
@@ -366,6 +1024,9 @@ pub mod sollend_micro_protocol {
         let config = &mut ctx.accounts.config;
         let clock = Clock::get()?;
         
+        // Check defaults are not paused
+        require!(!config.is_paused(PAUSE_DEFAULTS), ErrorCode::ProtocolPaused);
+        
         // Check loan is active
         require!(loan.state == LoanState::Active, ErrorCode::InvalidLoanState);
         
@@ -374,27 +1035,51 @@ pub mod sollend_micro_protocol {
         
         // Update loan state
         loan.state = LoanState::Defaulted;
+        release_loan_id(loan, ctx.accounts.loan_index.as_deref_mut())?;
+
+        // Write the principal off in the lender's portfolio
+        if let Some(lender_profile) = settled_lender_profile(loan, ctx.accounts.lender_profile.as_deref_mut())? {
+            lender_profile.record_default(loan.amount, clock.unix_timestamp);
+        }
+        release_concentration(
+            loan,
+            ctx.accounts.exposure_ledger.as_deref_mut(),
+            ctx.accounts.pair_exposure.as_deref_mut()
+        )?;
         
         // Apply heavy penalty to reputation
+        let previous_score = reputation.credit_score;
+        let previous_tier = reputation.credit_tier;
         reputation.active_loans = reputation.active_loans.saturating_sub(1);
+        reputation.outstanding_principal = reputation.outstanding_principal.saturating_sub(loan.amount);
         reputation.defaulted_loans += 1;
         reputation.credit_score = apply_credit_adjustment(
             reputation.credit_score,
-            DEFAULT_PENALTY
+            config.default_penalty
         );
-        reputation.credit_tier = calculate_credit_tier(reputation.credit_score);
+        reputation.refresh_tier();
         reputation.is_frozen = true; // Freeze reputation for defaulters
         reputation.last_updated = clock.unix_timestamp;
         
         // Update config stats
         config.total_defaults += 1;
         
+        emit_cpi!(LoanDefaulted {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            lender: loan.lender.unwrap_or_default(),
+            principal: loan.amount,
+            interest_rate_bps: loan.actual_interest_rate_bps,
+            due_date: loan.due_date,
+            oracle: ctx.accounts.oracle_authority.key(),
+            score_change: ScoreChange::new(previous_score, previous_tier, reputation),
+        });
         msg!("Loan marked as defaulted");
         msg!("Borrower reputation frozen - credit score: {}", reputation.credit_score);
         Ok(())
     }
 
-    /// Unfreeze reputation (admin only, for rehabilitation)
+    /// Unfreeze reputation (reputation steward only, for rehabilitation)
     pub fn unfreeze_reputation(ctx: Context<UnfreezeReputation>) -> Result<()> {
         let reputation = &mut ctx.accounts.reputation;
         let clock = Clock::get()?;
@@ -402,11 +1087,133 @@ pub mod sollend_micro_protocol {
         reputation.is_frozen = false;
         reputation.last_updated = clock.unix_timestamp;
         
+        emit_cpi!(ReputationUnfrozen {
+            owner: reputation.owner,
+            steward: ctx.accounts.reputation_steward.key(),
+            credit_score: reputation.credit_score,
+        });
         msg!("Reputation unfrozen for: {}", reputation.owner);
         Ok(())
     }
 
-    /// Cancel a loan request (borrower only, before funding)
+    /// Stake some of the voucher's score on another user, raising the vouchee's effective
+    /// score by the stake and lowering the voucher's by the same amount (voucher action)
+    pub fn issue_vouch(ctx: Context<IssueVouch>, stake: u16) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let voucher = &mut ctx.accounts.voucher_reputation;
+        let vouchee = &mut ctx.accounts.vouchee_reputation;
+        let vouch = &mut ctx.accounts.vouch;
+        let clock = Clock::get()?;
+        
+        // Check the voucher stands high on their own history, not on vouches they received,
+        // after what they already have staked on others
+        require!(voucher.owner != vouchee.owner, ErrorCode::VoucherIneligible);
+        require!(
+            !voucher.is_frozen && voucher.credit_score.saturating_sub(voucher.staked_score) >= MIN_VOUCHER_SCORE,
+            ErrorCode::VoucherIneligible
+        );
+        require!(!vouchee.is_frozen, ErrorCode::ReputationFrozen);
+        require!(
+            stake > 0 && stake <= MAX_VOUCH_STAKE && voucher.staked_score + stake <= voucher.credit_score,
+            ErrorCode::InvalidVouchStake
+        );
+        
+        // Check both sides are under their vouch caps
+        require!(
+            voucher.vouches_given < config.vouches_given_limit()
+                && vouchee.vouches_received < config.vouches_received_limit(),
+            ErrorCode::VouchLimitReached
+        );
+        
+        vouch.voucher = voucher.owner;
+        vouch.vouchee = vouchee.owner;
+        vouch.stake = stake;
+        vouch.vouchee_defaults = vouchee.defaulted_loans;
+        vouch.created_at = clock.unix_timestamp;
+        vouch.bump = ctx.bumps.vouch;
+        
+        voucher.vouches_given += 1;
+        voucher.staked_score += stake;
+        voucher.refresh_tier();
+        voucher.last_updated = clock.unix_timestamp;
+        
+        let previous_score = vouchee.credit_score;
+        let previous_tier = vouchee.credit_tier;
+        vouchee.vouches_received += 1;
+        vouchee.vouched_score += stake;
+        vouchee.refresh_tier();
+        vouchee.last_updated = clock.unix_timestamp;
+        
+        emit_cpi!(VouchIssued {
+            voucher: vouch.voucher,
+            vouchee: vouch.vouchee,
+            stake,
+            score_change: ScoreChange::new(previous_score, previous_tier, vouchee),
+        });
+        msg!("Vouch issued for: {} ({} points)", vouch.vouchee, stake);
+        Ok(())
+    }
+
+    /// Withdraw a vouch while the vouchee has no loans outstanding or requested, returning
+    /// the stake to the voucher (voucher action)
+    pub fn revoke_vouch(ctx: Context<RevokeVouch>) -> Result<()> {
+        let vouch = &ctx.accounts.vouch;
+        let voucher = &mut ctx.accounts.voucher_reputation;
+        let vouchee = &mut ctx.accounts.vouchee_reputation;
+        let clock = Clock::get()?;
+        
+        // Check the vouch is not backing a loan or an open request, and has not been
+        // forfeited to a default; vouchees without a loan index read back as having none
+        require!(vouchee.active_loans == 0, ErrorCode::VouchInUse);
+        let loan_index_info = ctx.accounts.vouchee_loan_index.to_account_info();
+        if !loan_index_info.data_is_empty() {
+            let loan_index = BorrowerLoanIndex::try_deserialize(&mut &loan_index_info.try_borrow_data()?[..])?;
+            require!(loan_index.active_loan_ids.is_empty(), ErrorCode::VouchInUse);
+        }
+        require!(!vouch.slashable(vouchee), ErrorCode::VouchInUse);
+        
+        let previous_score = vouchee.credit_score;
+        let previous_tier = vouchee.credit_tier;
+        unwind_vouch(vouch, voucher, vouchee, clock.unix_timestamp);
+        
+        emit_cpi!(VouchRevoked {
+            voucher: vouch.voucher,
+            vouchee: vouch.vouchee,
+            stake: vouch.stake,
+            score_change: ScoreChange::new(previous_score, previous_tier, vouchee),
+        });
+        msg!("Vouch for {} revoked", vouch.vouchee);
+        Ok(())
+    }
+
+    /// Burn a vouch's stake from the voucher's score after the vouchee defaulted on a loan
+    /// (anyone; the vouch rent goes back to the voucher)
+    pub fn slash_vouch(ctx: Context<SlashVouch>) -> Result<()> {
+        let vouch = &ctx.accounts.vouch;
+        let voucher = &mut ctx.accounts.voucher_reputation;
+        let vouchee = &mut ctx.accounts.vouchee_reputation;
+        let clock = Clock::get()?;
+        
+        // Check the vouchee has defaulted since the vouch was issued
+        require!(vouch.slashable(vouchee), ErrorCode::VouchNotSlashable);
+        
+        let previous_score = voucher.credit_score;
+        let previous_tier = voucher.credit_tier;
+        unwind_vouch(vouch, voucher, vouchee, clock.unix_timestamp);
+        voucher.credit_score = apply_credit_adjustment(voucher.credit_score, -(vouch.stake as i16));
+        voucher.refresh_tier();
+        
+        emit_cpi!(VouchSlashed {
+            voucher: vouch.voucher,
+            vouchee: vouch.vouchee,
+            stake: vouch.stake,
+            score_change: ScoreChange::new(previous_score, previous_tier, voucher),
+        });
+        msg!("Vouch for {} slashed: voucher loses {} points", vouch.vouchee, vouch.stake);
+        Ok(())
+    }
+
+    /// Cancel an unfunded loan request and close it, refunding rent to the borrower
     pub fn cancel_loan_request(ctx: Context<CancelLoanRequest>) -> Result<()> {
         let loan = &mut ctx.accounts.loan;
         
@@ -415,60 +1222,1932 @@ pub mod sollend_micro_protocol {
         
         // Update loan state
         loan.state = LoanState::Cancelled;
+        release_loan_id(loan, ctx.accounts.loan_index.as_deref_mut())?;
         
+        emit_cpi!(LoanCancelled {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            amount: loan.amount,
+        });
         msg!("Loan request cancelled");
         Ok(())
     }
-}
-
-// Helper functions
-fn calculate_credit_tier(credit_score: u16) -> u8 {
-    if credit_score >= TIER_A_THRESHOLD {
-        CREDIT_TIER_A
-    } else if credit_score >= TIER_B_THRESHOLD {
-        CREDIT_TIER_B
-    } else if credit_score >= TIER_C_THRESHOLD {
-        CREDIT_TIER_C
-    } else {
-        CREDIT_TIER_D
-    }
-}
-
-fn calculate_interest_rate(credit_tier: u8, duration_seconds: i64) -> u16 {
-    let risk_premium = match credit_tier {
-        CREDIT_TIER_A => TIER_A_PREMIUM,
-        CREDIT_TIER_B => TIER_B_PREMIUM,
-        CREDIT_TIER_C => TIER_C_PREMIUM,
-        _ => TIER_D_PREMIUM,
-    };
-    
-    // Duration factor: longer loans = slightly higher rate
-    // Add 0.1% per month (30 days)
-    let duration_days = duration_seconds / 86400;
-    let duration_factor = (duration_days / 30) as u16 * 10; // 10 bps per month
-    
-    BASE_RATE + risk_premium + duration_factor
-}
 
-fn get_max_borrow_amount(credit_tier: u8) -> u64 {
-    match credit_tier {
-        CREDIT_TIER_A => TIER_A_MAX_BORROW,
-        CREDIT_TIER_B => TIER_B_MAX_BORROW,
-        CREDIT_TIER_C => TIER_C_MAX_BORROW,
-        _ => TIER_D_MAX_BORROW,
+    /// Push an active loan's due date back for extra interest (borrower and lender co-sign)
+    pub fn request_extension(
+        ctx: Context<RequestExtension>,
+        extension_seconds: i64,
+        extension_interest_rate_bps: u16,
+    ) -> Result<()> {
+        let loan = &mut ctx.accounts.loan;
+        
+        // Check loan is active
+        require!(loan.state == LoanState::Active, ErrorCode::InvalidLoanState);
+        
+        // Check the co-signer holds the lender's position
+        let holder = loan.position_holder(ctx.accounts.position_token_account.as_deref())?;
+        require!(ctx.accounts.lender.key() == holder, ErrorCode::LenderMismatch);
+        
+        // Check the extension is within limits
+        require!(
+            loan.extension_count < MAX_LOAN_EXTENSIONS,
+            ErrorCode::MaxExtensionsReached
+        );
+        require!(
+            (1..=31536000).contains(&extension_seconds),
+            ErrorCode::InvalidDuration
+        );
+        let interest_rate_bps = extended_interest_rate(loan.actual_interest_rate_bps, extension_interest_rate_bps)?;
+        
+        // Update loan terms
+        let previous_due_date = loan.due_date;
+        loan.due_date += extension_seconds;
+        loan.duration_seconds += extension_seconds;
+        loan.actual_interest_rate_bps = interest_rate_bps;
+        loan.extension_count += 1;
+        
+        emit_cpi!(LoanExtended {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            lender: ctx.accounts.lender.key(),
+            previous_due_date,
+            due_date: loan.due_date,
+            extension_interest_rate_bps,
+            interest_rate_bps,
+            extension_count: loan.extension_count,
+        });
+        msg!("Loan extended to {} (extension {})", loan.due_date, loan.extension_count);
+        Ok(())
     }
-}
-
-fn apply_credit_adjustment(current_score: u16, adjustment: i16) -> u16 {
-    let new_score = (current_score as i32) + (adjustment as i32);
-    new_score.max(MIN_CREDIT_SCORE as i32).min(MAX_CREDIT_SCORE as i32) as u16
-}
 
-// Account Contexts
-#[derive(Accounts)]
-pub struct InitializeConfig<'info> {
-    #[account(
-        init,
+    /// Pay off an active loan's lender and take it over at a new rate
+    /// (new lender and borrower co-sign). The outgoing position holder does not sign, so
+    /// they are paid exactly what repay_loan would pay them at this moment
+    pub fn refinance(
+        ctx: Context<Refinance>,
+        interest_rate_bps: u16,
+        rebate_policy: RebatePolicy,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let loan = &mut ctx.accounts.loan;
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let clock = Clock::get()?;
+        
+        // Check funding is not paused
+        require!(!config.is_paused(PAUSE_FUNDING), ErrorCode::ProtocolPaused);
+        
+        // Check loan is active
+        require!(loan.state == LoanState::Active, ErrorCode::InvalidLoanState);
+        
+        // Check the new rate is within the borrower's max and covers the tier's minimum
+        let min_rate = calculate_interest_rate(reputation.credit_tier, loan.duration_seconds);
+        require!(interest_rate_bps >= min_rate, ErrorCode::InterestRateTooLow);
+        require!(
+            interest_rate_bps <= loan.max_interest_rate_bps && interest_rate_bps <= 10000,
+            ErrorCode::InterestRateTooHigh
+        );
+        
+        // Check the new lender's early repayment terms
+        require!(
+            rebate_policy.min_interest_bps <= 10000 && rebate_policy.prepayment_fee_bps <= 10000,
+            ErrorCode::InvalidRebatePolicy
+        );
+        
+        // Check the payoff goes to whoever holds the lender's position
+        let previous_lender = loan.position_holder(ctx.accounts.position_token_account.as_deref())?;
+        require!(
+            ctx.accounts.lender_token_account.owner == previous_lender,
+            ErrorCode::LenderMismatch
+        );
+        
+        // Check the loan changes hands; refinancing to the holder or the lender of record would
+        // compound interest into principal and load their pair exposure twice
+        let new_lender = ctx.accounts.new_lender.key();
+        require!(
+            new_lender != previous_lender && loan.lender != Some(new_lender),
+            ErrorCode::SelfRefinance
+        );
+        
+        // Check the payoff is in the mint the loan was funded in
+        let loan_mint = ctx.accounts.escrow_token_account.mint;
+        require!(
+            ctx.accounts.lender_token_account.mint == loan_mint
+                && ctx.accounts.new_lender_token_account.mint == loan_mint,
+            ErrorCode::PayoffMintMismatch
+        );
+        
+        // The outgoing lender is owed what repaying now would pay them, under their own
+        // early repayment terms
+        let accrued_interest = loan.interest_due(clock.unix_timestamp);
+        let protocol_fee = calculate_protocol_fee(accrued_interest, config.protocol_fee_bps);
+        let payoff = loan.amount + accrued_interest;
+        
+        // Transfer payoff from the new lender to the outgoing lender
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.new_lender_token_account.to_account_info(),
+            to: ctx.accounts.lender_token_account.to_account_info(),
+            authority: ctx.accounts.new_lender.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, payoff - protocol_fee)?;
+        
+        // Transfer protocol fee on the accrued interest to protocol treasury
+        if protocol_fee > 0 {
+            let cpi_accounts_fee = Transfer {
+                from: ctx.accounts.new_lender_token_account.to_account_info(),
+                to: ctx.accounts.protocol_treasury.to_account_info(),
+                authority: ctx.accounts.new_lender.to_account_info(),
+            };
+            let cpi_ctx_fee = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts_fee);
+            token::transfer(cpi_ctx_fee, protocol_fee)?;
+        }
+        
+        // Move the loan from the outgoing lender's portfolio to the new lender's
+        if let Some(lender_profile) = settled_lender_profile(loan, ctx.accounts.lender_profile.as_deref_mut())? {
+            lender_profile.record_repayment(loan.amount, accrued_interest - protocol_fee, clock.unix_timestamp);
+        }
+        ctx.accounts.new_lender_profile.record_funding(payoff, reputation.credit_tier, clock.unix_timestamp);
+        
+        // Move the principal to the new lender's pair exposure, which must have room for it;
+        // loans funded before concentration limits existed start counting against them here
+        let exposure_ledger = &mut ctx.accounts.exposure_ledger;
+        if loan.concentration_tracked {
+            ctx.accounts
+                .pair_exposure
+                .as_deref_mut()
+                .ok_or(error!(ErrorCode::ConcentrationAccountsMissing))?
+                .release(loan.amount);
+            exposure_ledger.record_funding(loan.funded_tier, accrued_interest)?;
+        } else {
+            exposure_ledger.record_funding(reputation.credit_tier, payoff)?;
+            loan.funded_tier = reputation.credit_tier;
+        }
+        let new_pair_exposure = &mut ctx.accounts.new_pair_exposure;
+        new_pair_exposure.open(ctx.accounts.new_lender.key(), loan.borrower, ctx.bumps.new_pair_exposure);
+        new_pair_exposure.record_funding(payoff, exposure_ledger.max_pair_exposure)?;
+        
+        // The new lender fronted the interest owed so far, so the borrower now owes it
+        // to them as principal, within the same limit as new borrowing. The loan is
+        // already counted in active_loans, so only the principal limit applies here
+        reputation.outstanding_principal += accrued_interest;
+        require!(
+            reputation.outstanding_principal
+                <= config.max_exposure(reputation.credit_tier).saturating_add(loan.guarantee_backing),
+            ErrorCode::ExceedsMaxExposure
+        );
+        reputation.last_updated = clock.unix_timestamp;
+
+        // Hand the loan over to the new lender with a fresh position; the old position
+        // token no longer matches the loan and carries no claim
+        mint_position(
+            &ctx.accounts.token_program,
+            &ctx.accounts.new_position_mint,
+            &ctx.accounts.new_lender_position_account,
+            &ctx.accounts.escrow,
+        )?;
+        let previous_interest_rate_bps = loan.actual_interest_rate_bps;
+        loan.lender = Some(ctx.accounts.new_lender.key());
+        loan.amount = payoff;
+        loan.position_mint = ctx.accounts.new_position_mint.key();
+        loan.lender_profiled = true;
+        loan.concentration_tracked = true;
+        loan.actual_interest_rate_bps = interest_rate_bps;
+        loan.rebate_policy = rebate_policy;
+        loan.refinanced_at = clock.unix_timestamp;
+        loan.refinance_count += 1;
+        
+        emit_cpi!(LoanRefinanced {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            previous_lender,
+            lender: ctx.accounts.new_lender.key(),
+            payoff,
+            accrued_interest,
+            protocol_fee,
+            previous_interest_rate_bps,
+            interest_rate_bps,
+            position_mint: loan.position_mint,
+        });
+        msg!("Loan refinanced at {} bps, payoff: {} tokens", interest_rate_bps, payoff);
+        Ok(())
+    }
+
+    /// Return escrowed funds to the lender once the withdrawal deadline has passed
+    /// without the borrower withdrawing, and mark the loan expired (lender only)
+    pub fn reclaim_funding(ctx: Context<ReclaimFunding>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let loan = &mut ctx.accounts.loan;
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let clock = Clock::get()?;
+        
+        // Check loan is funded but not withdrawn
+        require!(loan.state == LoanState::Funded, ErrorCode::InvalidLoanState);
+        
+        // Check the signer holds the lender's position
+        let holder = loan.position_holder(ctx.accounts.position_token_account.as_deref())?;
+        require!(ctx.accounts.lender.key() == holder, ErrorCode::LenderMismatch);
+        
+        let deadline = loan.withdrawal_deadline(config);
+        require!(clock.unix_timestamp > deadline, ErrorCode::WithdrawalWindowOpen);
+        
+        // Transfer tokens from escrow back to the lender
+        let borrower_key = loan.borrower;
+        let loan_id = loan.loan_id.to_le_bytes();
+        let seeds = &[
+            ESCROW_SEED,
+            borrower_key.as_ref(),
+            loan_id.as_ref(),
+            &[ctx.accounts.escrow.bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow_token_account.to_account_info(),
+            to: ctx.accounts.lender_token_account.to_account_info(),
+            authority: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, loan.funded_amount)?;
+        
+        // Update loan state
+        loan.state = LoanState::Expired;
+        release_loan_id(loan, ctx.accounts.loan_index.as_deref_mut())?;
+        
+        // The funding is back with the lender and no longer outstanding
+        if let Some(lender_profile) = settled_lender_profile(loan, ctx.accounts.lender_profile.as_deref_mut())? {
+            lender_profile.record_reclaim(loan.funded_amount, clock.unix_timestamp);
+        }
+        release_concentration(
+            loan,
+            ctx.accounts.exposure_ledger.as_deref_mut(),
+            ctx.accounts.pair_exposure.as_deref_mut()
+        )?;
+        
+        // Nothing was borrowed; only the (optional) unwithdrawn penalty applies
+        let previous_score = reputation.credit_score;
+        let previous_tier = reputation.credit_tier;
+        reputation.active_loans = reputation.active_loans.saturating_sub(1);
+        reputation.total_loans = reputation.total_loans.saturating_sub(1);
+        reputation.total_borrowed = reputation.total_borrowed.saturating_sub(loan.funded_amount);
+        reputation.outstanding_principal = reputation.outstanding_principal.saturating_sub(loan.funded_amount);
+        reputation.credit_score = apply_credit_adjustment(
+            reputation.credit_score,
+            config.unwithdrawn_penalty
+        );
+        reputation.refresh_tier();
+        reputation.last_updated = clock.unix_timestamp;
+        
+        emit_cpi!(LoanFundingReclaimed {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            lender: ctx.accounts.lender.key(),
+            amount: loan.funded_amount,
+            withdrawal_deadline: deadline,
+            score_change: ScoreChange::new(previous_score, previous_tier, reputation),
+        });
+        msg!("Unwithdrawn funding reclaimed: {} tokens", loan.funded_amount);
+        Ok(())
+    }
+
+    /// Close a repaid, defaulted or expired loan and its escrow, refunding rent to whoever paid it
+    /// (loan to the borrower, escrow to the lender). The loan is first folded into the
+    /// borrower's archived history and the config's archived stats (anyone)
+    pub fn close_loan(ctx: Context<CloseLoan>) -> Result<()> {
+        let loan = &ctx.accounts.loan;
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        
+        // Only settled loans can be closed
+        require!(
+            matches!(loan.state, LoanState::Repaid | LoanState::Defaulted | LoanState::Expired),
+            ErrorCode::InvalidLoanState
+        );
+        
+        // Guaranteed loans stay on record until their bond is released or claimed
+        if loan.guarantee_backing > 0 {
+            let guarantee = ctx.accounts.guarantee.as_ref().ok_or(error!(ErrorCode::GuaranteeOutstanding))?;
+            require!(guarantee.data_is_empty(), ErrorCode::GuaranteeOutstanding);
+        }
+        
+        // Archive the loan before its account goes away; expired loans were never borrowed
+        let interest_paid = loan.repaid_amount.saturating_sub(loan.amount);
+        let principal = if loan.state == LoanState::Expired { 0 } else { loan.funded_amount };
+        reputation.archived_loans += 1;
+        reputation.archived_principal += principal;
+        reputation.archived_interest_paid += interest_paid;
+        reputation.last_archived_loan_id = loan.loan_id;
+        reputation.last_archived_at = clock.unix_timestamp;
+        
+        config.archived_loans_issued += 1;
+        config.archived_volume += loan.funded_amount;
+        if loan.state == LoanState::Defaulted {
+            config.archived_defaults += 1;
+        }
+        
+        // Close the (drained) escrow token account back to whoever paid for it
+        let borrower_key = ctx.accounts.borrower.key();
+        let loan_id = loan.loan_id.to_le_bytes();
+        let seeds = &[
+            ESCROW_SEED,
+            borrower_key.as_ref(),
+            loan_id.as_ref(),
+            &[ctx.accounts.escrow.bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow_token_account.to_account_info(),
+            destination: ctx.accounts.escrow_payer.to_account_info(),
+            authority: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+        
+        emit_cpi!(LoanClosed {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            lender: loan.lender.unwrap_or_default(),
+            state: loan.state.clone(),
+            principal: loan.funded_amount,
+            interest_paid,
+            closed_at: clock.unix_timestamp,
+        });
+        msg!("Loan {} closed and archived", loan.loan_id);
+        Ok(())
+    }
+
+    /// Close an unfunded loan request past its expiry, refunding rent to the borrower (anyone)
+    pub fn expire_request(ctx: Context<ExpireRequest>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let loan = &mut ctx.accounts.loan;
+        let clock = Clock::get()?;
+        
+        // Check loan is still an open request
+        require!(loan.state == LoanState::Requested, ErrorCode::InvalidLoanState);
+        
+        // Check the request has expired
+        let expires_at = loan.request_expiry(config);
+        require!(clock.unix_timestamp > expires_at, ErrorCode::LoanRequestNotExpired);
+        
+        release_loan_id(loan, ctx.accounts.loan_index.as_deref_mut())?;
+        
+        emit_cpi!(LoanRequestExpired {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            amount: loan.amount,
+            expires_at,
+        });
+        msg!("Loan request {} expired", loan.loan_id);
+        Ok(())
+    }
+
+    /// Return a guarantor's bond once it backs nothing: the pledge was never used, or the
+    /// loan was repaid, reclaimed or closed unfunded (anyone; bond and rent go to the guarantor)
+    pub fn release_guarantee(ctx: Context<ReleaseGuarantee>) -> Result<()> {
+        let guarantee = &ctx.accounts.guarantee;
+        
+        // Check the loan no longer depends on the bond; closed loans read back as None
+        let loan_info = ctx.accounts.loan.to_account_info();
+        let loan = if loan_info.data_is_empty() {
+            None
+        } else {
+            Some(LoanAccount::try_deserialize(&mut &loan_info.try_borrow_data()?[..])?)
+        };
+        require!(guarantee.releasable(loan.as_ref()), ErrorCode::GuaranteeLocked);
+        
+        pay_out_bond(
+            &ctx.accounts.token_program,
+            guarantee,
+            &ctx.accounts.bond_vault,
+            &ctx.accounts.guarantor_token_account,
+            &ctx.accounts.guarantor,
+        )?;
+        
+        emit_cpi!(GuaranteeReleased {
+            borrower: guarantee.borrower,
+            loan_id: guarantee.loan_id,
+            guarantor: guarantee.guarantor,
+            bond: guarantee.bond,
+        });
+        msg!("Guarantee for loan {} released", guarantee.loan_id);
+        Ok(())
+    }
+
+    /// Pay a defaulted loan's guarantee bond to the holder of its lender position and
+    /// penalize the guarantor (anyone; the bond goes to the holder, rent to the guarantor)
+    pub fn claim_guarantee(ctx: Context<ClaimGuarantee>) -> Result<()> {
+        let loan = &ctx.accounts.loan;
+        let guarantee = &ctx.accounts.guarantee;
+        let reputation = &mut ctx.accounts.guarantor_reputation;
+        let clock = Clock::get()?;
+        
+        // Check the guarantee backs this loan and the loan defaulted
+        require!(
+            guarantee.attached && loan.state == LoanState::Defaulted,
+            ErrorCode::InvalidLoanState
+        );
+        
+        // Check the bond goes to whoever holds the lender's position
+        let holder = loan.position_holder(ctx.accounts.position_token_account.as_deref())?;
+        require!(ctx.accounts.holder_token_account.owner == holder, ErrorCode::LenderMismatch);
+        
+        pay_out_bond(
+            &ctx.accounts.token_program,
+            guarantee,
+            &ctx.accounts.bond_vault,
+            &ctx.accounts.holder_token_account,
+            &ctx.accounts.guarantor,
+        )?;
+        
+        // The guarantor answers for the default with their score, but is not frozen
+        let previous_score = reputation.credit_score;
+        let previous_tier = reputation.credit_tier;
+        reputation.credit_score = apply_credit_adjustment(
+            reputation.credit_score,
+            GUARANTOR_DEFAULT_PENALTY
+        );
+        reputation.refresh_tier();
+        reputation.last_updated = clock.unix_timestamp;
+        
+        emit_cpi!(GuaranteeClaimed {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            guarantor: guarantee.guarantor,
+            holder,
+            bond: guarantee.bond,
+            score_change: ScoreChange::new(previous_score, previous_tier, reputation),
+        });
+        msg!("Guarantee for loan {} claimed by: {}", loan.loan_id, holder);
+        Ok(())
+    }
+
+    /// Open a revolving credit line for a tier A or B borrower. The lender commits the
+    /// limit up front; the borrower draws and repays within it, paying interest on the
+    /// drawn balance each statement (lender and borrower co-sign)
+    pub fn open_credit_line(ctx: Context<OpenCreditLine>, limit: u64, interest_rate_bps: u16) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let reputation = &ctx.accounts.borrower_reputation;
+        let credit_line = &mut ctx.accounts.credit_line;
+        let clock = Clock::get()?;
+        
+        // Check funding is not paused
+        require!(!config.is_paused(PAUSE_FUNDING), ErrorCode::ProtocolPaused);
+        
+        // Check the borrower is in good standing and in a top tier
+        require!(!reputation.is_frozen, ErrorCode::ReputationFrozen);
+        require!(reputation.credit_tier <= MAX_CREDIT_LINE_TIER, ErrorCode::CreditLineIneligible);
+        
+        // Check the borrower is attested, if the protocol requires it
+        check_identity(
+            config,
+            ctx.accounts.identity_attestation.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        
+        // Check the limit is within what the borrower could take in a single loan
+        require!(
+            limit > 0 && limit <= get_max_borrow_amount(config, reputation.credit_tier),
+            ErrorCode::InvalidCreditLimit
+        );
+        
+        // Check the rate covers what a loan over one statement period would charge
+        let min_rate = calculate_interest_rate(reputation.credit_tier, CREDIT_LINE_STATEMENT_PERIOD);
+        require!(interest_rate_bps >= min_rate, ErrorCode::InterestRateTooLow);
+        require!(interest_rate_bps <= 10000, ErrorCode::InterestRateTooHigh);
+        
+        // Transfer the limit from the lender to the line's vault
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.lender_token_account.to_account_info(),
+            to: ctx.accounts.line_vault.to_account_info(),
+            authority: ctx.accounts.lender.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, limit)?;
+        
+        credit_line.lender = ctx.accounts.lender.key();
+        credit_line.borrower = ctx.accounts.borrower.key();
+        credit_line.mint = ctx.accounts.mint.key();
+        credit_line.limit = limit;
+        credit_line.drawn = 0;
+        credit_line.interest_rate_bps = interest_rate_bps;
+        credit_line.accrued_interest = 0;
+        credit_line.interest_due = 0;
+        credit_line.last_billed = 0;
+        credit_line.past_due = false;
+        credit_line.last_accrued_at = clock.unix_timestamp;
+        credit_line.statement_ends_at = clock.unix_timestamp + CREDIT_LINE_STATEMENT_PERIOD;
+        credit_line.statements_closed = 0;
+        credit_line.missed_statements = 0;
+        credit_line.opened_at = clock.unix_timestamp;
+        credit_line.funded_tier = reputation.credit_tier;
+        credit_line.bump = ctx.bumps.credit_line;
+        
+        emit_cpi!(CreditLineOpened {
+            lender: credit_line.lender,
+            borrower: credit_line.borrower,
+            mint: credit_line.mint,
+            limit,
+            interest_rate_bps,
+            statement_ends_at: credit_line.statement_ends_at,
+        });
+        msg!("Credit line opened for {}: {} tokens", credit_line.borrower, limit);
+        Ok(())
+    }
+
+    /// Draw from a credit line into the borrower's token account (borrower only)
+    pub fn draw_credit_line(ctx: Context<DrawCreditLine>, amount: u64) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let credit_line = &mut ctx.accounts.credit_line;
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let clock = Clock::get()?;
+        
+        // Check withdrawals are not paused
+        require!(!config.is_paused(PAUSE_WITHDRAWALS), ErrorCode::ProtocolPaused);
+        
+        // Check the borrower is still in good standing and in a top tier
+        require!(!reputation.is_frozen, ErrorCode::ReputationFrozen);
+        require!(reputation.credit_tier <= MAX_CREDIT_LINE_TIER, ErrorCode::CreditLineIneligible);
+        
+        // Check the borrower is still attested, if the protocol requires it
+        check_identity(
+            config,
+            ctx.accounts.identity_attestation.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        
+        // Check the current statement is open and nothing from a missed one is unpaid
+        require!(clock.unix_timestamp < credit_line.statement_ends_at, ErrorCode::StatementPending);
+        require!(!credit_line.past_due, ErrorCode::PastDueBalance);
+        
+        // Check the draw fits the line and the borrower's aggregate limits. A line counts as
+        // one active loan while it has a balance, so only the draw that opens one is counted
+        require!(amount > 0 && amount <= credit_line.available(), ErrorCode::CreditLimitExceeded);
+        let opens_balance = credit_line.drawn == 0;
+        if opens_balance {
+            check_exposure(config, reputation, amount, 0)?;
+        } else {
+            require!(
+                reputation.outstanding_principal.saturating_add(amount) <= config.max_exposure(reputation.credit_tier),
+                ErrorCode::ExceedsMaxExposure
+            );
+        }
+        
+        // Check the draw fits the concentration limits
+        let exposure_ledger = &mut ctx.accounts.exposure_ledger;
+        let pair_exposure = &mut ctx.accounts.pair_exposure;
+        pair_exposure.open(credit_line.lender, credit_line.borrower, ctx.bumps.pair_exposure);
+        pair_exposure.record_draw(amount, exposure_ledger.max_pair_exposure, opens_balance)?;
+        exposure_ledger.record_funding(credit_line.funded_tier, amount)?;
+        
+        // Transfer tokens from the line's vault to the borrower
+        let lender_key = credit_line.lender;
+        let borrower_key = credit_line.borrower;
+        let seeds = &[
+            CREDIT_LINE_SEED,
+            lender_key.as_ref(),
+            borrower_key.as_ref(),
+            &[credit_line.bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.line_vault.to_account_info(),
+            to: ctx.accounts.borrower_token_account.to_account_info(),
+            authority: credit_line.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+        
+        // Interest up to now accrues on the old balance
+        credit_line.accrue(clock.unix_timestamp);
+        credit_line.drawn += amount;
+        
+        reputation.outstanding_principal += amount;
+        reputation.total_borrowed += amount;
+        if opens_balance {
+            reputation.active_loans += 1;
+        }
+        reputation.last_updated = clock.unix_timestamp;
+        
+        emit_cpi!(CreditLineDrawn {
+            lender: credit_line.lender,
+            borrower: credit_line.borrower,
+            amount,
+            drawn: credit_line.drawn,
+        });
+        msg!("Credit line drawn: {} tokens", amount);
+        Ok(())
+    }
+
+    /// Repay up to `amount` of a credit line: billed interest first, then interest accrued
+    /// this cycle, then principal, which goes back to the line's vault (borrower only)
+    pub fn repay_credit_line(ctx: Context<RepayCreditLine>, amount: u64) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let credit_line = &mut ctx.accounts.credit_line;
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let clock = Clock::get()?;
+        
+        // Check repayments are not paused
+        require!(!config.is_paused(PAUSE_REPAYMENTS), ErrorCode::ProtocolPaused);
+        
+        // Check interest goes to the line's lender
+        require!(
+            ctx.accounts.lender_token_account.owner == credit_line.lender,
+            ErrorCode::LenderMismatch
+        );
+        
+        // Repayments above what is owed are capped at the balance
+        credit_line.accrue(clock.unix_timestamp);
+        let payment = amount.min(credit_line.owed());
+        require!(payment > 0, ErrorCode::InvalidRepaymentAmount);
+        let (interest, principal) = credit_line.apply_payment(payment);
+        let protocol_fee = calculate_protocol_fee(interest, config.protocol_fee_bps);
+        
+        // Transfer interest less the protocol fee from borrower to lender
+        if interest > protocol_fee {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.borrower_token_account.to_account_info(),
+                to: ctx.accounts.lender_token_account.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, interest - protocol_fee)?;
+        }
+        
+        // Transfer protocol fee to protocol treasury
+        if protocol_fee > 0 {
+            let cpi_accounts_fee = Transfer {
+                from: ctx.accounts.borrower_token_account.to_account_info(),
+                to: ctx.accounts.protocol_treasury.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            };
+            let cpi_ctx_fee = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts_fee);
+            token::transfer(cpi_ctx_fee, protocol_fee)?;
+        }
+        
+        // Transfer principal back to the line's vault, where it can be drawn again
+        if principal > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.borrower_token_account.to_account_info(),
+                to: ctx.accounts.line_vault.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            token::transfer(cpi_ctx, principal)?;
+        }
+        
+        // Take repaid principal off the concentration limits, and the line off the active
+        // count once its balance is cleared
+        let clears_balance = principal > 0 && credit_line.drawn == 0;
+        ctx.accounts.exposure_ledger.release(credit_line.funded_tier, principal);
+        ctx.accounts.pair_exposure.release_draw(principal, clears_balance);
+        
+        // Score moves when the statement closes, not on each repayment
+        reputation.outstanding_principal = reputation.outstanding_principal.saturating_sub(principal);
+        if clears_balance {
+            reputation.active_loans = reputation.active_loans.saturating_sub(1);
+        }
+        reputation.total_repaid += payment;
+        reputation.last_updated = clock.unix_timestamp;
+        
+        emit_cpi!(CreditLineRepaid {
+            lender: credit_line.lender,
+            borrower: credit_line.borrower,
+            principal,
+            interest,
+            protocol_fee,
+            drawn: credit_line.drawn,
+            interest_due: credit_line.interest_due,
+        });
+        msg!("Credit line repaid: {} tokens (principal) + {} tokens (interest)", principal, interest);
+        Ok(())
+    }
+
+    /// Close a credit line's statement once its period is over: bill the interest accrued
+    /// during the cycle and score whether the previous statement was paid (anyone)
+    pub fn close_statement(ctx: Context<CloseStatement>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let credit_line = &mut ctx.accounts.credit_line;
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let clock = Clock::get()?;
+        
+        // Check repayments are not paused, since a paused borrower could not have paid
+        require!(!config.is_paused(PAUSE_REPAYMENTS), ErrorCode::ProtocolPaused);
+        
+        // Check the statement period is over
+        require!(clock.unix_timestamp >= credit_line.statement_ends_at, ErrorCode::StatementNotDue);
+        
+        credit_line.accrue(clock.unix_timestamp);
+        let outcome = credit_line.close_statement(clock.unix_timestamp);
+        
+        let previous_score = reputation.credit_score;
+        let previous_tier = reputation.credit_tier;
+        match outcome {
+            StatementOutcome::Paid => {
+                reputation.on_time_payments += 1;
+                reputation.credit_score = apply_credit_adjustment(
+                    reputation.credit_score,
+                    config.on_time_payment_bonus
+                );
+            }
+            StatementOutcome::Missed => {
+                reputation.late_payments += 1;
+                reputation.credit_score = apply_credit_adjustment(
+                    reputation.credit_score,
+                    config.late_payment_penalty
+                );
+            }
+            StatementOutcome::Unused => {}
+        }
+        reputation.refresh_tier();
+        reputation.last_updated = clock.unix_timestamp;
+        
+        emit_cpi!(StatementClosed {
+            lender: credit_line.lender,
+            borrower: credit_line.borrower,
+            outcome,
+            billed: credit_line.last_billed,
+            interest_due: credit_line.interest_due,
+            drawn: credit_line.drawn,
+            statement_ends_at: credit_line.statement_ends_at,
+            score_change: ScoreChange::new(previous_score, previous_tier, reputation),
+        });
+        msg!("Statement {} closed for {}", credit_line.statements_closed, credit_line.borrower);
+        Ok(())
+    }
+
+    /// Close a credit line with nothing owed, returning the undrawn limit and rent to the
+    /// lender (lender only)
+    pub fn close_credit_line(ctx: Context<CloseCreditLine>) -> Result<()> {
+        let credit_line = &mut ctx.accounts.credit_line;
+        let clock = Clock::get()?;
+        
+        // Check the borrower owes nothing, including interest accrued since the last statement
+        credit_line.accrue(clock.unix_timestamp);
+        require!(credit_line.owed() == 0, ErrorCode::CreditLineOutstanding);
+        
+        let lender_key = credit_line.lender;
+        let borrower_key = credit_line.borrower;
+        let seeds = &[
+            CREDIT_LINE_SEED,
+            lender_key.as_ref(),
+            borrower_key.as_ref(),
+            &[credit_line.bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        let returned = ctx.accounts.line_vault.amount;
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.line_vault.to_account_info(),
+            to: ctx.accounts.lender_token_account.to_account_info(),
+            authority: credit_line.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token::transfer(cpi_ctx, returned)?;
+        
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.line_vault.to_account_info(),
+            destination: ctx.accounts.lender.to_account_info(),
+            authority: credit_line.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+        
+        emit_cpi!(CreditLineClosed {
+            lender: lender_key,
+            borrower: borrower_key,
+            returned,
+            statements_closed: credit_line.statements_closed,
+            missed_statements: credit_line.missed_statements,
+        });
+        msg!("Credit line closed for {}", borrower_key);
+        Ok(())
+    }
+
+    /// Pre-approve a borrower to draw loans from the lender's token account, up to
+    /// `allowance` at a fixed rate and term until `expires_at`. Nothing moves until the
+    /// borrower draws; the lender approves the delegation's delegate PDA on their token
+    /// account to cover it. Granting again replaces the terms (lender only)
+    pub fn delegate_credit(
+        ctx: Context<DelegateCredit>,
+        allowance: u64,
+        interest_rate_bps: u16,
+        duration_seconds: i64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let delegation = &mut ctx.accounts.credit_delegation;
+        let clock = Clock::get()?;
+        
+        // Check funding is not paused
+        require!(!config.is_paused(PAUSE_FUNDING), ErrorCode::ProtocolPaused);
+        
+        // Check the terms
+        require!(
+            allowance > 0 && expires_at > clock.unix_timestamp,
+            ErrorCode::InvalidDelegationTerms
+        );
+        require!(
+            (5..=31536000).contains(&duration_seconds),
+            ErrorCode::InvalidDuration
+        );
+        require!(interest_rate_bps <= 10000, ErrorCode::InterestRateTooHigh);
+        
+        delegation.lender = ctx.accounts.lender.key();
+        delegation.borrower = ctx.accounts.borrower.key();
+        delegation.mint = ctx.accounts.mint.key();
+        delegation.allowance = allowance;
+        delegation.interest_rate_bps = interest_rate_bps;
+        delegation.duration_seconds = duration_seconds;
+        delegation.expires_at = expires_at;
+        delegation.granted_at = clock.unix_timestamp;
+        delegation.bump = ctx.bumps.credit_delegation;
+        
+        emit_cpi!(CreditDelegated {
+            lender: delegation.lender,
+            borrower: delegation.borrower,
+            mint: delegation.mint,
+            allowance,
+            interest_rate_bps,
+            duration_seconds,
+            expires_at,
+        });
+        msg!("Credit delegated to {}: {} tokens", delegation.borrower, allowance);
+        Ok(())
+    }
+
+    /// Withdraw a credit delegation; loans already drawn are unaffected (lender only)
+    pub fn revoke_credit_delegation(ctx: Context<RevokeCreditDelegation>) -> Result<()> {
+        let delegation = &ctx.accounts.credit_delegation;
+        
+        emit_cpi!(CreditDelegationRevoked {
+            lender: delegation.lender,
+            borrower: delegation.borrower,
+            allowance: delegation.allowance,
+            total_drawn: delegation.total_drawn,
+        });
+        msg!("Credit delegation to {} revoked", delegation.borrower);
+        Ok(())
+    }
+
+    /// Draw a loan against a credit delegation. Creates the loan under the next ID from
+    /// the borrower's loan index and pays it straight from the lender's token account
+    /// through the delegation's delegate PDA, leaving it Active as if it had been funded
+    /// and withdrawn (borrower only)
+    pub fn draw_delegated_credit(ctx: Context<DrawDelegatedCredit>, amount: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let delegation = &mut ctx.accounts.credit_delegation;
+        let loan = &mut ctx.accounts.loan;
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let clock = Clock::get()?;
+        
+        // Check new requests, funding and withdrawals are not paused; a draw is all three
+        require!(
+            !config.is_paused(PAUSE_NEW_REQUESTS | PAUSE_FUNDING | PAUSE_WITHDRAWALS),
+            ErrorCode::ProtocolPaused
+        );
+        
+        // Check reputation is not frozen
+        require!(!reputation.is_frozen, ErrorCode::ReputationFrozen);
+        
+        // Check the borrower is still attested, if the protocol requires it
+        check_identity(
+            config,
+            ctx.accounts.identity_attestation.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        
+        // Check the draw is within the delegation and the borrower's own limits
+        delegation.record_draw(amount, clock.unix_timestamp)?;
+        require!(
+            amount <= get_max_borrow_amount(config, reputation.credit_tier),
+            ErrorCode::ExceedsMaxBorrowAmount
+        );
+        check_exposure(config, reputation, amount, 0)?;
+        
+        // Check the delegated rate still covers the borrower's tier
+        let min_interest = calculate_interest_rate(reputation.credit_tier, delegation.duration_seconds);
+        require!(
+            delegation.interest_rate_bps >= min_interest,
+            ErrorCode::InterestRateTooLow
+        );
+        
+        // Check the lender's exposure to this borrower and the protocol-wide debt limits
+        let exposure_ledger = &mut ctx.accounts.exposure_ledger;
+        let pair_exposure = &mut ctx.accounts.pair_exposure;
+        pair_exposure.open(delegation.lender, delegation.borrower, ctx.bumps.pair_exposure);
+        pair_exposure.record_funding(amount, exposure_ledger.max_pair_exposure)?;
+        exposure_ledger.record_funding(reputation.credit_tier, amount)?;
+        
+        // Check the lender has approved this delegation's delegate for the draw
+        let lender_token_account = &ctx.accounts.lender_token_account;
+        require!(
+            lender_token_account.delegate == COption::Some(ctx.accounts.delegate_authority.key())
+                && lender_token_account.delegated_amount >= amount,
+            ErrorCode::DelegateApprovalMissing
+        );
+        
+        // Transfer tokens from lender to borrower through the delegate
+        let delegation_key = delegation.key();
+        let seeds = &[DELEGATE_SEED, delegation_key.as_ref(), &[ctx.bumps.delegate_authority]];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = Transfer {
+            from: lender_token_account.to_account_info(),
+            to: ctx.accounts.borrower_token_account.to_account_info(),
+            authority: ctx.accounts.delegate_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+        
+        // The escrow holds nothing but lets the loan be refinanced and closed like any other
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.loan_id = ctx.accounts.loan_index.next_loan_id;
+        escrow.borrower = delegation.borrower;
+        escrow.bump = ctx.bumps.escrow;
+        
+        // Take the ID the loan PDA was derived from and track it as open
+        let loan_id = ctx.accounts.loan_index.assign()?;
+        
+        loan.version = LOAN_VERSION;
+        loan.borrower = delegation.borrower;
+        loan.loan_id = loan_id;
+        loan.amount = amount;
+        loan.funded_amount = amount;
+        loan.duration_seconds = delegation.duration_seconds;
+        loan.max_interest_rate_bps = delegation.interest_rate_bps;
+        loan.actual_interest_rate_bps = delegation.interest_rate_bps;
+        loan.suggested_interest_rate_bps = min_interest;
+        loan.state = LoanState::Active;
+        loan.created_at = clock.unix_timestamp;
+        loan.funded_at = clock.unix_timestamp;
+        loan.due_date = clock.unix_timestamp + delegation.duration_seconds;
+        loan.repaid_at = 0;
+        loan.lender = Some(delegation.lender);
+        loan.repaid_amount = 0;
+        loan.withdrawal_deadline = clock.unix_timestamp;
+        loan.expires_at = clock.unix_timestamp;
+        loan.extension_count = 0;
+        loan.refinance_count = 0;
+        loan.refinanced_at = 0;
+        loan.rebate_policy = RebatePolicy::default();
+        loan.position_mint = Pubkey::default();
+        loan.indexed = true;
+        loan.lender_profiled = true;
+        loan.concentration_tracked = true;
+        loan.funded_tier = reputation.credit_tier;
+        loan.guarantee_backing = 0;
+        loan.escrow_payer = delegation.borrower;
+        loan.bump = ctx.bumps.loan;
+        loan.reserved = [0; LOAN_RESERVED_BYTES];
+        
+        // Update reputation stats
+        reputation.active_loans += 1;
+        reputation.total_loans += 1;
+        reputation.total_borrowed += amount;
+        reputation.outstanding_principal += amount;
+        reputation.last_updated = clock.unix_timestamp;
+        
+        // Update the lender's portfolio
+        ctx.accounts.lender_profile.record_funding(
+            amount,
+            reputation.credit_tier,
+            clock.unix_timestamp
+        );
+        
+        // Update config stats
+        config.total_loans_issued += 1;
+        config.total_volume += amount;
+        
+        emit_cpi!(DelegatedCreditDrawn {
+            borrower: loan.borrower,
+            loan_id,
+            lender: delegation.lender,
+            amount,
+            interest_rate_bps: loan.actual_interest_rate_bps,
+            due_date: loan.due_date,
+            allowance: delegation.allowance,
+        });
+        msg!("Delegated credit drawn: {} tokens as loan {}", amount, loan_id);
+        Ok(())
+    }
+
+    /// Opt a loan into automatic repayment (borrower only). The repayment is split into
+    /// `installments` even shares of principal plus full interest, which any keeper can
+    /// collect from `borrower_token_account` through the schedule's delegate PDA as each share
+    /// of the term comes up, for `keeper_tip` per installment. Collected installments wait
+    /// in the schedule's vault; the last one repays the loan from it. The borrower approves
+    /// the delegate for the schedule and the tips on that token account
+    pub fn enable_auto_repay(ctx: Context<EnableAutoRepay>, installments: u8, keeper_tip: u64) -> Result<()> {
+        let loan = &ctx.accounts.loan;
+        let auto_repay = &mut ctx.accounts.auto_repay;
+        let clock = Clock::get()?;
+        
+        // Check loan is active
+        require!(loan.state == LoanState::Active, ErrorCode::InvalidLoanState);
+        
+        // Check the schedule fits before the due date and the tip stays small
+        require!(
+            (1..=MAX_AUTO_REPAY_INSTALLMENTS).contains(&installments)
+                && keeper_tip <= MAX_KEEPER_TIP
+                && clock.unix_timestamp < loan.due_date,
+            ErrorCode::InvalidAutoRepaySchedule
+        );
+        
+        auto_repay.loan = loan.key();
+        auto_repay.borrower = loan.borrower;
+        auto_repay.loan_id = loan.loan_id;
+        auto_repay.mint = ctx.accounts.mint.key();
+        auto_repay.borrower_token_account = ctx.accounts.borrower_token_account.key();
+        auto_repay.installments = installments;
+        auto_repay.installments_paid = 0;
+        auto_repay.scheduled_total = loan.amount + calculate_interest(loan.amount, loan.actual_interest_rate_bps);
+        auto_repay.collected = 0;
+        auto_repay.keeper_tip = keeper_tip;
+        auto_repay.enabled_at = clock.unix_timestamp;
+        auto_repay.bump = ctx.bumps.auto_repay;
+        
+        emit_cpi!(AutoRepayEnabled {
+            borrower: auto_repay.borrower,
+            loan_id: auto_repay.loan_id,
+            installments,
+            scheduled_total: auto_repay.scheduled_total,
+            keeper_tip,
+            due_date: loan.due_date,
+        });
+        msg!("Auto-repay enabled for loan {}: {} installment(s)", auto_repay.loan_id, installments);
+        Ok(())
+    }
+
+    /// Collect a loan's next auto-repay installment from the borrower through the
+    /// schedule's delegate PDA, tipping the keeper. The last installment repays the loan from
+    /// the schedule's vault exactly like `repay_loan`, returns anything collected beyond
+    /// what it owed and closes the schedule (anyone)
+    pub fn execute_auto_repay(ctx: Context<ExecuteAutoRepay>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let auto_repay = &mut ctx.accounts.auto_repay;
+        let loan = &mut ctx.accounts.loan;
+        let clock = Clock::get()?;
+        
+        // Check repayments are not paused
+        require!(!config.is_paused(PAUSE_REPAYMENTS), ErrorCode::ProtocolPaused);
+        
+        // Check loan is active
+        require!(loan.state == LoanState::Active, ErrorCode::InvalidLoanState);
+        
+        // Check the next installment has come up
+        let installment = auto_repay.installments_paid + 1;
+        require!(
+            clock.unix_timestamp >= auto_repay.collectible_at(installment, loan.due_date),
+            ErrorCode::InstallmentNotDue
+        );
+        
+        // Work out what to collect: this installment's share of the schedule, or on the
+        // last one whatever the loan still needs, which early repayment rebates may lower
+        let is_final = installment == auto_repay.installments;
+        let total_repayment = loan.amount + loan.interest_due(clock.unix_timestamp);
+        let amount = if is_final {
+            total_repayment.saturating_sub(auto_repay.collected)
+        } else {
+            auto_repay.collected_by(installment).saturating_sub(auto_repay.collected)
+        };
+        // Keepers are only tipped for installments that collect something
+        let keeper_tip = if amount > 0 { auto_repay.keeper_tip } else { 0 };
+        
+        // Check the borrower has approved this schedule's delegate for the installment and tip
+        let borrower_token_account = &ctx.accounts.borrower_token_account;
+        require!(
+            borrower_token_account.delegate == COption::Some(ctx.accounts.delegate_authority.key())
+                && borrower_token_account.delegated_amount >= amount + keeper_tip,
+            ErrorCode::DelegateApprovalMissing
+        );
+        
+        // Transfer the installment into the vault and the tip to the keeper through the delegate
+        let schedule_key = auto_repay.key();
+        let seeds = &[DELEGATE_SEED, schedule_key.as_ref(), &[ctx.bumps.delegate_authority]];
+        let signer = &[&seeds[..]];
+        
+        if amount > 0 {
+            let cpi_accounts = Transfer {
+                from: borrower_token_account.to_account_info(),
+                to: ctx.accounts.repay_vault.to_account_info(),
+                authority: ctx.accounts.delegate_authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+            token::transfer(cpi_ctx, amount)?;
+        }
+        if keeper_tip > 0 {
+            let cpi_accounts = Transfer {
+                from: borrower_token_account.to_account_info(),
+                to: ctx.accounts.keeper_token_account.to_account_info(),
+                authority: ctx.accounts.delegate_authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+            token::transfer(cpi_ctx, keeper_tip)?;
+        }
+        
+        auto_repay.collected += amount;
+        auto_repay.installments_paid = installment;
+        
+        emit_cpi!(AutoRepayExecuted {
+            borrower: auto_repay.borrower,
+            loan_id: auto_repay.loan_id,
+            installment,
+            installments: auto_repay.installments,
+            amount,
+            collected: auto_repay.collected,
+            keeper: ctx.accounts.keeper.key(),
+            keeper_tip,
+        });
+        msg!("Auto-repay installment {}/{} collected: {} tokens", installment, auto_repay.installments, amount);
+        if !is_final {
+            return Ok(());
+        }
+        
+        // Check the repayment goes to whoever holds the lender's position
+        let holder = loan.position_holder(ctx.accounts.position_token_account.as_deref())?;
+        require!(
+            ctx.accounts.lender_token_account.owner == holder,
+            ErrorCode::LenderMismatch
+        );
+        
+        let full_interest = calculate_interest(loan.amount, loan.actual_interest_rate_bps);
+        let interest_amount = total_repayment - loan.amount;
+        let protocol_fee = calculate_protocol_fee(interest_amount, config.protocol_fee_bps);
+        let lender_amount = total_repayment - protocol_fee;
+        let surplus = auto_repay.collected - total_repayment;
+        
+        // Pay the lender, the treasury and any surplus back to the borrower out of the vault
+        let loan_key = auto_repay.loan;
+        let seeds = &[AUTO_REPAY_SEED, loan_key.as_ref(), &[auto_repay.bump]];
+        let signer = &[&seeds[..]];
+        
+        let payouts = [
+            (ctx.accounts.lender_token_account.to_account_info(), lender_amount),
+            (ctx.accounts.protocol_treasury.to_account_info(), protocol_fee),
+            (borrower_token_account.to_account_info(), surplus),
+        ];
+        for (to, payout) in payouts {
+            if payout == 0 {
+                continue;
+            }
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.repay_vault.to_account_info(),
+                to,
+                authority: auto_repay.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+            token::transfer(cpi_ctx, payout)?;
+        }
+        
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.repay_vault.to_account_info(),
+            destination: ctx.accounts.borrower.to_account_info(),
+            authority: auto_repay.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+        
+        // The schedule is done; its rent goes back to the borrower along with the vault's
+        ctx.accounts.auto_repay.close(ctx.accounts.borrower.to_account_info())?;
+        
+        // Close the loan out of the borrower's index, the lender's portfolio and the
+        // concentration limits
+        release_loan_id(loan, ctx.accounts.loan_index.as_deref_mut())?;
+        if let Some(lender_profile) = settled_lender_profile(loan, ctx.accounts.lender_profile.as_deref_mut())? {
+            lender_profile.record_repayment(loan.amount, interest_amount - protocol_fee, clock.unix_timestamp);
+        }
+        release_concentration(
+            loan,
+            ctx.accounts.exposure_ledger.as_deref_mut(),
+            ctx.accounts.pair_exposure.as_deref_mut()
+        )?;
+        
+        // Mark the loan repaid and score the borrower on whether it was on time
+        let reputation = &mut ctx.accounts.borrower_reputation;
+        let previous_score = reputation.credit_score;
+        let previous_tier = reputation.credit_tier;
+        let is_late = record_repayment(config, loan, reputation, total_repayment, clock.unix_timestamp);
+        
+        emit_cpi!(LoanRepaid {
+            borrower: loan.borrower,
+            loan_id: loan.loan_id,
+            lender: holder,
+            principal: loan.amount,
+            interest: interest_amount,
+            rebate: full_interest - interest_amount,
+            protocol_fee,
+            lender_amount,
+            is_late,
+            repaid_at: loan.repaid_at,
+            score_change: ScoreChange::new(previous_score, previous_tier, reputation),
+        });
+        msg!("Loan repaid automatically: {} tokens (principal) + {} tokens (interest)", loan.amount, interest_amount);
+        Ok(())
+    }
+
+    /// Stop a loan's automatic repayment, returning anything already collected and the
+    /// rent to the borrower. Works whatever state the loan is in (borrower only)
+    pub fn disable_auto_repay(ctx: Context<DisableAutoRepay>) -> Result<()> {
+        let auto_repay = &ctx.accounts.auto_repay;
+        
+        let loan_key = auto_repay.loan;
+        let seeds = &[AUTO_REPAY_SEED, loan_key.as_ref(), &[auto_repay.bump]];
+        let signer = &[&seeds[..]];
+        
+        let returned = ctx.accounts.repay_vault.amount;
+        if returned > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.repay_vault.to_account_info(),
+                to: ctx.accounts.borrower_token_account.to_account_info(),
+                authority: auto_repay.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+            token::transfer(cpi_ctx, returned)?;
+        }
+        
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.repay_vault.to_account_info(),
+            destination: ctx.accounts.borrower.to_account_info(),
+            authority: auto_repay.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+        
+        emit_cpi!(AutoRepayDisabled {
+            borrower: auto_repay.borrower,
+            loan_id: auto_repay.loan_id,
+            installments_paid: auto_repay.installments_paid,
+            returned,
+        });
+        msg!("Auto-repay disabled for loan {}", auto_repay.loan_id);
+        Ok(())
+    }
+
+    /// Quote a loan for a borrower without creating it (read-only, meant for simulation).
+    /// Checks the same eligibility rules as `create_loan_request` but reports failures
+    /// as error codes in the quote instead of aborting.
+    pub fn quote_loan(ctx: Context<QuoteLoan>, amount: u64, duration_seconds: i64) -> Result<LoanQuote> {
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+        let mut errors = Vec::new();
+        
+        // Borrowers without a reputation account are quoted as new borrowers
+        let (credit_score, credit_tier, is_frozen, active_loans, outstanding_principal) =
+            match &ctx.accounts.borrower_reputation {
+                Some(reputation) => (
+                    reputation.credit_score,
+                    reputation.credit_tier,
+                    reputation.is_frozen,
+                    reputation.active_loans,
+                    reputation.outstanding_principal,
+                ),
+                None => {
+                    errors.push(anchor_lang::error::ErrorCode::AccountNotInitialized.into());
+                    (INITIAL_CREDIT_SCORE, calculate_credit_tier(INITIAL_CREDIT_SCORE), false, 0, 0)
+                }
+            };
+        
+        if config.is_paused(PAUSE_NEW_REQUESTS) {
+            errors.push(ErrorCode::ProtocolPaused.into());
+        }
+        if is_frozen {
+            errors.push(ErrorCode::ReputationFrozen.into());
+        }
+        if check_identity(config, ctx.accounts.identity_attestation.as_deref(), clock.unix_timestamp).is_err() {
+            errors.push(ErrorCode::IdentityNotAttested.into());
+        }
+        let max_borrow = get_max_borrow_amount(config, credit_tier);
+        if amount > max_borrow {
+            errors.push(ErrorCode::ExceedsMaxBorrowAmount.into());
+        }
+        let max_exposure = config.max_exposure(credit_tier);
+        if active_loans >= config.active_loan_limit() {
+            errors.push(ErrorCode::TooManyActiveLoans.into());
+        }
+        if outstanding_principal.saturating_add(amount) > max_exposure {
+            errors.push(ErrorCode::ExceedsMaxExposure.into());
+        }
+        if !(5..=31536000).contains(&duration_seconds) {
+            errors.push(ErrorCode::InvalidDuration.into());
+        }
+        
+        // Invalid durations are still priced, so the rate math must not panic on them
+        let suggested_interest_rate_bps = calculate_interest_rate(credit_tier, duration_seconds);
+        let (interest, protocol_fee) =
+            calculate_repayment(amount, suggested_interest_rate_bps, config.protocol_fee_bps);
+        // Amounts this large are never eligible; saturate instead of overflowing the quote
+        let total_repayment = amount.saturating_add(interest);
+        
+        Ok(LoanQuote {
+            credit_score,
+            credit_tier,
+            max_borrow,
+            max_exposure,
+            outstanding_principal,
+            suggested_interest_rate_bps,
+            interest,
+            protocol_fee,
+            lender_amount: total_repayment - protocol_fee,
+            total_repayment,
+            eligible: errors.is_empty(),
+            errors,
+        })
+    }
+
+    /// Snapshot a reputation into a short-lived attestation other programs can read
+    /// without the full reputation layout (anyone; the payer covers the rent)
+    pub fn attest_reputation(ctx: Context<AttestReputation>) -> Result<()> {
+        let reputation = &ctx.accounts.reputation;
+        let attestation = &mut ctx.accounts.attestation;
+        let clock = Clock::get()?;
+        
+        // Re-attesting refreshes the snapshot and restarts its validity window
+        attestation.owner = reputation.owner;
+        attestation.credit_score = reputation.credit_score;
+        attestation.credit_tier = reputation.credit_tier;
+        attestation.total_loans = reputation.total_loans;
+        attestation.active_loans = reputation.active_loans;
+        attestation.completed_loans = reputation.completed_loans;
+        attestation.defaulted_loans = reputation.defaulted_loans;
+        attestation.is_frozen = reputation.is_frozen;
+        attestation.valid_from = clock.unix_timestamp;
+        attestation.valid_until = clock.unix_timestamp + REPUTATION_ATTESTATION_TTL;
+        attestation.bump = ctx.bumps.attestation;
+        
+        emit_cpi!(ReputationAttested {
+            owner: attestation.owner,
+            credit_score: attestation.credit_score,
+            credit_tier: attestation.credit_tier,
+            valid_until: attestation.valid_until,
+        });
+        msg!("Reputation attested for: {}", attestation.owner);
+        Ok(())
+    }
+
+    /// Check a borrower is unfrozen and at `required_tier` or better, failing otherwise.
+    /// Meant to be called by other programs over CPI; returns the borrower's tier
+    pub fn check_credit_tier(ctx: Context<CheckCreditTier>, required_tier: u8) -> Result<u8> {
+        let reputation = &ctx.accounts.reputation;
+        
+        // Tier A is 0, so better tiers are lower numbers
+        require!(!reputation.is_frozen, ErrorCode::ReputationFrozen);
+        require!(reputation.credit_tier <= required_tier, ErrorCode::CreditTierTooLow);
+        
+        Ok(reputation.credit_tier)
+    }
+
+    /// Start an audit of the protocol stats against the loan set (owner or risk manager)
+    pub fn start_audit(ctx: Context<StartAudit>) -> Result<()> {
+        let audit = &mut ctx.accounts.audit;
+        let clock = Clock::get()?;
+        
+        audit.auditor = ctx.accounts.auditor.key();
+        audit.loans_counted = 0;
+        audit.loans_issued = 0;
+        audit.volume = 0;
+        audit.defaults = 0;
+        audit.last_loan = Pubkey::default();
+        audit.started_at = clock.unix_timestamp;
+        audit.bump = ctx.bumps.audit;
+        
+        emit_cpi!(AuditStarted {
+            auditor: audit.auditor,
+            started_at: audit.started_at,
+        });
+        msg!("Audit started by: {}", audit.auditor);
+        Ok(())
+    }
+
+    /// Tally a batch of loan accounts passed as remaining accounts, in ascending key order
+    pub fn audit_loans<'info>(ctx: Context<'_, '_, 'info, 'info, AuditLoans<'info>>) -> Result<()> {
+        let audit = &mut ctx.accounts.audit;
+        
+        for account_info in ctx.remaining_accounts {
+            // Strictly ascending keys across all batches means no loan is counted twice
+            require!(account_info.key() > audit.last_loan, ErrorCode::AuditOrderViolation);
+            let loan: Account<LoanAccount> = Account::try_from(account_info)?;
+            
+            audit.loans_counted += 1;
+            if loan.funded_at != 0 {
+                audit.loans_issued += 1;
+                audit.volume += loan.funded_amount;
+            }
+            if loan.state == LoanState::Defaulted {
+                audit.defaults += 1;
+            }
+            audit.last_loan = account_info.key();
+        }
+        
+        emit_cpi!(LoansAudited {
+            auditor: audit.auditor,
+            batch_size: ctx.remaining_accounts.len() as u32,
+            loans_counted: audit.loans_counted,
+            last_loan: audit.last_loan,
+        });
+        msg!("Audited {} loans so far", audit.loans_counted);
+        Ok(())
+    }
+
+    /// Compare the tallied loan set with the config stats and close the audit (owner or
+    /// risk manager). The report is returned via `set_return_data`, so the call can be simulated.
+    pub fn finish_audit(ctx: Context<FinishAudit>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let audit = &ctx.accounts.audit;
+        
+        // Closed loans can't be tallied, so their archived stats are counted as-is
+        let counted_loans_issued = audit.loans_issued + config.archived_loans_issued;
+        let counted_volume = audit.volume + config.archived_volume;
+        let counted_defaults = audit.defaults + config.archived_defaults;
+        
+        let report = AuditReport {
+            loans_counted: audit.loans_counted,
+            expected_loans_issued: config.total_loans_issued,
+            counted_loans_issued,
+            expected_volume: config.total_volume,
+            counted_volume,
+            expected_defaults: config.total_defaults,
+            counted_defaults,
+            consistent: config.total_loans_issued == counted_loans_issued
+                && config.total_volume == counted_volume
+                && config.total_defaults == counted_defaults,
+        };
+        
+        if report.consistent {
+            msg!("Audit passed over {} loans", report.loans_counted);
+        } else {
+            msg!(
+                "Audit mismatch - issued: {}/{}, volume: {}/{}, defaults: {}/{}",
+                report.counted_loans_issued,
+                report.expected_loans_issued,
+                report.counted_volume,
+                report.expected_volume,
+                report.counted_defaults,
+                report.expected_defaults
+            );
+        }
+        set_return_data(&report.try_to_vec()?);
+        emit_cpi!(AuditFinished {
+            auditor: audit.auditor,
+            report,
+        });
+        Ok(())
+    }
+
+    /// Upgrade the protocol config to the current layout (owner only)
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config = ctx.accounts.config.to_account_info();
+        let upgraded = upgrade_config(&config.try_borrow_data()?)?;
+        require_keys_eq!(
+            upgraded.authority,
+            ctx.accounts.authority.key(),
+            ErrorCode::MissingRole
+        );
+        
+        write_migrated_account(
+            &config,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            8 + ProtocolConfig::INIT_SPACE,
+            &upgraded,
+        )?;
+        
+        emit_cpi!(ConfigMigrated {
+            authority: upgraded.authority,
+            version: CONFIG_VERSION,
+        });
+        msg!("Protocol config migrated to v{}", CONFIG_VERSION);
+        Ok(())
+    }
+
+    /// Upgrade a reputation account to the current layout (anyone, payer funds the realloc)
+    pub fn migrate_reputation(ctx: Context<MigrateReputation>) -> Result<()> {
+        let reputation = ctx.accounts.reputation.to_account_info();
+        let upgraded = upgrade_reputation(&reputation.try_borrow_data()?)?;
+        require_keys_eq!(
+            upgraded.owner,
+            ctx.accounts.owner.key(),
+            ErrorCode::MigrationAccountMismatch
+        );
+        
+        write_migrated_account(
+            &reputation,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            8 + ReputationAccount::INIT_SPACE,
+            &upgraded,
+        )?;
+        
+        emit_cpi!(ReputationMigrated {
+            owner: upgraded.owner,
+            payer: ctx.accounts.payer.key(),
+            version: REPUTATION_VERSION,
+        });
+        msg!("Reputation migrated to v{} for: {}", REPUTATION_VERSION, upgraded.owner);
+        Ok(())
+    }
+
+    /// Upgrade a loan account to the current layout (anyone, payer funds the realloc)
+    pub fn migrate_loan(ctx: Context<MigrateLoan>, loan_id: u64) -> Result<()> {
+        let loan = ctx.accounts.loan.to_account_info();
+        let upgraded = upgrade_loan(&loan.try_borrow_data()?)?;
+        require!(
+            upgraded.borrower == ctx.accounts.borrower.key() && upgraded.loan_id == loan_id,
+            ErrorCode::MigrationAccountMismatch
+        );
+        
+        write_migrated_account(
+            &loan,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            8 + LoanAccount::INIT_SPACE,
+            &upgraded,
+        )?;
+        
+        emit_cpi!(LoanMigrated {
+            borrower: upgraded.borrower,
+            loan_id,
+            payer: ctx.accounts.payer.key(),
+            version: LOAN_VERSION,
+        });
+        msg!("Loan {} migrated to v{}", loan_id, LOAN_VERSION);
+        Ok(())
+    }
+}
+
+// Helper functions
+fn calculate_credit_tier(credit_score: u16) -> u8 {
+    if credit_score >= TIER_A_THRESHOLD {
+        CREDIT_TIER_A
+    } else if credit_score >= TIER_B_THRESHOLD {
+        CREDIT_TIER_B
+    } else if credit_score >= TIER_C_THRESHOLD {
+        CREDIT_TIER_C
+    } else {
+        CREDIT_TIER_D
+    }
+}
+
+fn calculate_interest_rate(credit_tier: u8, duration_seconds: i64) -> u16 {
+    let risk_premium = match credit_tier {
+        CREDIT_TIER_A => TIER_A_PREMIUM,
+        CREDIT_TIER_B => TIER_B_PREMIUM,
+        CREDIT_TIER_C => TIER_C_PREMIUM,
+        _ => TIER_D_PREMIUM,
+    };
+    
+    // Duration factor: longer loans = slightly higher rate
+    // Add 0.1% per month (30 days); quotes can ask about any duration, so negative
+    // durations add nothing and absurdly long ones saturate instead of overflowing
+    let duration_months = (duration_seconds.max(0) / 86400 / 30).min(u16::MAX as i64) as u16;
+    let duration_factor = duration_months.saturating_mul(10); // 10 bps per month
+    
+    BASE_RATE.saturating_add(risk_premium).saturating_add(duration_factor)
+}
+
+/// Interest owed on `amount` at `interest_rate_bps` over the full term
+fn calculate_interest(amount: u64, interest_rate_bps: u16) -> u64 {
+    (amount as u128)
+        .checked_mul(interest_rate_bps as u128)
+        .unwrap()
+        .checked_div(10000)
+        .unwrap() as u64
+}
+
+/// Rate after an extension adds `extension_interest_rate_bps`, which is capped per
+/// extension; the total stays within the 100% `fund_loan` and `refinance` allow
+fn extended_interest_rate(interest_rate_bps: u16, extension_interest_rate_bps: u16) -> Result<u16> {
+    require!(
+        extension_interest_rate_bps <= MAX_EXTENSION_RATE_BPS,
+        ErrorCode::InterestRateTooHigh
+    );
+    let interest_rate_bps = interest_rate_bps.saturating_add(extension_interest_rate_bps);
+    require!(interest_rate_bps <= 10000, ErrorCode::InterestRateTooHigh);
+    Ok(interest_rate_bps)
+}
+
+/// The protocol's cut of `interest`
+fn calculate_protocol_fee(interest: u64, protocol_fee_bps: u16) -> u64 {
+    (interest as u128)
+        .checked_mul(protocol_fee_bps as u128)
+        .unwrap()
+        .checked_div(10000)
+        .unwrap() as u64
+}
+
+/// Interest owed on `amount` at `interest_rate_bps`, and the protocol's cut of that interest
+fn calculate_repayment(amount: u64, interest_rate_bps: u16, protocol_fee_bps: u16) -> (u64, u64) {
+    let interest_amount = calculate_interest(amount, interest_rate_bps);
+    (interest_amount, calculate_protocol_fee(interest_amount, protocol_fee_bps))
+}
+
+/// Share of `interest` earned between `start` and `now`, spread evenly up to `due_date`
+fn calculate_accrued_interest(interest: u64, start: i64, due_date: i64, now: i64) -> u64 {
+    if now >= due_date || due_date <= start {
+        return interest;
+    }
+    let elapsed = now.saturating_sub(start).max(0) as u128;
+    let term = (due_date - start) as u128;
+    ((interest as u128) * elapsed / term) as u64
+}
+
+/// Check no loan account exists at the ID a loan index would assign next. The index can't
+/// skip IDs, so every later request would collide with it and fail
+fn check_loan_id_unused(loan: &AccountInfo) -> Result<()> {
+    require!(loan.data_is_empty(), ErrorCode::LoanIdTaken);
+    Ok(())
+}
+
+/// Drop a settled loan from its borrower's loan index, if `loan.indexed`
+fn release_loan_id(loan: &LoanAccount, loan_index: Option<&mut BorrowerLoanIndex>) -> Result<()> {
+    if !loan.indexed {
+        return Ok(());
+    }
+    let loan_index = loan_index.ok_or(error!(ErrorCode::LoanIndexMissing))?;
+    loan_index.active_loan_ids.retain(|loan_id| *loan_id != loan.loan_id);
+    Ok(())
+}
+
+/// Mark an active loan repaid in full and score its borrower for paying on time or late.
+/// Returns whether the repayment came after the due date
+fn record_repayment(
+    config: &ProtocolConfig,
+    loan: &mut LoanAccount,
+    reputation: &mut ReputationAccount,
+    total_repayment: u64,
+    now: i64,
+) -> bool {
+    loan.state = LoanState::Repaid;
+    loan.repaid_at = now;
+    loan.repaid_amount = total_repayment;
+    
+    let is_late = now > loan.due_date;
+    reputation.active_loans = reputation.active_loans.saturating_sub(1);
+    reputation.outstanding_principal = reputation.outstanding_principal.saturating_sub(loan.amount);
+    reputation.completed_loans += 1;
+    reputation.total_repaid += total_repayment;
+    
+    if is_late {
+        reputation.late_payments += 1;
+        reputation.credit_score = apply_credit_adjustment(reputation.credit_score, config.late_payment_penalty);
+        msg!("Late payment - credit score decreased");
+    } else {
+        reputation.on_time_payments += 1;
+        reputation.credit_score = apply_credit_adjustment(reputation.credit_score, config.on_time_payment_bonus);
+        msg!("On-time payment - credit score increased");
+    }
+    
+    reputation.refresh_tier();
+    reputation.last_updated = now;
+    is_late
+}
+
+/// The recorded lender's profile for a loan leaving their portfolio, if `loan.lender_profiled`
+fn settled_lender_profile<'a>(
+    loan: &LoanAccount,
+    lender_profile: Option<&'a mut LenderProfile>,
+) -> Result<Option<&'a mut LenderProfile>> {
+    if !loan.lender_profiled {
+        return Ok(None);
+    }
+    lender_profile.map(Some).ok_or(error!(ErrorCode::LenderProfileMissing))
+}
+
+/// Mint a loan position's single token to `holder_position_account`, then revoke the mint
+/// authority so the position can never be duplicated
+fn mint_position<'info>(
+    token_program: &Program<'info, Token>,
+    position_mint: &Account<'info, Mint>,
+    holder_position_account: &Account<'info, TokenAccount>,
+    escrow: &Account<'info, EscrowAccount>,
+) -> Result<()> {
+    let loan_id = escrow.loan_id.to_le_bytes();
+    let seeds = &[
+        ESCROW_SEED,
+        escrow.borrower.as_ref(),
+        loan_id.as_ref(),
+        &[escrow.bump],
+    ];
+    let signer = &[&seeds[..]];
+    
+    let cpi_accounts = MintTo {
+        mint: position_mint.to_account_info(),
+        to: holder_position_account.to_account_info(),
+        authority: escrow.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::mint_to(cpi_ctx, 1)?;
+    
+    let cpi_accounts = SetAuthority {
+        current_authority: escrow.to_account_info(),
+        account_or_mint: position_mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::set_authority(cpi_ctx, AuthorityType::MintTokens, None)
+}
+
+/// Move a guarantee's whole bond vault to `destination`, then close the vault, returning
+/// its rent to the guarantor
+fn pay_out_bond<'info>(
+    token_program: &Program<'info, Token>,
+    guarantee: &Account<'info, Guarantee>,
+    bond_vault: &Account<'info, TokenAccount>,
+    destination: &Account<'info, TokenAccount>,
+    guarantor: &UncheckedAccount<'info>,
+) -> Result<()> {
+    let loan_id = guarantee.loan_id.to_le_bytes();
+    let seeds = &[
+        GUARANTEE_SEED,
+        guarantee.borrower.as_ref(),
+        loan_id.as_ref(),
+        &[guarantee.bump],
+    ];
+    let signer = &[&seeds[..]];
+    
+    let cpi_accounts = Transfer {
+        from: bond_vault.to_account_info(),
+        to: destination.to_account_info(),
+        authority: guarantee.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::transfer(cpi_ctx, bond_vault.amount)?;
+    
+    let cpi_accounts = CloseAccount {
+        account: bond_vault.to_account_info(),
+        destination: guarantor.to_account_info(),
+        authority: guarantee.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::close_account(cpi_ctx)
+}
+
+fn get_max_borrow_amount(config: &ProtocolConfig, credit_tier: u8) -> u64 {
+    match credit_tier {
+        CREDIT_TIER_A => config.tier_max_borrow[0],
+        CREDIT_TIER_B => config.tier_max_borrow[1],
+        CREDIT_TIER_C => config.tier_max_borrow[2],
+        _ => config.tier_max_borrow[3],
+    }
+}
+
+/// Check a new loan of `amount` against the borrower's aggregate limits: the number of
+/// loans they have funded at once and the principal outstanding across them, which a
+/// guarantee's `backing` raises for its loan
+fn check_exposure(
+    config: &ProtocolConfig,
+    reputation: &ReputationAccount,
+    amount: u64,
+    backing: u64,
+) -> Result<()> {
+    require!(
+        reputation.active_loans < config.active_loan_limit(),
+        ErrorCode::TooManyActiveLoans
+    );
+    require!(
+        reputation.outstanding_principal.saturating_add(amount)
+            <= config.max_exposure(reputation.credit_tier).saturating_add(backing),
+        ErrorCode::ExceedsMaxExposure
+    );
+    Ok(())
+}
+
+/// Take a settled loan's principal off the concentration limits, if `loan.concentration_tracked`
+fn release_concentration(
+    loan: &LoanAccount,
+    exposure_ledger: Option<&mut ExposureLedger>,
+    pair_exposure: Option<&mut PairExposure>,
+) -> Result<()> {
+    if !loan.concentration_tracked {
+        return Ok(());
+    }
+    let (Some(exposure_ledger), Some(pair_exposure)) = (exposure_ledger, pair_exposure) else {
+        return err!(ErrorCode::ConcentrationAccountsMissing);
+    };
+    exposure_ledger.release(loan.funded_tier, loan.amount);
+    pair_exposure.release(loan.amount);
+    Ok(())
+}
+
+/// Check the identity gate: while an attester is assigned, the wallet must hold a live
+/// attestation from that attester
+fn check_identity(config: &ProtocolConfig, attestation: Option<&IdentityAttestation>, now: i64) -> Result<()> {
+    if config.requires_identity() {
+        require!(
+            attestation.is_some_and(|attestation| attestation.is_valid(config, now)),
+            ErrorCode::IdentityNotAttested
+        );
+    }
+    Ok(())
+}
+
+/// Take a closed vouch's stake and count off both the voucher and the vouchee
+fn unwind_vouch(vouch: &Vouch, voucher: &mut ReputationAccount, vouchee: &mut ReputationAccount, now: i64) {
+    voucher.vouches_given = voucher.vouches_given.saturating_sub(1);
+    voucher.staked_score = voucher.staked_score.saturating_sub(vouch.stake);
+    voucher.refresh_tier();
+    voucher.last_updated = now;
+    
+    vouchee.vouches_received = vouchee.vouches_received.saturating_sub(1);
+    vouchee.vouched_score = vouchee.vouched_score.saturating_sub(vouch.stake);
+    vouchee.refresh_tier();
+    vouchee.last_updated = now;
+}
+
+fn apply_credit_adjustment(current_score: u16, adjustment: i16) -> u16 {
+    let new_score = (current_score as i32) + (adjustment as i32);
+    new_score.max(MIN_CREDIT_SCORE as i32).min(MAX_CREDIT_SCORE as i32) as u16
+}
+
+// Migration helpers
+
+/// Reads the layout version of a raw account. Legacy (v0) accounts predate the
+/// version byte and are recognised by their exact allocated size instead.
+fn stored_version(data: &[u8], discriminator: &[u8], legacy_space: usize) -> Result<u8> {
+    require!(
+        data.len() > 8 && data[..8] == *discriminator,
+        ErrorCode::UnsupportedAccountVersion
+    );
+    if data.len() == 8 + legacy_space {
+        return Ok(0);
+    }
+    Ok(data[8])
+}
+
+fn upgrade_config(data: &[u8]) -> Result<ProtocolConfig> {
+    match stored_version(data, ProtocolConfig::DISCRIMINATOR, ProtocolConfigV0::INIT_SPACE)? {
+        0 => {
+            let legacy = ProtocolConfigV0::deserialize(&mut &data[8..])?;
+            Ok(legacy.into())
+        }
+        CONFIG_VERSION => err!(ErrorCode::AlreadyMigrated),
+        _ => err!(ErrorCode::UnsupportedAccountVersion),
+    }
+}
+
+fn upgrade_reputation(data: &[u8]) -> Result<ReputationAccount> {
+    match stored_version(data, ReputationAccount::DISCRIMINATOR, ReputationAccountV0::INIT_SPACE)? {
+        0 => {
+            let legacy = ReputationAccountV0::deserialize(&mut &data[8..])?;
+            Ok(legacy.into())
+        }
+        REPUTATION_VERSION => err!(ErrorCode::AlreadyMigrated),
+        _ => err!(ErrorCode::UnsupportedAccountVersion),
+    }
+}
+
+fn upgrade_loan(data: &[u8]) -> Result<LoanAccount> {
+    match stored_version(data, LoanAccount::DISCRIMINATOR, LoanAccountV0::INIT_SPACE)? {
+        0 => {
+            let legacy = LoanAccountV0::deserialize(&mut &data[8..])?;
+            Ok(legacy.into())
+        }
+        LOAN_VERSION => err!(ErrorCode::AlreadyMigrated),
+        _ => err!(ErrorCode::UnsupportedAccountVersion),
+    }
+}
+
+/// Grows the account to `space` (topping up rent from `payer`) and writes the upgraded value
+fn write_migrated_account<'info, T: AccountSerialize>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    value: &T,
+) -> Result<()> {
+    if space > account.data_len() {
+        let rent_due = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(account.lamports());
+        if rent_due > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: payer.to_account_info(),
+                to: account.clone(),
+            };
+            let cpi_ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        account.resize(space)?;
+    }
+    
+    let mut data = account.try_borrow_mut_data()?;
+    value.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+// Account Contexts
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
         payer = authority,
         space = 8 + ProtocolConfig::INIT_SPACE,
         seeds = [CONFIG_SEED],
@@ -480,281 +3159,3081 @@ pub struct InitializeConfig<'info> {
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct UpdateConfig<'info> {
-    #[account(
-        mut,
-        seeds = [CONFIG_SEED],
-        bump,
-        has_one = authority
-    )]
-    pub config: Account<'info, ProtocolConfig>,
-    pub authority: Signer<'info>,
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub signer: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateRiskParams<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::RiskManager, &risk_manager.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub risk_manager: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitializeExposureLedger<'info> {
+    #[account(
+        init,
+        payer = risk_manager,
+        space = 8 + ExposureLedger::INIT_SPACE,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump
+    )]
+    pub exposure_ledger: Account<'info, ExposureLedger>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::RiskManager, &risk_manager.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub risk_manager: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateConcentrationLimits<'info> {
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Account<'info, ExposureLedger>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::RiskManager, &risk_manager.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub risk_manager: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptBondMint<'info> {
+    #[account(
+        init,
+        payer = risk_manager,
+        space = 8 + AcceptedBondMint::INIT_SPACE,
+        seeds = [BOND_MINT_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub accepted_bond_mint: Account<'info, AcceptedBondMint>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::RiskManager, &risk_manager.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub risk_manager: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RemoveBondMint<'info> {
+    #[account(
+        mut,
+        seeds = [BOND_MINT_SEED, accepted_bond_mint.mint.as_ref()],
+        bump = accepted_bond_mint.bump,
+        close = risk_manager
+    )]
+    pub accepted_bond_mint: Account<'info, AcceptedBondMint>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::RiskManager, &risk_manager.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub risk_manager: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ManageRole<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = authority
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateReputation<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ReputationAccount::INIT_SPACE,
+        seeds = [REPUTATION_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub reputation: Account<'info, ReputationAccount>,
+    /// Owner's identity attestation; may be omitted while no attester is assigned
+    #[account(
+        seeds = [IDENTITY_SEED, owner.key().as_ref()],
+        bump = identity_attestation.bump
+    )]
+    pub identity_attestation: Option<Account<'info, IdentityAttestation>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AttestIdentity<'info> {
+    #[account(
+        init_if_needed,
+        payer = attester,
+        space = 8 + IdentityAttestation::INIT_SPACE,
+        seeds = [IDENTITY_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub identity_attestation: Account<'info, IdentityAttestation>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::IdentityAttester, &attester.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub attester: Signer<'info>,
+    /// CHECK: Wallet being attested; only used to derive the attestation address
+    pub owner: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RevokeIdentity<'info> {
+    #[account(
+        mut,
+        seeds = [IDENTITY_SEED, identity_attestation.owner.as_ref()],
+        bump = identity_attestation.bump,
+        close = attester
+    )]
+    pub identity_attestation: Account<'info, IdentityAttestation>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::IdentityAttester, &attester.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub attester: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(next_loan_id: u64)]
+pub struct CreateLoanIndex<'info> {
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + BorrowerLoanIndex::INIT_SPACE,
+        seeds = [LOAN_INDEX_SEED, borrower.key().as_ref()],
+        bump
+    )]
+    pub loan_index: Account<'info, BorrowerLoanIndex>,
+    /// CHECK: Loan PDA at `next_loan_id`, which must not exist yet
+    #[account(
+        seeds = [LOAN_SEED, borrower.key().as_ref(), next_loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub next_loan: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateLenderProfile<'info> {
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + LenderProfile::INIT_SPACE,
+        seeds = [LENDER_PROFILE_SEED, lender.key().as_ref()],
+        bump
+    )]
+    pub lender_profile: Account<'info, LenderProfile>,
+    #[account(mut)]
+    pub lender: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct GuaranteeLoan<'info> {
+    #[account(
+        init,
+        payer = guarantor,
+        space = 8 + Guarantee::INIT_SPACE,
+        seeds = [GUARANTEE_SEED, borrower.key().as_ref(), loan_index.next_loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub guarantee: Account<'info, Guarantee>,
+    #[account(
+        seeds = [LOAN_INDEX_SEED, borrower.key().as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Account<'info, BorrowerLoanIndex>,
+    #[account(
+        seeds = [REPUTATION_SEED, guarantor.key().as_ref()],
+        bump = guarantor_reputation.bump
+    )]
+    pub guarantor_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub guarantor: Signer<'info>,
+    #[account(mut)]
+    pub guarantor_token_account: Account<'info, TokenAccount>,
+    pub bond_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [BOND_MINT_SEED, bond_mint.key().as_ref()],
+        bump = accepted_bond_mint.bump
+    )]
+    pub accepted_bond_mint: Account<'info, AcceptedBondMint>,
+    #[account(
+        init,
+        payer = guarantor,
+        associated_token::mint = bond_mint,
+        associated_token::authority = guarantee
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateLoanRequest<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, borrower.key().as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Account<'info, BorrowerLoanIndex>,
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + LoanAccount::INIT_SPACE,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan_index.next_loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    /// Guarantee pledged for this loan ID; omitted for requests without a guarantor
+    #[account(
+        mut,
+        seeds = [GUARANTEE_SEED, borrower.key().as_ref(), loan_index.next_loan_id.to_le_bytes().as_ref()],
+        bump = guarantee.bump
+    )]
+    pub guarantee: Option<Account<'info, Guarantee>>,
+    /// Borrower's identity attestation; may be omitted while no attester is assigned
+    #[account(
+        seeds = [IDENTITY_SEED, borrower.key().as_ref()],
+        bump = identity_attestation.bump
+    )]
+    pub identity_attestation: Option<Account<'info, IdentityAttestation>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FundLoan<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + EscrowAccount::INIT_SPACE,
+        seeds = [ESCROW_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    /// CHECK: This is the borrower's public key
+    pub borrower: AccountInfo<'info>,
+    #[account(mut)]
+    pub lender: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, lender.key().as_ref()],
+        bump = lender_profile.bump
+    )]
+    pub lender_profile: Box<Account<'info, LenderProfile>>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Box<Account<'info, ExposureLedger>>,
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + PairExposure::INIT_SPACE,
+        seeds = [PAIR_EXPOSURE_SEED, lender.key().as_ref(), borrower.key().as_ref()],
+        bump
+    )]
+    pub pair_exposure: Box<Account<'info, PairExposure>>,
+    #[account(mut)]
+    pub lender_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = lender,
+        seeds = [POSITION_SEED, loan.key().as_ref(), loan.refinance_count.to_le_bytes().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = escrow
+    )]
+    pub position_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = position_mint,
+        associated_token::authority = lender
+    )]
+    pub lender_position_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawLoan<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RepayLoan<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    /// Borrower's loan index; passed when `loan.indexed`
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, borrower.key().as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Option<Account<'info, BorrowerLoanIndex>>,
+    /// Recorded lender's profile; passed when `loan.lender_profiled`
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, loan.lender.unwrap_or_default().as_ref()],
+        bump = lender_profile.bump
+    )]
+    pub lender_profile: Option<Account<'info, LenderProfile>>,
+    /// Exposure ledger; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Option<Account<'info, ExposureLedger>>,
+    /// Recorded lender's exposure to the borrower; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [PAIR_EXPOSURE_SEED, loan.lender.unwrap_or_default().as_ref(), borrower.key().as_ref()],
+        bump = pair_exposure.bump
+    )]
+    pub pair_exposure: Option<Account<'info, PairExposure>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub lender_token_account: Account<'info, TokenAccount>,
+    /// Holder's position token account; passed when the loan has a `position_mint`
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub protocol_treasury: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MarkDefault<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, loan.borrower.as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    /// Borrower's loan index; passed when `loan.indexed`
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, loan.borrower.as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Option<Account<'info, BorrowerLoanIndex>>,
+    /// Recorded lender's profile; passed when `loan.lender_profiled`
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, loan.lender.unwrap_or_default().as_ref()],
+        bump = lender_profile.bump
+    )]
+    pub lender_profile: Option<Account<'info, LenderProfile>>,
+    /// Exposure ledger; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Option<Account<'info, ExposureLedger>>,
+    /// Recorded lender's exposure to the borrower; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [PAIR_EXPOSURE_SEED, loan.lender.unwrap_or_default().as_ref(), loan.borrower.as_ref()],
+        bump = pair_exposure.bump
+    )]
+    pub pair_exposure: Option<Account<'info, PairExposure>>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = oracle_authority
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub oracle_authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnfreezeReputation<'info> {
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, reputation.owner.as_ref()],
+        bump = reputation.bump
+    )]
+    pub reputation: Account<'info, ReputationAccount>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.has_role(Role::ReputationSteward, &reputation_steward.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub reputation_steward: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct IssueVouch<'info> {
+    #[account(
+        init,
+        payer = voucher,
+        space = 8 + Vouch::INIT_SPACE,
+        seeds = [VOUCH_SEED, voucher.key().as_ref(), vouchee.key().as_ref()],
+        bump
+    )]
+    pub vouch: Account<'info, Vouch>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, voucher.key().as_ref()],
+        bump = voucher_reputation.bump
+    )]
+    pub voucher_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, vouchee.key().as_ref()],
+        bump = vouchee_reputation.bump
+    )]
+    pub vouchee_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub voucher: Signer<'info>,
+    /// CHECK: Only used to derive the vouch and reputation addresses
+    pub vouchee: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RevokeVouch<'info> {
+    #[account(
+        mut,
+        seeds = [VOUCH_SEED, voucher.key().as_ref(), vouch.vouchee.as_ref()],
+        bump = vouch.bump,
+        has_one = voucher,
+        close = voucher
+    )]
+    pub vouch: Account<'info, Vouch>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, voucher.key().as_ref()],
+        bump = voucher_reputation.bump
+    )]
+    pub voucher_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, vouch.vouchee.as_ref()],
+        bump = vouchee_reputation.bump
+    )]
+    pub vouchee_reputation: Account<'info, ReputationAccount>,
+    /// CHECK: Must list no open loans; may not exist for vouchees who never created one
+    #[account(
+        seeds = [LOAN_INDEX_SEED, vouch.vouchee.as_ref()],
+        bump
+    )]
+    pub vouchee_loan_index: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub voucher: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SlashVouch<'info> {
+    #[account(
+        mut,
+        seeds = [VOUCH_SEED, voucher.key().as_ref(), vouch.vouchee.as_ref()],
+        bump = vouch.bump,
+        has_one = voucher,
+        close = voucher
+    )]
+    pub vouch: Account<'info, Vouch>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, voucher.key().as_ref()],
+        bump = voucher_reputation.bump
+    )]
+    pub voucher_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, vouch.vouchee.as_ref()],
+        bump = vouchee_reputation.bump
+    )]
+    pub vouchee_reputation: Account<'info, ReputationAccount>,
+    /// CHECK: Receives the vouch rent; checked by has_one on the vouch
+    #[account(mut)]
+    pub voucher: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelLoanRequest<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower,
+        close = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    /// Borrower's loan index; passed when `loan.indexed`
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, borrower.key().as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Option<Account<'info, BorrowerLoanIndex>>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExpireRequest<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower,
+        close = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    /// Borrower's loan index; passed when `loan.indexed`
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, borrower.key().as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Option<Account<'info, BorrowerLoanIndex>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    /// CHECK: Receives the loan account's rent; checked by has_one on the loan
+    #[account(mut)]
+    pub borrower: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestExtension<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    pub borrower: Signer<'info>,
+    pub lender: Signer<'info>,
+    /// Lender's position token account; passed when the loan has a `position_mint`
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Refinance<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        seeds = [ESCROW_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Escrow's token account, fixing the mint the loan was funded in
+    #[account(token::authority = escrow)]
+    pub escrow_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub new_lender: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, new_lender.key().as_ref()],
+        bump = new_lender_profile.bump
+    )]
+    pub new_lender_profile: Box<Account<'info, LenderProfile>>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Box<Account<'info, ExposureLedger>>,
+    #[account(
+        init_if_needed,
+        payer = new_lender,
+        space = 8 + PairExposure::INIT_SPACE,
+        seeds = [PAIR_EXPOSURE_SEED, new_lender.key().as_ref(), borrower.key().as_ref()],
+        bump
+    )]
+    pub new_pair_exposure: Box<Account<'info, PairExposure>>,
+    #[account(mut)]
+    pub new_lender_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub lender_token_account: Account<'info, TokenAccount>,
+    /// Outgoing lender's position token account; passed when the loan has a `position_mint`
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+    /// Outgoing lender of record's profile; passed when `loan.lender_profiled`
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, loan.lender.unwrap_or_default().as_ref()],
+        bump = lender_profile.bump
+    )]
+    pub lender_profile: Option<Account<'info, LenderProfile>>,
+    /// Outgoing lender of record's exposure to the borrower; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [PAIR_EXPOSURE_SEED, loan.lender.unwrap_or_default().as_ref(), borrower.key().as_ref()],
+        bump = pair_exposure.bump
+    )]
+    pub pair_exposure: Option<Box<Account<'info, PairExposure>>>,
+    #[account(
+        init,
+        payer = new_lender,
+        seeds = [POSITION_SEED, loan.key().as_ref(), (loan.refinance_count + 1).to_le_bytes().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = escrow
+    )]
+    pub new_position_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = new_lender,
+        associated_token::mint = new_position_mint,
+        associated_token::authority = new_lender
+    )]
+    pub new_lender_position_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = config.authority
+    )]
+    pub protocol_treasury: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReclaimFunding<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        seeds = [ESCROW_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, loan.borrower.as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    /// Borrower's loan index; passed when `loan.indexed`
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, loan.borrower.as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Option<Account<'info, BorrowerLoanIndex>>,
+    /// Recorded lender's profile; passed when `loan.lender_profiled`
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, loan.lender.unwrap_or_default().as_ref()],
+        bump = lender_profile.bump
+    )]
+    pub lender_profile: Option<Account<'info, LenderProfile>>,
+    /// Exposure ledger; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Option<Account<'info, ExposureLedger>>,
+    /// Recorded lender's exposure to the borrower; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [PAIR_EXPOSURE_SEED, loan.lender.unwrap_or_default().as_ref(), loan.borrower.as_ref()],
+        bump = pair_exposure.bump
+    )]
+    pub pair_exposure: Option<Account<'info, PairExposure>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub lender: Signer<'info>,
+    #[account(mut)]
+    pub lender_token_account: Account<'info, TokenAccount>,
+    /// Lender's position token account; passed when the loan has a `position_mint`
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = escrow
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseLoan<'info> {
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower,
+        constraint = loan.escrow_rent_payer() == Some(escrow_payer.key()) @ ErrorCode::RentPayerMismatch,
+        close = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        close = escrow_payer
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(
+        mut,
+        token::authority = escrow
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    /// CHECK: Must no longer exist, since the bond is released or claimed before the loan
+    /// is closed; omitted for loans without a guarantor
+    #[account(
+        seeds = [GUARANTEE_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub guarantee: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    /// CHECK: Receives the loan account's rent; checked by has_one on the loan
+    #[account(mut)]
+    pub borrower: UncheckedAccount<'info>,
+    /// CHECK: Receives the escrow rent; checked against the payer recorded on the loan
+    #[account(mut)]
+    pub escrow_payer: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReleaseGuarantee<'info> {
+    #[account(
+        mut,
+        seeds = [GUARANTEE_SEED, guarantee.borrower.as_ref(), guarantee.loan_id.to_le_bytes().as_ref()],
+        bump = guarantee.bump,
+        has_one = guarantor,
+        close = guarantor
+    )]
+    pub guarantee: Account<'info, Guarantee>,
+    /// CHECK: The guaranteed loan, which may already be closed; read in the handler
+    #[account(
+        seeds = [LOAN_SEED, guarantee.borrower.as_ref(), guarantee.loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub loan: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        token::authority = guarantee
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::authority = guarantor
+    )]
+    pub guarantor_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Receives the bond vault and guarantee rent; checked by has_one on the guarantee
+    #[account(mut)]
+    pub guarantor: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimGuarantee<'info> {
+    #[account(
+        mut,
+        seeds = [GUARANTEE_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = guarantee.bump,
+        has_one = guarantor,
+        close = guarantor
+    )]
+    pub guarantee: Account<'info, Guarantee>,
+    #[account(
+        seeds = [LOAN_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, guarantor.key().as_ref()],
+        bump = guarantor_reputation.bump
+    )]
+    pub guarantor_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        mut,
+        token::authority = guarantee
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub holder_token_account: Account<'info, TokenAccount>,
+    /// Holder's position token account; passed when the loan has a `position_mint`
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+    /// CHECK: Receives the bond vault and guarantee rent; checked by has_one on the guarantee
+    #[account(mut)]
+    pub guarantor: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct OpenCreditLine<'info> {
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + CreditLine::INIT_SPACE,
+        seeds = [CREDIT_LINE_SEED, lender.key().as_ref(), borrower.key().as_ref()],
+        bump
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+    #[account(
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    /// Borrower's identity attestation; may be omitted while no attester is assigned
+    #[account(
+        seeds = [IDENTITY_SEED, borrower.key().as_ref()],
+        bump = identity_attestation.bump
+    )]
+    pub identity_attestation: Option<Account<'info, IdentityAttestation>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub lender: Signer<'info>,
+    #[account(mut)]
+    pub lender_token_account: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = mint,
+        associated_token::authority = credit_line
+    )]
+    pub line_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DrawCreditLine<'info> {
+    #[account(
+        mut,
+        seeds = [CREDIT_LINE_SEED, credit_line.lender.as_ref(), borrower.key().as_ref()],
+        bump = credit_line.bump,
+        has_one = borrower
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    /// Borrower's identity attestation; may be omitted while no attester is assigned
+    #[account(
+        seeds = [IDENTITY_SEED, borrower.key().as_ref()],
+        bump = identity_attestation.bump
+    )]
+    pub identity_attestation: Option<Account<'info, IdentityAttestation>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Account<'info, ExposureLedger>,
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        space = 8 + PairExposure::INIT_SPACE,
+        seeds = [PAIR_EXPOSURE_SEED, credit_line.lender.as_ref(), borrower.key().as_ref()],
+        bump
+    )]
+    pub pair_exposure: Account<'info, PairExposure>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = credit_line.mint,
+        associated_token::authority = credit_line
+    )]
+    pub line_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RepayCreditLine<'info> {
+    #[account(
+        mut,
+        seeds = [CREDIT_LINE_SEED, credit_line.lender.as_ref(), borrower.key().as_ref()],
+        bump = credit_line.bump,
+        has_one = borrower
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Account<'info, ExposureLedger>,
+    #[account(
+        mut,
+        seeds = [PAIR_EXPOSURE_SEED, credit_line.lender.as_ref(), borrower.key().as_ref()],
+        bump = pair_exposure.bump
+    )]
+    pub pair_exposure: Account<'info, PairExposure>,
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = credit_line.mint,
+        associated_token::authority = credit_line
+    )]
+    pub line_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub lender_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::authority = config.authority
+    )]
+    pub protocol_treasury: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseStatement<'info> {
+    #[account(
+        mut,
+        seeds = [CREDIT_LINE_SEED, credit_line.lender.as_ref(), credit_line.borrower.as_ref()],
+        bump = credit_line.bump
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, credit_line.borrower.as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Account<'info, ReputationAccount>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseCreditLine<'info> {
+    #[account(
+        mut,
+        seeds = [CREDIT_LINE_SEED, lender.key().as_ref(), credit_line.borrower.as_ref()],
+        bump = credit_line.bump,
+        has_one = lender,
+        close = lender
+    )]
+    pub credit_line: Account<'info, CreditLine>,
+    #[account(
+        mut,
+        associated_token::mint = credit_line.mint,
+        associated_token::authority = credit_line
+    )]
+    pub line_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::authority = lender
+    )]
+    pub lender_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub lender: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DelegateCredit<'info> {
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + CreditDelegation::INIT_SPACE,
+        seeds = [CREDIT_DELEGATION_SEED, lender.key().as_ref(), borrower.key().as_ref()],
+        bump
+    )]
+    pub credit_delegation: Account<'info, CreditDelegation>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    /// CHECK: Only used to derive the delegation PDA; the borrower accepts by drawing
+    pub borrower: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub lender: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RevokeCreditDelegation<'info> {
+    #[account(
+        mut,
+        seeds = [CREDIT_DELEGATION_SEED, lender.key().as_ref(), credit_delegation.borrower.as_ref()],
+        bump = credit_delegation.bump,
+        has_one = lender,
+        close = lender
+    )]
+    pub credit_delegation: Account<'info, CreditDelegation>,
+    #[account(mut)]
+    pub lender: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DrawDelegatedCredit<'info> {
+    #[account(
+        mut,
+        seeds = [CREDIT_DELEGATION_SEED, credit_delegation.lender.as_ref(), borrower.key().as_ref()],
+        bump = credit_delegation.bump,
+        has_one = borrower
+    )]
+    pub credit_delegation: Box<Account<'info, CreditDelegation>>,
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, borrower.key().as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Box<Account<'info, BorrowerLoanIndex>>,
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + LoanAccount::INIT_SPACE,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan_index.next_loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub loan: Box<Account<'info, LoanAccount>>,
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + EscrowAccount::INIT_SPACE,
+        seeds = [ESCROW_SEED, borrower.key().as_ref(), loan_index.next_loan_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+    #[account(
+        init,
+        payer = borrower,
+        associated_token::mint = mint,
+        associated_token::authority = escrow
+    )]
+    pub escrow_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Box<Account<'info, ReputationAccount>>,
+    /// Borrower's identity attestation; may be omitted while no attester is assigned
+    #[account(
+        seeds = [IDENTITY_SEED, borrower.key().as_ref()],
+        bump = identity_attestation.bump
+    )]
+    pub identity_attestation: Option<Account<'info, IdentityAttestation>>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, credit_delegation.lender.as_ref()],
+        bump = lender_profile.bump
+    )]
+    pub lender_profile: Box<Account<'info, LenderProfile>>,
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Box<Account<'info, ExposureLedger>>,
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        space = 8 + PairExposure::INIT_SPACE,
+        seeds = [PAIR_EXPOSURE_SEED, credit_delegation.lender.as_ref(), borrower.key().as_ref()],
+        bump
+    )]
+    pub pair_exposure: Box<Account<'info, PairExposure>>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    #[account(mut)]
+    pub borrower_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = credit_delegation.lender
+    )]
+    pub lender_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(address = credit_delegation.mint)]
+    pub mint: Box<Account<'info, Mint>>,
+    /// CHECK: Program PDA the lender approved as delegate on their token account
+    #[account(
+        seeds = [DELEGATE_SEED, credit_delegation.key().as_ref()],
+        bump
+    )]
+    pub delegate_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EnableAutoRepay<'info> {
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + AutoRepay::INIT_SPACE,
+        seeds = [AUTO_REPAY_SEED, loan.key().as_ref()],
+        bump
+    )]
+    pub auto_repay: Account<'info, AutoRepay>,
+    #[account(
+        init,
+        payer = borrower,
+        associated_token::mint = mint,
+        associated_token::authority = auto_repay
+    )]
+    pub repay_vault: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump,
+        has_one = borrower
+    )]
+    pub loan: Account<'info, LoanAccount>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    #[account(
+        token::mint = mint,
+        token::authority = borrower
+    )]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteAutoRepay<'info> {
+    #[account(
+        mut,
+        seeds = [AUTO_REPAY_SEED, loan.key().as_ref()],
+        bump = auto_repay.bump,
+        has_one = loan,
+        has_one = borrower,
+        has_one = borrower_token_account
+    )]
+    pub auto_repay: Box<Account<'info, AutoRepay>>,
+    #[account(
+        mut,
+        associated_token::mint = auto_repay.mint,
+        associated_token::authority = auto_repay
+    )]
+    pub repay_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
+        bump = loan.bump
+    )]
+    pub loan: Box<Account<'info, LoanAccount>>,
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, loan.borrower.as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Box<Account<'info, ReputationAccount>>,
+    /// Borrower's loan index; passed when `loan.indexed`
+    #[account(
+        mut,
+        seeds = [LOAN_INDEX_SEED, loan.borrower.as_ref()],
+        bump = loan_index.bump
+    )]
+    pub loan_index: Option<Account<'info, BorrowerLoanIndex>>,
+    /// Recorded lender's profile; passed when `loan.lender_profiled`
+    #[account(
+        mut,
+        seeds = [LENDER_PROFILE_SEED, loan.lender.unwrap_or_default().as_ref()],
+        bump = lender_profile.bump
+    )]
+    pub lender_profile: Option<Account<'info, LenderProfile>>,
+    /// Exposure ledger; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [EXPOSURE_LEDGER_SEED],
+        bump = exposure_ledger.bump
+    )]
+    pub exposure_ledger: Option<Account<'info, ExposureLedger>>,
+    /// Recorded lender's exposure to the borrower; passed when `loan.concentration_tracked`
+    #[account(
+        mut,
+        seeds = [PAIR_EXPOSURE_SEED, loan.lender.unwrap_or_default().as_ref(), loan.borrower.as_ref()],
+        bump = pair_exposure.bump
+    )]
+    pub pair_exposure: Option<Account<'info, PairExposure>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+    /// CHECK: Receives the schedule's rent once the loan is repaid; checked against the schedule
+    #[account(mut)]
+    pub borrower: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub borrower_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub lender_token_account: Box<Account<'info, TokenAccount>>,
+    /// Holder's position token account; passed when the loan has a `position_mint`
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+    /// Keepers pick the accounts, so the treasury must belong to the protocol authority
+    #[account(
+        mut,
+        token::authority = config.authority
+    )]
+    pub protocol_treasury: Box<Account<'info, TokenAccount>>,
+    pub keeper: Signer<'info>,
+    #[account(
+        mut,
+        token::authority = keeper
+    )]
+    pub keeper_token_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Program PDA the borrower approved as delegate on their token account
+    #[account(
+        seeds = [DELEGATE_SEED, auto_repay.key().as_ref()],
+        bump
+    )]
+    pub delegate_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DisableAutoRepay<'info> {
+    #[account(
+        mut,
+        seeds = [AUTO_REPAY_SEED, auto_repay.loan.as_ref()],
+        bump = auto_repay.bump,
+        has_one = borrower,
+        close = borrower
+    )]
+    pub auto_repay: Account<'info, AutoRepay>,
+    #[account(
+        mut,
+        associated_token::mint = auto_repay.mint,
+        associated_token::authority = auto_repay
+    )]
+    pub repay_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    #[account(
+        mut,
+        token::authority = borrower
+    )]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteLoan<'info> {
+    #[account(
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = borrower_reputation.bump
+    )]
+    pub borrower_reputation: Option<Account<'info, ReputationAccount>>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    /// Borrower's identity attestation; may be omitted while no attester is assigned
+    #[account(
+        seeds = [IDENTITY_SEED, borrower.key().as_ref()],
+        bump = identity_attestation.bump
+    )]
+    pub identity_attestation: Option<Account<'info, IdentityAttestation>>,
+    /// CHECK: Only used to derive the reputation PDA; quotes don't need the borrower's signature
+    pub borrower: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AttestReputation<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ReputationAttestation::INIT_SPACE,
+        seeds = [REPUTATION_ATTESTATION_SEED, reputation.owner.as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, ReputationAttestation>,
+    #[account(
+        seeds = [REPUTATION_SEED, reputation.owner.as_ref()],
+        bump = reputation.bump
+    )]
+    pub reputation: Account<'info, ReputationAccount>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CheckCreditTier<'info> {
+    #[account(
+        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
+        bump = reputation.bump
+    )]
+    pub reputation: Account<'info, ReputationAccount>,
+    /// CHECK: Only used to derive the reputation PDA; checks don't need the borrower's signature
+    pub borrower: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct StartAudit<'info> {
+    #[account(
+        init,
+        payer = auditor,
+        space = 8 + LoanAudit::INIT_SPACE,
+        seeds = [AUDIT_SEED, auditor.key().as_ref()],
+        bump
+    )]
+    pub audit: Account<'info, LoanAudit>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.can_audit(&auditor.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub auditor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AuditLoans<'info> {
+    #[account(
+        mut,
+        seeds = [AUDIT_SEED, auditor.key().as_ref()],
+        bump = audit.bump,
+        has_one = auditor
+    )]
+    pub audit: Account<'info, LoanAudit>,
+    pub auditor: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinishAudit<'info> {
+    #[account(
+        mut,
+        seeds = [AUDIT_SEED, auditor.key().as_ref()],
+        bump = audit.bump,
+        has_one = auditor,
+        close = auditor
+    )]
+    pub audit: Account<'info, LoanAudit>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.can_audit(&auditor.key()) @ ErrorCode::MissingRole
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub auditor: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// CHECK: Decoded and validated by `upgrade_config`; may still be in a legacy layout
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        owner = crate::ID
+    )]
+    pub config: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateReputation<'info> {
+    /// CHECK: Decoded and validated by `upgrade_reputation`; may still be in a legacy layout
+    #[account(
+        mut,
+        seeds = [REPUTATION_SEED, owner.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub reputation: UncheckedAccount<'info>,
+    /// CHECK: Only used to derive the reputation PDA
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct MigrateLoan<'info> {
+    /// CHECK: Decoded and validated by `upgrade_loan`; may still be in a legacy layout
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, borrower.key().as_ref(), loan_id.to_le_bytes().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub loan: UncheckedAccount<'info>,
+    /// CHECK: Only used to derive the loan PDA
+    pub borrower: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account Structures
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolConfig {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub oracle_authority: Pubkey,
+    pub protocol_fee_bps: u16, // Fee in basis points (100 = 1%)
+    pub total_loans_issued: u64,
+    pub total_volume: u64,
+    pub total_defaults: u64,
+    pub pause_flags: u8, // Bitmask of PAUSE_* flags
+    pub pauser: Pubkey,
+    pub fee_manager: Pubkey,
+    pub risk_manager: Pubkey,
+    pub reputation_steward: Pubkey,
+    pub tier_max_borrow: [u64; 4], // Indexed by credit tier
+    pub on_time_payment_bonus: i16,
+    pub late_payment_penalty: i16,
+    pub default_penalty: i16,
+    pub withdrawal_window_seconds: i64, // 0 on configs created before the field; see withdrawal_window()
+    pub unwithdrawn_penalty: i16,
+    pub request_ttl_seconds: i64, // 0 on configs created before the field; see request_ttl()
+    // Stats of closed loan accounts, which audits can no longer tally
+    pub archived_loans_issued: u64,
+    pub archived_volume: u64,
+    pub archived_defaults: u64,
+    pub tier_max_exposure: [u64; 4], // Indexed by credit tier; zeroed on configs created before the field, see max_exposure()
+    pub max_active_loans: u32, // 0 on configs created before the field; see active_loan_limit()
+    pub max_vouches_given: u8, // 0 on configs created before the field; see vouches_given_limit()
+    pub max_vouches_received: u8, // 0 on configs created before the field; see vouches_received_limit()
+    pub identity_attester: Pubkey, // Default pubkey leaves the identity gate off
+    pub reserved: [u8; CONFIG_RESERVED_BYTES],
+}
+
+impl ProtocolConfig {
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.pause_flags & flag != 0
+    }
+
+    /// Reputations and loan requests need an identity attestation while an attester is assigned
+    pub fn requires_identity(&self) -> bool {
+        self.identity_attester != Pubkey::default()
+    }
+
+    pub fn withdrawal_window(&self) -> i64 {
+        if self.withdrawal_window_seconds > 0 {
+            self.withdrawal_window_seconds
+        } else {
+            DEFAULT_WITHDRAWAL_WINDOW
+        }
+    }
+
+    pub fn request_ttl(&self) -> i64 {
+        if self.request_ttl_seconds > 0 {
+            self.request_ttl_seconds
+        } else {
+            DEFAULT_REQUEST_TTL
+        }
+    }
+
+    /// Outstanding principal a borrower in `credit_tier` may carry across their loans
+    pub fn max_exposure(&self, credit_tier: u8) -> u64 {
+        let limits = if self.tier_max_exposure == [0; 4] {
+            DEFAULT_TIER_MAX_EXPOSURE
+        } else {
+            self.tier_max_exposure
+        };
+        limits[credit_tier.min(CREDIT_TIER_D) as usize]
+    }
+
+    pub fn active_loan_limit(&self) -> u32 {
+        if self.max_active_loans > 0 {
+            self.max_active_loans
+        } else {
+            DEFAULT_MAX_ACTIVE_LOANS
+        }
+    }
+
+    pub fn vouches_given_limit(&self) -> u8 {
+        if self.max_vouches_given > 0 {
+            self.max_vouches_given
+        } else {
+            DEFAULT_MAX_VOUCHES_GIVEN
+        }
+    }
+
+    pub fn vouches_received_limit(&self) -> u8 {
+        if self.max_vouches_received > 0 {
+            self.max_vouches_received
+        } else {
+            DEFAULT_MAX_VOUCHES_RECEIVED
+        }
+    }
+
+    pub fn role_holder(&self, role: Role) -> Pubkey {
+        match role {
+            Role::Pauser => self.pauser,
+            Role::FeeManager => self.fee_manager,
+            Role::RiskManager => self.risk_manager,
+            Role::ReputationSteward => self.reputation_steward,
+            Role::Oracle => self.oracle_authority,
+            Role::IdentityAttester => self.identity_attester,
+        }
+    }
+
+    pub fn set_role_holder(&mut self, role: Role, holder: Pubkey) {
+        match role {
+            Role::Pauser => self.pauser = holder,
+            Role::FeeManager => self.fee_manager = holder,
+            Role::RiskManager => self.risk_manager = holder,
+            Role::ReputationSteward => self.reputation_steward = holder,
+            Role::Oracle => self.oracle_authority = holder,
+            Role::IdentityAttester => self.identity_attester = holder,
+        }
+    }
+
+    /// Revoked roles hold the default pubkey, which can never sign
+    pub fn has_role(&self, role: Role, key: &Pubkey) -> bool {
+        let holder = self.role_holder(role);
+        holder != Pubkey::default() && holder == *key
+    }
+
+    /// Audits are run by the owner or the risk manager
+    pub fn can_audit(&self, key: &Pubkey) -> bool {
+        self.authority == *key || self.has_role(Role::RiskManager, key)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ReputationAccount {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub credit_score: u16, // 0-1000
+    pub credit_tier: u8, // A=0, B=1, C=2, D=3
+    pub total_loans: u32,
+    pub active_loans: u32,
+    pub completed_loans: u32,
+    pub defaulted_loans: u32,
+    pub total_borrowed: u64,
+    pub total_repaid: u64,
+    pub on_time_payments: u32,
+    pub late_payments: u32,
+    pub created_at: i64,
+    pub last_updated: i64,
+    pub is_frozen: bool,
+    pub bump: u8,
+    // Summary of loan accounts closed via close_loan
+    pub archived_loans: u32,
+    pub archived_principal: u64,
+    pub archived_interest_paid: u64,
+    pub last_archived_loan_id: u64,
+    pub last_archived_at: i64,
+    pub outstanding_principal: u64, // Principal of funded and active loans; loans funded before the field are not counted
+    // Open vouches; the tier follows effective_score()
+    pub vouches_given: u8,
+    pub vouches_received: u8,
+    pub staked_score: u16,  // Staked on others by this owner's vouches
+    pub vouched_score: u16, // Staked on this owner by others' vouches
+    pub reserved: [u8; REPUTATION_RESERVED_BYTES],
+}
+
+impl ReputationAccount {
+    /// Score the credit tier follows: the owner's own score, plus what vouchers have
+    /// staked on them, minus what they have staked on others
+    pub fn effective_score(&self) -> u16 {
+        let score = self.credit_score as i32 + self.vouched_score as i32 - self.staked_score as i32;
+        score.clamp(MIN_CREDIT_SCORE as i32, MAX_CREDIT_SCORE as i32) as u16
+    }
+
+    pub fn refresh_tier(&mut self) {
+        self.credit_tier = calculate_credit_tier(self.effective_score());
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LoanAccount {
+    pub version: u8,
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub amount: u64,
+    pub funded_amount: u64,
+    pub duration_seconds: i64,
+    pub max_interest_rate_bps: u16,
+    pub actual_interest_rate_bps: u16,
+    pub suggested_interest_rate_bps: u16,
+    pub state: LoanState,
+    pub created_at: i64,
+    pub funded_at: i64,
+    pub due_date: i64,
+    pub repaid_at: i64,
+    #[max_len(1)]
+    pub lender: Option<Pubkey>,
+    pub repaid_amount: u64,
+    pub bump: u8,
+    pub withdrawal_deadline: i64, // Lender may reclaim unwithdrawn funds after this
+    pub expires_at: i64,          // Unfunded request can be expired after this
+    pub extension_count: u8,
+    pub refinance_count: u8,
+    pub refinanced_at: i64, // Interest accrues to the current lender from here (0 = never refinanced)
+    pub rebate_policy: RebatePolicy, // Set by the lender at funding or refinance
+    pub position_mint: Pubkey,       // Lender position token (default = funded before positions)
+    pub indexed: bool,               // Tracked in the borrower's BorrowerLoanIndex (false = created before indexes)
+    pub lender_profiled: bool,       // Counted in the recorded lender's LenderProfile (false = funded before profiles)
+    pub concentration_tracked: bool, // Counted in the ExposureLedger and PairExposure (false = funded before the limits)
+    pub funded_tier: u8,             // Borrower's tier when the ExposureLedger started counting the loan
+    pub guarantee_backing: u64,      // Limit raise from the loan's Guarantee (0 = no guarantor)
+    pub escrow_payer: Pubkey,        // Paid the escrow's rent (default = funded before it was recorded)
+    pub reserved: [u8; LOAN_RESERVED_BYTES],
+}
+
+impl LoanAccount {
+    /// Loans created before deadlines were recorded fall back to the config windows
+    pub fn request_expiry(&self, config: &ProtocolConfig) -> i64 {
+        if self.expires_at != 0 {
+            self.expires_at
+        } else {
+            self.created_at + config.request_ttl()
+        }
+    }
+
+    /// Wallet that paid the escrow's rent and gets it back when the loan is closed. Loans
+    /// funded before the payer was recorded fall back to the recorded lender
+    pub fn escrow_rent_payer(&self) -> Option<Pubkey> {
+        if self.escrow_payer != Pubkey::default() {
+            Some(self.escrow_payer)
+        } else {
+            self.lender
+        }
+    }
+
+    /// When the current lender's interest started accruing
+    pub fn interest_start(&self) -> i64 {
+        self.funded_at.max(self.refinanced_at)
+    }
+
+    /// Interest owed if the loan is settled at `now`. Without a rebate policy, or once the
+    /// loan is due, that is the full interest; before that, the accrued interest (at least
+    /// the policy's minimum) plus the prepayment fee, never more than the full interest.
+    pub fn interest_due(&self, now: i64) -> u64 {
+        let full_interest = calculate_interest(self.amount, self.actual_interest_rate_bps);
+        let policy = &self.rebate_policy;
+        if !policy.enabled || now >= self.due_date {
+            return full_interest;
+        }
+        
+        let accrued = calculate_accrued_interest(full_interest, self.interest_start(), self.due_date, now);
+        let minimum = calculate_interest(full_interest, policy.min_interest_bps);
+        let prepayment_fee = calculate_interest(self.amount, policy.prepayment_fee_bps);
+        (accrued.max(minimum) + prepayment_fee).min(full_interest)
+    }
+
+    /// Wallet entitled to the lender's side of the loan: whoever holds its position token,
+    /// or the recorded lender for loans funded before positions existed
+    pub fn position_holder(&self, position_token_account: Option<&TokenAccount>) -> Result<Pubkey> {
+        if self.position_mint == Pubkey::default() {
+            return self.lender.ok_or(error!(ErrorCode::LenderMismatch));
+        }
+        let position = position_token_account.ok_or(error!(ErrorCode::PositionMismatch))?;
+        require!(
+            position.mint == self.position_mint && position.amount == 1,
+            ErrorCode::PositionMismatch
+        );
+        Ok(position.owner)
+    }
+
+    pub fn withdrawal_deadline(&self, config: &ProtocolConfig) -> i64 {
+        if self.withdrawal_deadline != 0 {
+            self.withdrawal_deadline
+        } else {
+            self.funded_at + config.withdrawal_window()
+        }
+    }
+}
+
+/// Early repayment terms a lender offers when funding. Disabled (the default, and what
+/// loans funded before rebates existed read back) means the full interest is always due.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct RebatePolicy {
+    pub enabled: bool,
+    pub min_interest_bps: u16,   // Minimum share of the full interest charged
+    pub prepayment_fee_bps: u16, // Fee on the principal for repaying early
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct EscrowAccount {
+    pub loan_id: u64,
+    pub borrower: Pubkey,
+    pub bump: u8,
+}
+
+/// Assigns a borrower's loan IDs and lists their open loans, so clients can
+/// enumerate them without scanning program accounts
+#[account]
+#[derive(InitSpace)]
+pub struct BorrowerLoanIndex {
+    pub borrower: Pubkey,
+    pub next_loan_id: u64, // ID the borrower's next loan request gets
+    #[max_len(MAX_ACTIVE_LOAN_IDS)]
+    pub active_loan_ids: Vec<u64>, // Requested, funded or active loans, oldest first
+    pub bump: u8,
+}
+
+impl BorrowerLoanIndex {
+    /// Hand out the next loan ID and track it as open
+    pub fn assign(&mut self) -> Result<u64> {
+        require!(
+            self.active_loan_ids.len() < MAX_ACTIVE_LOAN_IDS,
+            ErrorCode::LoanIndexFull
+        );
+        let loan_id = self.next_loan_id;
+        self.next_loan_id += 1;
+        self.active_loan_ids.push(loan_id);
+        Ok(loan_id)
+    }
+}
+
+/// A lender's portfolio across every loan they funded or refinanced into. Loans are
+/// credited to the lender of record, even after the position token is sold
+#[account]
+#[derive(InitSpace)]
+pub struct LenderProfile {
+    pub lender: Pubkey,
+    pub total_deployed: u64,  // Principal committed across all loans
+    pub outstanding: u64,     // Principal in loans not yet repaid, defaulted or reclaimed
+    pub interest_earned: u64, // Interest received, net of protocol fees
+    pub losses: u64,          // Principal written off to defaults
+    pub loans_funded: u32,
+    pub loans_repaid: u32, // Includes loans refinanced away to another lender
+    pub loans_defaulted: u32,
+    pub loans_by_tier: [u32; 4], // Loans funded per borrower credit tier at funding
+    pub created_at: i64,
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+impl LenderProfile {
+    pub fn record_funding(&mut self, principal: u64, credit_tier: u8, now: i64) {
+        self.total_deployed += principal;
+        self.outstanding += principal;
+        self.loans_funded += 1;
+        self.loans_by_tier[credit_tier as usize] += 1;
+        self.last_updated = now;
+    }
+
+    /// Principal came back with `interest` on top, from the borrower or a refinancing lender
+    pub fn record_repayment(&mut self, principal: u64, interest: u64, now: i64) {
+        self.outstanding = self.outstanding.saturating_sub(principal);
+        self.interest_earned += interest;
+        self.loans_repaid += 1;
+        self.last_updated = now;
+    }
+
+    pub fn record_default(&mut self, principal: u64, now: i64) {
+        self.outstanding = self.outstanding.saturating_sub(principal);
+        self.losses += principal;
+        self.loans_defaulted += 1;
+        self.last_updated = now;
+    }
+
+    /// Funding the borrower never withdrew went back to the lender untouched
+    pub fn record_reclaim(&mut self, principal: u64, now: i64) {
+        self.outstanding = self.outstanding.saturating_sub(principal);
+        self.last_updated = now;
+    }
+}
+
+/// Protocol-wide debt limits and the principal outstanding against them
+#[account]
+#[derive(InitSpace)]
+pub struct ExposureLedger {
+    pub tier_debt_ceiling: [u64; 4], // Indexed by the borrower's credit tier at funding
+    pub tier_outstanding: [u64; 4],
+    pub max_total_outstanding: u64,
+    pub max_pair_exposure: u64, // Per (lender, borrower) pair; see PairExposure
+    pub bump: u8,
+}
+
+impl ExposureLedger {
+    pub fn total_outstanding(&self) -> u64 {
+        self.tier_outstanding.iter().sum()
+    }
+
+    /// Count a newly funded loan against its tier's ceiling and the protocol-wide cap
+    pub fn record_funding(&mut self, credit_tier: u8, principal: u64) -> Result<()> {
+        let tier = credit_tier.min(CREDIT_TIER_D) as usize;
+        require!(
+            self.tier_outstanding[tier].saturating_add(principal) <= self.tier_debt_ceiling[tier],
+            ErrorCode::TierDebtCeilingReached
+        );
+        require!(
+            self.total_outstanding().saturating_add(principal) <= self.max_total_outstanding,
+            ErrorCode::ProtocolDebtCapReached
+        );
+        self.tier_outstanding[tier] += principal;
+        Ok(())
+    }
+
+    pub fn release(&mut self, credit_tier: u8, principal: u64) {
+        let tier = credit_tier.min(CREDIT_TIER_D) as usize;
+        self.tier_outstanding[tier] = self.tier_outstanding[tier].saturating_sub(principal);
+    }
+}
+
+/// Principal a lender of record has outstanding with one borrower
+#[account]
+#[derive(InitSpace)]
+pub struct PairExposure {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub outstanding: u64,
+    pub active_loans: u32,
+    pub bump: u8,
+}
+
+impl PairExposure {
+    /// Fill in a pair account the funding instruction just created
+    pub fn open(&mut self, lender: Pubkey, borrower: Pubkey, bump: u8) {
+        if self.lender == Pubkey::default() {
+            self.lender = lender;
+            self.borrower = borrower;
+            self.bump = bump;
+        }
+    }
+
+    pub fn record_funding(&mut self, principal: u64, max_pair_exposure: u64) -> Result<()> {
+        self.record_draw(principal, max_pair_exposure, true)
+    }
+
+    pub fn release(&mut self, principal: u64) {
+        self.release_draw(principal, true);
+    }
+
+    /// Count a credit line draw; the line is one active loan while it has a drawn balance
+    pub fn record_draw(&mut self, principal: u64, max_pair_exposure: u64, opens_balance: bool) -> Result<()> {
+        require!(
+            self.outstanding.saturating_add(principal) <= max_pair_exposure,
+            ErrorCode::PairExposureExceeded
+        );
+        self.outstanding += principal;
+        if opens_balance {
+            self.active_loans += 1;
+        }
+        Ok(())
+    }
+
+    pub fn release_draw(&mut self, principal: u64, clears_balance: bool) {
+        self.outstanding = self.outstanding.saturating_sub(principal);
+        if clears_balance {
+            self.active_loans = self.active_loans.saturating_sub(1);
+        }
+    }
+}
+
+/// A mint the risk manager accepts for guarantee bonds
+#[account]
+#[derive(InitSpace)]
+pub struct AcceptedBondMint {
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+/// A guarantor's bond behind one of a borrower's loans, pledged against the borrower's next
+/// loan ID. The bond sits in the guarantee's associated token account until it is released
+/// back to the guarantor or claimed for a default.
+#[account]
+#[derive(InitSpace)]
+pub struct Guarantee {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub guarantor: Pubkey,
+    pub bond: u64,
+    pub backing: u64,   // Added to the borrower's limits for the loan: the bond, up to the guarantor's max borrow
+    pub attached: bool, // The loan request was made with this guarantee
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Guarantee {
+    /// Whether the bond can go back to the guarantor given the loan's current state, or
+    /// `None` once the loan account is closed. Guaranteed loans can only be closed after
+    /// the guarantee is settled, so a closed loan means the request was never funded
+    pub fn releasable(&self, loan: Option<&LoanAccount>) -> bool {
+        if !self.attached {
+            return true;
+        }
+        loan.is_none_or(|loan| matches!(loan.state, LoanState::Repaid | LoanState::Expired))
+    }
+}
+
+/// Score a voucher has staked on another user. The stake counts toward the vouchee's
+/// effective score and against the voucher's until the vouch is revoked, and is burned
+/// from the voucher's own score if the vouchee defaults in the meantime.
+#[account]
+#[derive(InitSpace)]
+pub struct Vouch {
+    pub voucher: Pubkey,
+    pub vouchee: Pubkey,
+    pub stake: u16,
+    pub vouchee_defaults: u32, // Vouchee's defaulted_loans when the vouch was issued
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Vouch {
+    /// The vouchee has defaulted on a loan since the vouch was issued
+    pub fn slashable(&self, vouchee: &ReputationAccount) -> bool {
+        vouchee.defaulted_loans > self.vouchee_defaults
+    }
+}
+
+/// An identity attester's statement that a wallet belongs to a verified person. Only
+/// attestations from the attester currently assigned in the config count.
+#[account]
+#[derive(InitSpace)]
+pub struct IdentityAttestation {
+    pub owner: Pubkey,
+    pub attester: Pubkey,
+    pub issued_at: i64,
+    pub expires_at: i64, // 0 if it never expires
+    pub bump: u8,
+}
+
+impl IdentityAttestation {
+    pub fn is_valid(&self, config: &ProtocolConfig, now: i64) -> bool {
+        self.attester == config.identity_attester && (self.expires_at == 0 || now < self.expires_at)
+    }
+}
+
+/// Revolving credit a lender has committed to a tier A or B borrower. The undrawn limit
+/// sits in the line's associated token account; interest accrues on the drawn balance and
+/// is billed when each statement closes, due by the close of the next one.
+#[account]
+#[derive(InitSpace)]
+pub struct CreditLine {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub limit: u64,
+    pub drawn: u64,             // Principal the borrower holds
+    pub interest_rate_bps: u16, // Charged on the drawn balance per statement period
+    pub accrued_interest: u64,  // Accrued during the current cycle, not yet billed
+    pub interest_due: u64,      // Billed on closed statements and not yet paid
+    pub last_billed: u64,       // Billed on the most recent statement
+    pub past_due: bool,         // A statement closed with an earlier bill unpaid
+    pub last_accrued_at: i64,
+    pub statement_ends_at: i64,
+    pub statements_closed: u32,
+    pub missed_statements: u32,
+    pub opened_at: i64,
+    pub funded_tier: u8, // Borrower's tier when opened; the ExposureLedger counts draws against it
+    pub bump: u8,
+}
+
+impl CreditLine {
+    /// Accrue interest on the drawn balance up to `now`, prorated over the statement period
+    pub fn accrue(&mut self, now: i64) {
+        let elapsed = now.saturating_sub(self.last_accrued_at).max(0) as u128;
+        let interest = self.drawn as u128 * self.interest_rate_bps as u128 * elapsed
+            / (10000 * CREDIT_LINE_STATEMENT_PERIOD as u128);
+        self.accrued_interest += interest as u64;
+        self.last_accrued_at = self.last_accrued_at.max(now);
+    }
+
+    /// Principal and interest the borrower would need to pay off the line
+    pub fn owed(&self) -> u64 {
+        self.drawn + self.accrued_interest + self.interest_due
+    }
+
+    pub fn available(&self) -> u64 {
+        self.limit - self.drawn
+    }
+
+    /// Apply a payment to billed interest, then accrued interest, then principal, and
+    /// return the `(interest, principal)` it covered
+    pub fn apply_payment(&mut self, payment: u64) -> (u64, u64) {
+        let billed = payment.min(self.interest_due);
+        self.interest_due -= billed;
+        let accrued = (payment - billed).min(self.accrued_interest);
+        self.accrued_interest -= accrued;
+        let principal = (payment - billed - accrued).min(self.drawn);
+        self.drawn -= principal;
+        if self.interest_due == 0 {
+            self.past_due = false;
+        }
+        (billed + accrued, principal)
+    }
+
+    /// Bill the cycle's accrued interest and start the next statement. The outcome judges
+    /// the previous bill: missed if any of it is still unpaid, paid if there was one
+    pub fn close_statement(&mut self, now: i64) -> StatementOutcome {
+        let outcome = if self.interest_due > 0 {
+            self.past_due = true;
+            self.missed_statements += 1;
+            StatementOutcome::Missed
+        } else if self.last_billed > 0 {
+            StatementOutcome::Paid
+        } else {
+            StatementOutcome::Unused
+        };
+        self.last_billed = self.accrued_interest;
+        self.interest_due += self.accrued_interest;
+        self.accrued_interest = 0;
+        self.statement_ends_at = now + CREDIT_LINE_STATEMENT_PERIOD;
+        self.statements_closed += 1;
+        outcome
+    }
+}
+
+/// A lender's standing offer to fund a borrower's loans from their own token account, up
+/// to `allowance` in total at a fixed rate and term. Funds stay with the lender until the
+/// borrower draws; the program moves them as the SPL delegate the lender approved.
+#[account]
+#[derive(InitSpace)]
+pub struct CreditDelegation {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub allowance: u64, // Left to draw
+    pub interest_rate_bps: u16,
+    pub duration_seconds: i64, // Term of each loan drawn
+    pub expires_at: i64,
+    pub total_drawn: u64,
+    pub loans_drawn: u32,
+    pub granted_at: i64,
+    pub bump: u8,
+}
+
+impl CreditDelegation {
+    /// Take a draw of `amount` out of the allowance, if the delegation is still live
+    pub fn record_draw(&mut self, amount: u64, now: i64) -> Result<()> {
+        require!(now < self.expires_at, ErrorCode::DelegationExpired);
+        require!(amount > 0 && amount <= self.allowance, ErrorCode::AllowanceExceeded);
+        self.allowance -= amount;
+        self.total_drawn += amount;
+        self.loans_drawn += 1;
+        Ok(())
+    }
+}
+
+/// A borrower's opt-in schedule for repaying one loan automatically. Keepers collect each
+/// installment through the program's SPL delegate into the schedule's vault, and the last
+/// one repays the loan from it. A single installment repays the loan in full.
+#[account]
+#[derive(InitSpace)]
+pub struct AutoRepay {
+    pub loan: Pubkey,
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub mint: Pubkey,
+    pub borrower_token_account: Pubkey, // Where keepers collect from
+    pub installments: u8,
+    pub installments_paid: u8,
+    pub scheduled_total: u64, // Principal plus full interest when enabled
+    pub collected: u64, // Held in the repay vault until the last installment
+    pub keeper_tip: u64, // Paid by the borrower for each installment
+    pub enabled_at: i64,
+    pub bump: u8,
+}
+
+impl AutoRepay {
+    /// When installment `number` (counting from 1) can first be collected:
+    /// `AUTO_REPAY_WINDOW` ahead of its even share of the time up to `due_date`
+    pub fn collectible_at(&self, number: u8, due_date: i64) -> i64 {
+        let term = (due_date - self.enabled_at).max(0);
+        let share = term * number as i64 / self.installments as i64;
+        (self.enabled_at + share - AUTO_REPAY_WINDOW).max(self.enabled_at)
+    }
+
+    /// Total the schedule should have collected once installment `number` is in
+    pub fn collected_by(&self, number: u8) -> u64 {
+        (self.scheduled_total as u128 * number as u128 / self.installments as u128) as u64
+    }
+}
+
+/// Snapshot of a reputation for other programs to read. Only meaningful between
+/// `valid_from` and `valid_until`; `attest_reputation` refreshes it.
+#[account]
+#[derive(InitSpace)]
+pub struct ReputationAttestation {
+    pub owner: Pubkey,
+    pub credit_score: u16,
+    pub credit_tier: u8,
+    pub total_loans: u32,
+    pub active_loans: u32,
+    pub completed_loans: u32,
+    pub defaulted_loans: u32,
+    pub is_frozen: bool,
+    pub valid_from: i64,
+    pub valid_until: i64,
+    pub bump: u8,
+}
+
+impl ReputationAttestation {
+    pub fn is_valid(&self, now: i64) -> bool {
+        self.valid_from <= now && now < self.valid_until
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LoanAudit {
+    pub auditor: Pubkey,
+    pub loans_counted: u64,
+    pub loans_issued: u64,
+    pub volume: u64,
+    pub defaults: u64,
+    pub last_loan: Pubkey, // Highest loan key tallied so far
+    pub started_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LoanQuote {
+    pub credit_score: u16,
+    pub credit_tier: u8,
+    pub max_borrow: u64,
+    pub max_exposure: u64,                // Outstanding principal the borrower's tier allows
+    pub outstanding_principal: u64,       // Already outstanding across the borrower's loans
+    pub suggested_interest_rate_bps: u16, // Minimum rate create_loan_request accepts
+    pub interest: u64,                    // At the suggested rate
+    pub protocol_fee: u64,
+    pub lender_amount: u64,
+    pub total_repayment: u64,
+    pub eligible: bool,
+    pub errors: Vec<u32>, // Error codes create_loan_request would fail with
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuditReport {
+    pub loans_counted: u64,
+    pub expected_loans_issued: u64,
+    pub counted_loans_issued: u64,
+    pub expected_volume: u64,
+    pub counted_volume: u64,
+    pub expected_defaults: u64,
+    pub counted_defaults: u64,
+    pub consistent: bool,
+}
+
+// Legacy (v0) layouts, kept only so `migrate_*` can decode accounts created before versioning
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct ProtocolConfigV0 {
+    pub authority: Pubkey,
+    pub oracle_authority: Pubkey,
+    pub protocol_fee_bps: u16,
+    pub total_loans_issued: u64,
+    pub total_volume: u64,
+    pub total_defaults: u64,
+    pub is_paused: bool,
+}
+
+impl From<ProtocolConfigV0> for ProtocolConfig {
+    fn from(legacy: ProtocolConfigV0) -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            authority: legacy.authority,
+            oracle_authority: legacy.oracle_authority,
+            protocol_fee_bps: legacy.protocol_fee_bps,
+            total_loans_issued: legacy.total_loans_issued,
+            total_volume: legacy.total_volume,
+            total_defaults: legacy.total_defaults,
+            // v0 pausing only ever blocked new requests and funding
+            pause_flags: if legacy.is_paused {
+                PAUSE_NEW_REQUESTS | PAUSE_FUNDING
+            } else {
+                0
+            },
+            pauser: legacy.authority,
+            fee_manager: legacy.authority,
+            risk_manager: legacy.authority,
+            reputation_steward: legacy.authority,
+            tier_max_borrow: [
+                TIER_A_MAX_BORROW,
+                TIER_B_MAX_BORROW,
+                TIER_C_MAX_BORROW,
+                TIER_D_MAX_BORROW,
+            ],
+            on_time_payment_bonus: ON_TIME_PAYMENT_BONUS,
+            late_payment_penalty: LATE_PAYMENT_PENALTY,
+            default_penalty: DEFAULT_PENALTY,
+            withdrawal_window_seconds: DEFAULT_WITHDRAWAL_WINDOW,
+            unwithdrawn_penalty: UNWITHDRAWN_PENALTY,
+            request_ttl_seconds: DEFAULT_REQUEST_TTL,
+            archived_loans_issued: 0,
+            archived_volume: 0,
+            archived_defaults: 0,
+            tier_max_exposure: DEFAULT_TIER_MAX_EXPOSURE,
+            max_active_loans: DEFAULT_MAX_ACTIVE_LOANS,
+            max_vouches_given: DEFAULT_MAX_VOUCHES_GIVEN,
+            max_vouches_received: DEFAULT_MAX_VOUCHES_RECEIVED,
+            identity_attester: Pubkey::default(),
+            reserved: [0; CONFIG_RESERVED_BYTES],
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct ReputationAccountV0 {
+    pub owner: Pubkey,
+    pub credit_score: u16,
+    pub credit_tier: u8,
+    pub total_loans: u32,
+    pub active_loans: u32,
+    pub completed_loans: u32,
+    pub defaulted_loans: u32,
+    pub total_borrowed: u64,
+    pub total_repaid: u64,
+    pub on_time_payments: u32,
+    pub late_payments: u32,
+    pub created_at: i64,
+    pub last_updated: i64,
+    pub is_frozen: bool,
+    pub bump: u8,
+}
+
+impl From<ReputationAccountV0> for ReputationAccount {
+    fn from(legacy: ReputationAccountV0) -> Self {
+        Self {
+            version: REPUTATION_VERSION,
+            owner: legacy.owner,
+            credit_score: legacy.credit_score,
+            credit_tier: legacy.credit_tier,
+            total_loans: legacy.total_loans,
+            active_loans: legacy.active_loans,
+            completed_loans: legacy.completed_loans,
+            defaulted_loans: legacy.defaulted_loans,
+            total_borrowed: legacy.total_borrowed,
+            total_repaid: legacy.total_repaid,
+            on_time_payments: legacy.on_time_payments,
+            late_payments: legacy.late_payments,
+            created_at: legacy.created_at,
+            last_updated: legacy.last_updated,
+            is_frozen: legacy.is_frozen,
+            bump: legacy.bump,
+            archived_loans: 0,
+            archived_principal: 0,
+            archived_interest_paid: 0,
+            last_archived_loan_id: 0,
+            last_archived_at: 0,
+            outstanding_principal: 0,
+            vouches_given: 0,
+            vouches_received: 0,
+            staked_score: 0,
+            vouched_score: 0,
+            reserved: [0; REPUTATION_RESERVED_BYTES],
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct LoanAccountV0 {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub amount: u64,
+    pub funded_amount: u64,
+    pub duration_seconds: i64,
+    pub max_interest_rate_bps: u16,
+    pub actual_interest_rate_bps: u16,
+    pub suggested_interest_rate_bps: u16,
+    pub state: LoanState,
+    pub created_at: i64,
+    pub funded_at: i64,
+    pub due_date: i64,
+    pub repaid_at: i64,
+    pub lender: Option<Pubkey>,
+    pub repaid_amount: u64,
+    pub bump: u8,
+}
+
+impl From<LoanAccountV0> for LoanAccount {
+    fn from(legacy: LoanAccountV0) -> Self {
+        Self {
+            version: LOAN_VERSION,
+            borrower: legacy.borrower,
+            loan_id: legacy.loan_id,
+            amount: legacy.amount,
+            funded_amount: legacy.funded_amount,
+            duration_seconds: legacy.duration_seconds,
+            max_interest_rate_bps: legacy.max_interest_rate_bps,
+            actual_interest_rate_bps: legacy.actual_interest_rate_bps,
+            suggested_interest_rate_bps: legacy.suggested_interest_rate_bps,
+            state: legacy.state,
+            created_at: legacy.created_at,
+            funded_at: legacy.funded_at,
+            due_date: legacy.due_date,
+            repaid_at: legacy.repaid_at,
+            lender: legacy.lender,
+            repaid_amount: legacy.repaid_amount,
+            bump: legacy.bump,
+            withdrawal_deadline: 0,
+            expires_at: 0,
+            extension_count: 0,
+            refinance_count: 0,
+            refinanced_at: 0,
+            rebate_policy: RebatePolicy::default(),
+            position_mint: Pubkey::default(),
+            indexed: false,
+            lender_profiled: false,
+            concentration_tracked: false,
+            funded_tier: 0,
+            guarantee_backing: 0,
+            escrow_payer: Pubkey::default(),
+            reserved: [0; LOAN_RESERVED_BYTES],
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Pauser,
+    FeeManager,
+    RiskManager,
+    ReputationSteward,
+    Oracle,
+    IdentityAttester,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum LoanState {
+    Requested,
+    Funded,
+    Active,
+    Repaid,
+    Defaulted,
+    Cancelled,
+    Expired, // Funded but never withdrawn; the lender reclaimed the funds
+}
+
+/// How a credit line's previous statement was settled when the next one closed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatementOutcome {
+    Unused, // Nothing was billed
+    Paid,
+    Missed,
+}
+
+// Events
+
+#[event]
+pub struct ConfigInitialized {
+    pub authority: Pubkey,
+    pub oracle_authority: Pubkey,
+    pub protocol_fee_bps: u16,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub signer: Pubkey,
+    pub protocol_fee_bps: u16,
+    pub pause_flags: u8,
+}
+
+#[event]
+pub struct RiskParamsUpdated {
+    pub risk_manager: Pubkey,
+    pub tier_max_borrow: [u64; 4],
+    pub on_time_payment_bonus: i16,
+    pub late_payment_penalty: i16,
+    pub default_penalty: i16,
+    pub unwithdrawn_penalty: i16,
+}
+
+#[event]
+pub struct LoanWindowsUpdated {
+    pub risk_manager: Pubkey,
+    pub withdrawal_window_seconds: i64,
+    pub request_ttl_seconds: i64,
+}
+
+#[event]
+pub struct ExposureLimitsUpdated {
+    pub risk_manager: Pubkey,
+    pub tier_max_exposure: [u64; 4],
+    pub max_active_loans: u32,
+}
+
+#[event]
+pub struct VouchLimitsUpdated {
+    pub risk_manager: Pubkey,
+    pub max_vouches_given: u8,
+    pub max_vouches_received: u8,
+}
+
+#[event]
+pub struct RoleUpdated {
+    pub role: Role,
+    pub holder: Pubkey, // Default pubkey when revoked
+}
+
+#[event]
+pub struct IdentityAttested {
+    pub owner: Pubkey,
+    pub attester: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct IdentityRevoked {
+    pub owner: Pubkey,
+    pub attester: Pubkey,
+}
+
+#[event]
+pub struct ReputationAttested {
+    pub owner: Pubkey,
+    pub credit_score: u16,
+    pub credit_tier: u8,
+    pub valid_until: i64,
+}
+
+#[event]
+pub struct ReputationCreated {
+    pub owner: Pubkey,
+    pub credit_score: u16,
+    pub credit_tier: u8,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct ReputationUnfrozen {
+    pub owner: Pubkey,
+    pub steward: Pubkey,
+    pub credit_score: u16,
+}
+
+#[event]
+pub struct VouchIssued {
+    pub voucher: Pubkey,
+    pub vouchee: Pubkey,
+    pub stake: u16,
+    pub score_change: ScoreChange, // Vouchee's
+}
+
+#[event]
+pub struct VouchRevoked {
+    pub voucher: Pubkey,
+    pub vouchee: Pubkey,
+    pub stake: u16,
+    pub score_change: ScoreChange, // Vouchee's
+}
+
+#[event]
+pub struct VouchSlashed {
+    pub voucher: Pubkey,
+    pub vouchee: Pubkey,
+    pub stake: u16,
+    pub score_change: ScoreChange, // Voucher's
 }
 
-#[derive(Accounts)]
-pub struct CreateReputation<'info> {
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + ReputationAccount::INIT_SPACE,
-        seeds = [REPUTATION_SEED, owner.key().as_ref()],
-        bump
-    )]
-    pub reputation: Account<'info, ReputationAccount>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct LoanIndexCreated {
+    pub borrower: Pubkey,
+    pub next_loan_id: u64,
 }
 
-#[derive(Accounts)]
-#[instruction(loan_id: u64)]
-pub struct CreateLoanRequest<'info> {
-    #[account(
-        init,
-        payer = borrower,
-        space = 8 + LoanAccount::INIT_SPACE,
-        seeds = [LOAN_SEED, borrower.key().as_ref(), loan_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub loan: Account<'info, LoanAccount>,
-    #[account(
-        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
-        bump = borrower_reputation.bump
-    )]
-    pub borrower_reputation: Account<'info, ReputationAccount>,
-    #[account(
-        seeds = [CONFIG_SEED],
-        bump
-    )]
-    pub config: Account<'info, ProtocolConfig>,
-    #[account(mut)]
-    pub borrower: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ConcentrationLimitsUpdated {
+    pub risk_manager: Pubkey,
+    pub tier_debt_ceiling: [u64; 4],
+    pub max_total_outstanding: u64,
+    pub max_pair_exposure: u64,
 }
 
-#[derive(Accounts)]
-pub struct FundLoan<'info> {
-    #[account(
-        mut,
-        seeds = [LOAN_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
-        bump = loan.bump,
-        has_one = borrower
-    )]
-    pub loan: Account<'info, LoanAccount>,
-    #[account(
-        init,
-        payer = lender,
-        space = 8 + EscrowAccount::INIT_SPACE,
-        seeds = [ESCROW_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
-    #[account(
-        mut,
-        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
-        bump = borrower_reputation.bump
-    )]
-    pub borrower_reputation: Account<'info, ReputationAccount>,
-    #[account(
-        mut,
-        seeds = [CONFIG_SEED],
-        bump
-    )]
-    pub config: Account<'info, ProtocolConfig>,
-    #[account(mut)]
-    pub config_account: Account<'info, ProtocolConfig>,
-    /// CHECK: This is the borrower's public key
-    pub borrower: AccountInfo<'info>,
-    #[account(mut)]
-    pub lender: Signer<'info>,
-    #[account(mut)]
-    pub lender_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct LenderProfileCreated {
+    pub lender: Pubkey,
+    pub created_at: i64,
 }
 
-#[derive(Accounts)]
-pub struct WithdrawLoan<'info> {
-    #[account(
-        mut,
-        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
-        bump = loan.bump,
-        has_one = borrower
-    )]
-    pub loan: Account<'info, LoanAccount>,
-    #[account(
-        mut,
-        seeds = [ESCROW_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
-        bump = escrow.bump
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
-    #[account(mut)]
-    pub borrower: Signer<'info>,
-    #[account(mut)]
-    pub borrower_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct LoanRequested {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub amount: u64,
+    pub duration_seconds: i64,
+    pub max_interest_rate_bps: u16,
+    pub suggested_interest_rate_bps: u16,
+    pub credit_tier: u8,
+    pub expires_at: i64,
 }
 
-#[derive(Accounts)]
-pub struct RepayLoan<'info> {
-    #[account(
-        mut,
-        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
-        bump = loan.bump,
-        has_one = borrower
-    )]
-    pub loan: Account<'info, LoanAccount>,
-    #[account(
-        mut,
-        seeds = [REPUTATION_SEED, borrower.key().as_ref()],
-        bump = borrower_reputation.bump
-    )]
-    pub borrower_reputation: Account<'info, ReputationAccount>,
-    #[account(
-        seeds = [CONFIG_SEED],
-        bump
-    )]
-    pub config: Account<'info, ProtocolConfig>,
-    #[account(mut)]
-    pub borrower: Signer<'info>,
-    #[account(mut)]
-    pub borrower_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub lender_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub protocol_treasury: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct LoanFunded {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub lender: Pubkey,
+    pub amount: u64,
+    pub interest_rate_bps: u16,
+    pub funded_at: i64,
+    pub due_date: i64,
+    pub position_mint: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct MarkDefault<'info> {
-    #[account(
-        mut,
-        seeds = [LOAN_SEED, loan.borrower.as_ref(), loan.loan_id.to_le_bytes().as_ref()],
-        bump = loan.bump
-    )]
-    pub loan: Account<'info, LoanAccount>,
-    #[account(
-        mut,
-        seeds = [REPUTATION_SEED, loan.borrower.as_ref()],
-        bump = borrower_reputation.bump
-    )]
-    pub borrower_reputation: Account<'info, ReputationAccount>,
-    #[account(
-        mut,
-        seeds = [CONFIG_SEED],
-        bump,
-        has_one = oracle_authority
-    )]
-    pub config: Account<'info, ProtocolConfig>,
-    pub oracle_authority: Signer<'info>,
+#[event]
+pub struct LoanWithdrawn {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub amount: u64,
 }
 
-#[derive(Accounts)]
-pub struct UnfreezeReputation<'info> {
-    #[account(
-        mut,
-        seeds = [REPUTATION_SEED, reputation.owner.as_ref()],
-        bump = reputation.bump
-    )]
-    pub reputation: Account<'info, ReputationAccount>,
-    #[account(
-        seeds = [CONFIG_SEED],
-        bump,
-        has_one = authority
-    )]
-    pub config: Account<'info, ProtocolConfig>,
-    pub authority: Signer<'info>,
+#[event]
+pub struct LoanRepaid {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub lender: Pubkey,
+    pub principal: u64,
+    pub interest: u64,
+    pub rebate: u64, // Interest waived for repaying early
+    pub protocol_fee: u64,
+    pub lender_amount: u64,
+    pub is_late: bool,
+    pub repaid_at: i64,
+    pub score_change: ScoreChange,
+}
+
+#[event]
+pub struct LoanDefaulted {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub lender: Pubkey,
+    pub principal: u64,
+    pub interest_rate_bps: u16,
+    pub due_date: i64,
+    pub oracle: Pubkey,
+    pub score_change: ScoreChange,
+}
+
+#[event]
+pub struct LoanCancelled {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LoanExtended {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub lender: Pubkey,
+    pub previous_due_date: i64,
+    pub due_date: i64,
+    pub extension_interest_rate_bps: u16,
+    pub interest_rate_bps: u16, // Total rate after the extension
+    pub extension_count: u8,
+}
+
+#[event]
+pub struct LoanRefinanced {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub previous_lender: Pubkey,
+    pub lender: Pubkey,
+    pub payoff: u64, // Becomes the loan's principal
+    pub accrued_interest: u64,
+    pub protocol_fee: u64,
+    pub previous_interest_rate_bps: u16,
+    pub interest_rate_bps: u16,
+    pub position_mint: Pubkey, // Replaces the outgoing lender's position
+}
+
+#[event]
+pub struct LoanRequestExpired {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub amount: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct LoanFundingReclaimed {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub lender: Pubkey,
+    pub amount: u64,
+    pub withdrawal_deadline: i64,
+    pub score_change: ScoreChange,
+}
+
+#[event]
+pub struct LoanClosed {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub lender: Pubkey,
+    pub state: LoanState,
+    pub principal: u64,
+    pub interest_paid: u64,
+    pub closed_at: i64,
+}
+
+#[event]
+pub struct BondMintUpdated {
+    pub risk_manager: Pubkey,
+    pub mint: Pubkey,
+    pub accepted: bool,
+}
+
+#[event]
+pub struct GuaranteePledged {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub guarantor: Pubkey,
+    pub bond: u64,
+    pub backing: u64,
+}
+
+#[event]
+pub struct GuaranteeReleased {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub guarantor: Pubkey,
+    pub bond: u64,
+}
+
+#[event]
+pub struct GuaranteeClaimed {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub guarantor: Pubkey,
+    pub holder: Pubkey,
+    pub bond: u64,
+    pub score_change: ScoreChange,
+}
+
+#[event]
+pub struct CreditLineOpened {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub limit: u64,
+    pub interest_rate_bps: u16,
+    pub statement_ends_at: i64,
+}
+
+#[event]
+pub struct CreditLineDrawn {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub amount: u64,
+    pub drawn: u64,
+}
+
+#[event]
+pub struct CreditLineRepaid {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub principal: u64,
+    pub interest: u64,
+    pub protocol_fee: u64,
+    pub drawn: u64,
+    pub interest_due: u64,
+}
+
+#[event]
+pub struct StatementClosed {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub outcome: StatementOutcome,
+    pub billed: u64,
+    pub interest_due: u64,
+    pub drawn: u64,
+    pub statement_ends_at: i64,
+    pub score_change: ScoreChange,
 }
 
-#[derive(Accounts)]
-pub struct CancelLoanRequest<'info> {
-    #[account(
-        mut,
-        seeds = [LOAN_SEED, borrower.key().as_ref(), loan.loan_id.to_le_bytes().as_ref()],
-        bump = loan.bump,
-        has_one = borrower
-    )]
-    pub loan: Account<'info, LoanAccount>,
-    pub borrower: Signer<'info>,
+#[event]
+pub struct CreditLineClosed {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub returned: u64,
+    pub statements_closed: u32,
+    pub missed_statements: u32,
 }
 
-// Account Structures
-#[account]
-#[derive(InitSpace)]
-pub struct ProtocolConfig {
-    pub authority: Pubkey,
-    pub oracle_authority: Pubkey,
-    pub protocol_fee_bps: u16, // Fee in basis points (100 = 1%)
-    pub total_loans_issued: u64,
-    pub total_volume: u64,
-    pub total_defaults: u64,
-    pub is_paused: bool,
+#[event]
+pub struct CreditDelegated {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub allowance: u64,
+    pub interest_rate_bps: u16,
+    pub duration_seconds: i64,
+    pub expires_at: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct ReputationAccount {
-    pub owner: Pubkey,
-    pub credit_score: u16, // 0-1000
-    pub credit_tier: u8, // A=0, B=1, C=2, D=3
-    pub total_loans: u32,
-    pub active_loans: u32,
-    pub completed_loans: u32,
-    pub defaulted_loans: u32,
-    pub total_borrowed: u64,
-    pub total_repaid: u64,
-    pub on_time_payments: u32,
-    pub late_payments: u32,
-    pub created_at: i64,
-    pub last_updated: i64,
-    pub is_frozen: bool,
-    pub bump: u8,
+#[event]
+pub struct CreditDelegationRevoked {
+    pub lender: Pubkey,
+    pub borrower: Pubkey,
+    pub allowance: u64,
+    pub total_drawn: u64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct LoanAccount {
+#[event]
+pub struct DelegatedCreditDrawn {
     pub borrower: Pubkey,
     pub loan_id: u64,
+    pub lender: Pubkey,
     pub amount: u64,
-    pub funded_amount: u64,
-    pub duration_seconds: i64,
-    pub max_interest_rate_bps: u16,
-    pub actual_interest_rate_bps: u16,
-    pub suggested_interest_rate_bps: u16,
-    pub state: LoanState,
-    pub created_at: i64,
-    pub funded_at: i64,
+    pub interest_rate_bps: u16,
     pub due_date: i64,
-    pub repaid_at: i64,
-    #[max_len(1)]
-    pub lender: Option<Pubkey>,
-    pub repaid_amount: u64,
-    pub bump: u8,
+    pub allowance: u64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct EscrowAccount {
+#[event]
+pub struct AutoRepayEnabled {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub installments: u8,
+    pub scheduled_total: u64,
+    pub keeper_tip: u64,
+    pub due_date: i64,
+}
+
+#[event]
+pub struct AutoRepayExecuted {
+    pub borrower: Pubkey,
     pub loan_id: u64,
+    pub installment: u8,
+    pub installments: u8,
+    pub amount: u64,
+    pub collected: u64,
+    pub keeper: Pubkey,
+    pub keeper_tip: u64,
+}
+
+#[event]
+pub struct AutoRepayDisabled {
     pub borrower: Pubkey,
-    pub bump: u8,
+    pub loan_id: u64,
+    pub installments_paid: u8,
+    pub returned: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum LoanState {
-    Requested,
-    Funded,
-    Active,
-    Repaid,
-    Defaulted,
-    Cancelled,
+#[event]
+pub struct AuditStarted {
+    pub auditor: Pubkey,
+    pub started_at: i64,
+}
+
+#[event]
+pub struct LoansAudited {
+    pub auditor: Pubkey,
+    pub batch_size: u32,
+    pub loans_counted: u64,
+    pub last_loan: Pubkey,
+}
+
+/// Expected values are the config's stats, counted values what the audit tallied
+#[event]
+pub struct AuditFinished {
+    pub auditor: Pubkey,
+    pub report: AuditReport,
+}
+
+#[event]
+pub struct ConfigMigrated {
+    pub authority: Pubkey,
+    pub version: u8,
+}
+
+#[event]
+pub struct ReputationMigrated {
+    pub owner: Pubkey,
+    pub payer: Pubkey,
+    pub version: u8,
+}
+
+#[event]
+pub struct LoanMigrated {
+    pub borrower: Pubkey,
+    pub loan_id: u64,
+    pub payer: Pubkey,
+    pub version: u8,
+}
+
+/// Credit score and tier movement carried by events that touch reputation
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoreChange {
+    pub previous_score: u16,
+    pub new_score: u16,
+    pub score_delta: i16,
+    pub previous_tier: u8,
+    pub new_tier: u8,
+}
+
+impl ScoreChange {
+    pub fn new(previous_score: u16, previous_tier: u8, reputation: &ReputationAccount) -> Self {
+        Self {
+            previous_score,
+            new_score: reputation.credit_score,
+            score_delta: reputation.credit_score as i16 - previous_score as i16,
+            previous_tier,
+            new_tier: reputation.credit_tier,
+        }
+    }
 }
 
 // Error Codes
@@ -778,4 +6257,852 @@ pub enum ErrorCode {
     LoanNotDue,
     #[msg("Invalid protocol fee")]
     InvalidFee,
+    #[msg("Signer does not hold the role required for this operation")]
+    MissingRole,
+    #[msg("Role holder cannot be the default pubkey")]
+    InvalidRoleHolder,
+    #[msg("Invalid risk parameters")]
+    InvalidRiskParams,
+    #[msg("Unknown pause flag bits")]
+    InvalidPauseFlags,
+    #[msg("Account is already at the current layout version")]
+    AlreadyMigrated,
+    #[msg("Account layout version is not supported")]
+    UnsupportedAccountVersion,
+    #[msg("Migrated account does not match the supplied keys")]
+    MigrationAccountMismatch,
+    #[msg("Audited loans must be passed in strictly ascending key order")]
+    AuditOrderViolation,
+    #[msg("Lender does not match the loan")]
+    LenderMismatch,
+    #[msg("Borrower's withdrawal window has not passed yet")]
+    WithdrawalWindowOpen,
+    #[msg("Loan request has expired")]
+    LoanRequestExpired,
+    #[msg("Loan request has not expired yet")]
+    LoanRequestNotExpired,
+    #[msg("Loan has already been extended the maximum number of times")]
+    MaxExtensionsReached,
+    #[msg("Rebate policy basis points must not exceed 10000")]
+    InvalidRebatePolicy,
+    #[msg("Position token account does not hold the loan's lender position")]
+    PositionMismatch,
+    #[msg("Borrower already has the maximum number of open loans in their loan index")]
+    LoanIndexFull,
+    #[msg("Loan is tracked in a loan index that was not supplied")]
+    LoanIndexMissing,
+    #[msg("Loan is tracked in a lender profile that was not supplied")]
+    LenderProfileMissing,
+    #[msg("Borrower already has the maximum number of loans outstanding")]
+    TooManyActiveLoans,
+    #[msg("Loan would take the borrower's outstanding principal past their tier's limit")]
+    ExceedsMaxExposure,
+    #[msg("Lender's outstanding principal with this borrower would exceed the pair limit")]
+    PairExposureExceeded,
+    #[msg("Outstanding principal for the borrower's tier would exceed its debt ceiling")]
+    TierDebtCeilingReached,
+    #[msg("Protocol-wide outstanding principal would exceed its cap")]
+    ProtocolDebtCapReached,
+    #[msg("Loan is counted in concentration limits whose accounts were not supplied")]
+    ConcentrationAccountsMissing,
+    #[msg("Guarantor must be someone else in good standing at or above the minimum guarantor tier")]
+    GuarantorIneligible,
+    #[msg("Guarantee bond must be greater than zero")]
+    InvalidBond,
+    #[msg("Guarantee still backs a loan that has not been settled")]
+    GuaranteeLocked,
+    #[msg("Loan's guarantee must be released or claimed first")]
+    GuaranteeOutstanding,
+    #[msg("Voucher must be someone else in good standing with the minimum voucher score of their own")]
+    VoucherIneligible,
+    #[msg("Vouch stake must be between 1 and the maximum, and within the voucher's score")]
+    InvalidVouchStake,
+    #[msg("Voucher or vouchee already has the maximum number of vouches")]
+    VouchLimitReached,
+    #[msg("Vouch backs outstanding loans or was forfeited to a default")]
+    VouchInUse,
+    #[msg("Vouchee has not defaulted since the vouch was issued")]
+    VouchNotSlashable,
+    #[msg("Wallet has no live identity attestation from the protocol's attester")]
+    IdentityNotAttested,
+    #[msg("Identity attestation must expire in the future, or never")]
+    InvalidAttestationExpiry,
+    #[msg("Borrower's credit tier is below the required tier")]
+    CreditTierTooLow,
+    #[msg("Credit lines are only open to unfrozen tier A and B borrowers")]
+    CreditLineIneligible,
+    #[msg("Credit limit must be positive and within the borrower's max borrow amount")]
+    InvalidCreditLimit,
+    #[msg("Draw exceeds the credit line's available limit")]
+    CreditLimitExceeded,
+    #[msg("Credit line statement period is over and must be closed first")]
+    StatementPending,
+    #[msg("Credit line statement period is not over yet")]
+    StatementNotDue,
+    #[msg("Credit line has a missed statement that must be paid first")]
+    PastDueBalance,
+    #[msg("Credit line still has principal or interest owed")]
+    CreditLineOutstanding,
+    #[msg("Repayment must cover part of what is owed")]
+    InvalidRepaymentAmount,
+    #[msg("Credit delegation needs a positive allowance and an expiry in the future")]
+    InvalidDelegationTerms,
+    #[msg("Credit delegation has expired")]
+    DelegationExpired,
+    #[msg("Draw exceeds the credit delegation's remaining allowance")]
+    AllowanceExceeded,
+    #[msg("Token owner has not approved the delegate PDA for this amount")]
+    DelegateApprovalMissing,
+    #[msg("Auto-repay needs 1 to 12 installments, a keeper tip within the cap and a loan not yet due")]
+    InvalidAutoRepaySchedule,
+    #[msg("Next auto-repay installment is not due yet")]
+    InstallmentNotDue,
+    #[msg("Rent refund must go to the wallet that paid it")]
+    RentPayerMismatch,
+    #[msg("Payoff must be made in the loan's mint")]
+    PayoffMintMismatch,
+    #[msg("A loan can't be refinanced to its current lender")]
+    SelfRefinance,
+    #[msg("A loan already exists at this loan ID")]
+    LoanIdTaken,
+    #[msg("Config update must set the protocol fee or the pause flags")]
+    EmptyConfigUpdate,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lays out a legacy account exactly as v0 allocated it: discriminator, data, zero padding
+    fn legacy_account<T: AnchorSerialize>(discriminator: &[u8], value: &T, space: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        value.serialize(&mut data).unwrap();
+        data.resize(8 + space, 0);
+        data
+    }
+
+    fn current_account<T: AccountSerialize>(value: &T, space: usize) -> Vec<u8> {
+        let mut data = vec![0; 8 + space];
+        value.try_serialize(&mut &mut data[..]).unwrap();
+        data
+    }
+
+    fn expect_error<T>(result: Result<T>) -> Error {
+        match result {
+            Ok(_) => panic!("expected the upgrade to fail"),
+            Err(error) => error,
+        }
+    }
+
+    fn legacy_config(is_paused: bool) -> ProtocolConfigV0 {
+        ProtocolConfigV0 {
+            authority: Pubkey::new_unique(),
+            oracle_authority: Pubkey::new_unique(),
+            protocol_fee_bps: 150,
+            total_loans_issued: 7,
+            total_volume: 70_000_000_000,
+            total_defaults: 2,
+            is_paused,
+        }
+    }
+
+    fn legacy_reputation() -> ReputationAccountV0 {
+        ReputationAccountV0 {
+            owner: Pubkey::new_unique(),
+            credit_score: 650,
+            credit_tier: CREDIT_TIER_B,
+            total_loans: 4,
+            active_loans: 1,
+            completed_loans: 3,
+            defaulted_loans: 0,
+            total_borrowed: 40_000_000_000,
+            total_repaid: 33_000_000_000,
+            on_time_payments: 2,
+            late_payments: 1,
+            created_at: 1_700_000_000,
+            last_updated: 1_700_500_000,
+            is_frozen: false,
+            bump: 254,
+        }
+    }
+
+    fn legacy_loan(lender: Option<Pubkey>) -> LoanAccountV0 {
+        LoanAccountV0 {
+            borrower: Pubkey::new_unique(),
+            loan_id: 42,
+            amount: 10_000_000_000,
+            funded_amount: 10_000_000_000,
+            duration_seconds: 86400 * 30,
+            max_interest_rate_bps: 1500,
+            actual_interest_rate_bps: 1000,
+            suggested_interest_rate_bps: 1010,
+            state: LoanState::Active,
+            created_at: 1_700_000_000,
+            funded_at: 1_700_000_100,
+            due_date: 1_702_592_100,
+            repaid_at: 0,
+            lender,
+            repaid_amount: 0,
+            bump: 253,
+        }
+    }
+
+    #[test]
+    fn accrued_interest_is_prorated_until_due() {
+        assert_eq!(calculate_accrued_interest(1_000, 100, 200, 100), 0);
+        assert_eq!(calculate_accrued_interest(1_000, 100, 200, 150), 500);
+        assert_eq!(calculate_accrued_interest(1_000, 100, 200, 200), 1_000);
+        assert_eq!(calculate_accrued_interest(1_000, 100, 200, 900), 1_000);
+        // Clock before the start never accrues negative interest
+        assert_eq!(calculate_accrued_interest(1_000, 100, 200, 50), 0);
+    }
+
+    #[test]
+    fn interest_rate_saturates_outside_valid_durations() {
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_C, 86400 * 30), 1010);
+        // Negative durations add no duration factor
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_C, -86400 * 365), 1000);
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_C, i64::MIN), 1000);
+        // A thousand years, or the largest duration, saturates instead of overflowing
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_D, 86400 * 365 * 1000), u16::MAX);
+        assert_eq!(calculate_interest_rate(CREDIT_TIER_A, i64::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn extensions_cap_the_added_and_total_interest_rate() {
+        assert_eq!(extended_interest_rate(1000, 500).unwrap(), 1500);
+        assert_eq!(extended_interest_rate(8000, MAX_EXTENSION_RATE_BPS).unwrap(), 10000);
+        assert_eq!(
+            expect_error(extended_interest_rate(1000, MAX_EXTENSION_RATE_BPS + 1)),
+            error!(ErrorCode::InterestRateTooHigh)
+        );
+        // Repeated extensions can't push the rate past 100%, let alone to u16::MAX
+        assert_eq!(
+            expect_error(extended_interest_rate(9000, MAX_EXTENSION_RATE_BPS)),
+            error!(ErrorCode::InterestRateTooHigh)
+        );
+        assert!(extended_interest_rate(u16::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn early_repayment_follows_the_rebate_policy() {
+        let mut loan = LoanAccount::from(legacy_loan(None));
+        loan.amount = 10_000;
+        loan.actual_interest_rate_bps = 1000; // 1_000 full interest
+        loan.funded_at = 0;
+        loan.due_date = 1_000;
+        let halfway = 500;
+
+        // Disabled: full interest, as before rebates existed
+        assert_eq!(loan.interest_due(halfway), 1_000);
+
+        loan.rebate_policy = RebatePolicy { enabled: true, ..Default::default() };
+        assert_eq!(loan.interest_due(halfway), 500);
+        assert_eq!(loan.interest_due(1_000), 1_000);
+
+        // A minimum of 75% of the interest outweighs what has accrued
+        loan.rebate_policy.min_interest_bps = 7500;
+        assert_eq!(loan.interest_due(halfway), 750);
+
+        // A 1% prepayment fee on the principal, capped at the full interest
+        loan.rebate_policy = RebatePolicy { enabled: true, min_interest_bps: 0, prepayment_fee_bps: 100 };
+        assert_eq!(loan.interest_due(halfway), 600);
+        loan.rebate_policy.prepayment_fee_bps = 2000;
+        assert_eq!(loan.interest_due(halfway), 1_000);
+    }
+
+    #[test]
+    fn loan_index_assigns_ids_and_tracks_open_loans() {
+        let mut loan_index = BorrowerLoanIndex {
+            borrower: Pubkey::new_unique(),
+            next_loan_id: 7,
+            active_loan_ids: Vec::new(),
+            bump: 255,
+        };
+        assert_eq!(loan_index.assign().unwrap(), 7);
+        assert_eq!(loan_index.assign().unwrap(), 8);
+        assert_eq!(loan_index.active_loan_ids, vec![7, 8]);
+
+        let mut loan = LoanAccount::from(legacy_loan(None));
+        loan.loan_id = 7;
+        loan.indexed = true;
+        release_loan_id(&loan, Some(&mut loan_index)).unwrap();
+        assert_eq!(loan_index.active_loan_ids, vec![8]);
+        assert_eq!(
+            release_loan_id(&loan, None).unwrap_err(),
+            error!(ErrorCode::LoanIndexMissing)
+        );
+
+        // Untracked legacy loans settle without an index
+        loan.indexed = false;
+        release_loan_id(&loan, None).unwrap();
+
+        while loan_index.active_loan_ids.len() < MAX_ACTIVE_LOAN_IDS {
+            loan_index.assign().unwrap();
+        }
+        assert_eq!(loan_index.assign().unwrap_err(), error!(ErrorCode::LoanIndexFull));
+        // IDs are never reused
+        assert_eq!(loan_index.next_loan_id, 7 + MAX_ACTIVE_LOAN_IDS as u64 + 1);
+
+        // An index can't start on an ID a hand-picked loan already holds
+        let (key, owner) = (Pubkey::new_unique(), crate::ID);
+        let (mut lamports, mut data) = (1_000_000, vec![0u8; 8 + LoanAccount::INIT_SPACE]);
+        let taken = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(check_loan_id_unused(&taken).unwrap_err(), error!(ErrorCode::LoanIdTaken));
+        let (mut lamports, mut data) = (0, Vec::new());
+        let free = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        check_loan_id_unused(&free).unwrap();
+    }
+
+    #[test]
+    fn lender_profile_follows_loans_to_settlement() {
+        let mut lender_profile = LenderProfile {
+            lender: Pubkey::new_unique(),
+            total_deployed: 0,
+            outstanding: 0,
+            interest_earned: 0,
+            losses: 0,
+            loans_funded: 0,
+            loans_repaid: 0,
+            loans_defaulted: 0,
+            loans_by_tier: [0; 4],
+            created_at: 0,
+            last_updated: 0,
+            bump: 255,
+        };
+        lender_profile.record_funding(1_000, CREDIT_TIER_B, 10);
+        lender_profile.record_funding(500, CREDIT_TIER_D, 20);
+        lender_profile.record_funding(200, CREDIT_TIER_D, 30);
+        assert_eq!(lender_profile.loans_by_tier, [0, 1, 0, 2]);
+        assert_eq!(lender_profile.outstanding, 1_700);
+
+        lender_profile.record_repayment(1_000, 99, 40);
+        lender_profile.record_default(500, 50);
+        lender_profile.record_reclaim(200, 60);
+        assert_eq!(lender_profile.total_deployed, 1_700);
+        assert_eq!(lender_profile.outstanding, 0);
+        assert_eq!(lender_profile.interest_earned, 99);
+        assert_eq!(lender_profile.losses, 500);
+        assert_eq!((lender_profile.loans_repaid, lender_profile.loans_defaulted), (1, 1));
+        assert_eq!(lender_profile.last_updated, 60);
+
+        // Loans funded before profiles existed settle without one
+        let mut loan = LoanAccount::from(legacy_loan(Some(lender_profile.lender)));
+        assert!(settled_lender_profile(&loan, None).unwrap().is_none());
+        loan.lender_profiled = true;
+        assert_eq!(
+            expect_error(settled_lender_profile(&loan, None)),
+            error!(ErrorCode::LenderProfileMissing)
+        );
+        assert!(settled_lender_profile(&loan, Some(&mut lender_profile)).unwrap().is_some());
+    }
+
+    #[test]
+    fn concentration_limits_follow_loans_to_settlement() {
+        let mut exposure_ledger = ExposureLedger {
+            tier_debt_ceiling: [1_000, 500, 0, 0],
+            tier_outstanding: [0; 4],
+            max_total_outstanding: 1_200,
+            max_pair_exposure: 600,
+            bump: 255,
+        };
+        let mut pair_exposure = PairExposure {
+            lender: Pubkey::default(),
+            borrower: Pubkey::default(),
+            outstanding: 0,
+            active_loans: 0,
+            bump: 0,
+        };
+        let (lender, borrower) = (Pubkey::new_unique(), Pubkey::new_unique());
+        pair_exposure.open(lender, borrower, 254);
+        // Reopening an existing pair never resets it
+        pair_exposure.open(Pubkey::new_unique(), borrower, 1);
+        assert_eq!((pair_exposure.lender, pair_exposure.bump), (lender, 254));
+
+        exposure_ledger.record_funding(CREDIT_TIER_A, 800).unwrap();
+        assert_eq!(
+            expect_error(exposure_ledger.record_funding(CREDIT_TIER_A, 201)),
+            error!(ErrorCode::TierDebtCeilingReached)
+        );
+        assert_eq!(
+            expect_error(exposure_ledger.record_funding(CREDIT_TIER_B, 401)),
+            error!(ErrorCode::ProtocolDebtCapReached)
+        );
+        // A zero ceiling closes the tier to new funding
+        assert_eq!(
+            expect_error(exposure_ledger.record_funding(CREDIT_TIER_C, 1)),
+            error!(ErrorCode::TierDebtCeilingReached)
+        );
+        exposure_ledger.record_funding(CREDIT_TIER_B, 400).unwrap();
+        assert_eq!(exposure_ledger.total_outstanding(), 1_200);
+
+        pair_exposure.record_funding(600, exposure_ledger.max_pair_exposure).unwrap();
+        assert_eq!(
+            expect_error(pair_exposure.record_funding(1, exposure_ledger.max_pair_exposure)),
+            error!(ErrorCode::PairExposureExceeded)
+        );
+
+        let mut loan = LoanAccount::from(legacy_loan(Some(lender)));
+        loan.amount = 600;
+        loan.funded_tier = CREDIT_TIER_A;
+        // Loans funded before the limits existed settle without them
+        release_concentration(&loan, None, None).unwrap();
+        loan.concentration_tracked = true;
+        assert_eq!(
+            expect_error(release_concentration(&loan, Some(&mut exposure_ledger), None)),
+            error!(ErrorCode::ConcentrationAccountsMissing)
+        );
+        release_concentration(&loan, Some(&mut exposure_ledger), Some(&mut pair_exposure)).unwrap();
+        assert_eq!(exposure_ledger.tier_outstanding, [200, 400, 0, 0]);
+        assert_eq!((pair_exposure.outstanding, pair_exposure.active_loans), (0, 0));
+
+        // A credit line counts once while it has a balance, however many draws it takes
+        pair_exposure.record_draw(200, exposure_ledger.max_pair_exposure, true).unwrap();
+        pair_exposure.record_draw(300, exposure_ledger.max_pair_exposure, false).unwrap();
+        assert_eq!(
+            expect_error(pair_exposure.record_draw(101, exposure_ledger.max_pair_exposure, false)),
+            error!(ErrorCode::PairExposureExceeded)
+        );
+        assert_eq!((pair_exposure.outstanding, pair_exposure.active_loans), (500, 1));
+        pair_exposure.release_draw(400, false);
+        pair_exposure.release_draw(100, true);
+        assert_eq!((pair_exposure.outstanding, pair_exposure.active_loans), (0, 0));
+    }
+
+    #[test]
+    fn guarantees_release_once_the_loan_no_longer_needs_them() {
+        let mut guarantee = Guarantee {
+            borrower: Pubkey::new_unique(),
+            loan_id: 1,
+            guarantor: Pubkey::new_unique(),
+            bond: 50,
+            backing: 50,
+            attached: false,
+            created_at: 0,
+            bump: 255,
+        };
+        let mut loan = LoanAccount::from(legacy_loan(Some(Pubkey::new_unique())));
+        loan.state = LoanState::Active;
+        // A pledge no request was made with can always be withdrawn
+        assert!(guarantee.releasable(Some(&loan)));
+
+        guarantee.attached = true;
+        for (state, releasable) in [
+            (LoanState::Requested, false),
+            (LoanState::Funded, false),
+            (LoanState::Active, false),
+            (LoanState::Defaulted, false),
+            (LoanState::Repaid, true),
+            (LoanState::Expired, true),
+        ] {
+            loan.state = state;
+            assert_eq!(guarantee.releasable(Some(&loan)), releasable);
+        }
+        // Cancelled and expired requests are closed straight away
+        assert!(guarantee.releasable(None));
+    }
+
+    #[test]
+    fn vouches_move_effective_tiers_and_slash_after_a_default() {
+        let mut voucher = ReputationAccount::from(legacy_reputation());
+        voucher.credit_score = TIER_A_THRESHOLD + 20;
+        let mut vouchee = ReputationAccount::from(legacy_reputation());
+        vouchee.credit_score = TIER_A_THRESHOLD - 30;
+        let vouch = Vouch {
+            voucher: voucher.owner,
+            vouchee: vouchee.owner,
+            stake: MAX_VOUCH_STAKE,
+            vouchee_defaults: vouchee.defaulted_loans,
+            created_at: 0,
+            bump: 255,
+        };
+
+        // The stake lifts the vouchee into tier A and drops the voucher out of it
+        voucher.staked_score += vouch.stake;
+        voucher.refresh_tier();
+        vouchee.vouched_score += vouch.stake;
+        vouchee.refresh_tier();
+        assert_eq!(vouchee.credit_tier, CREDIT_TIER_A);
+        assert_eq!(voucher.credit_tier, CREDIT_TIER_B);
+        assert!(!vouch.slashable(&vouchee));
+
+        vouchee.defaulted_loans += 1;
+        assert!(vouch.slashable(&vouchee));
+    }
+
+    #[test]
+    fn identity_gate_only_accepts_live_attestations_from_the_current_attester() {
+        let mut config = ProtocolConfig::from(legacy_config(false));
+        assert!(check_identity(&config, None, 100).is_ok());
+
+        let attester = Pubkey::new_unique();
+        config.set_role_holder(Role::IdentityAttester, attester);
+        let mut attestation = IdentityAttestation {
+            owner: Pubkey::new_unique(),
+            attester,
+            issued_at: 0,
+            expires_at: 200,
+            bump: 255,
+        };
+        assert_eq!(
+            check_identity(&config, None, 100).unwrap_err(),
+            error!(ErrorCode::IdentityNotAttested)
+        );
+        assert!(check_identity(&config, Some(&attestation), 100).is_ok());
+        assert!(check_identity(&config, Some(&attestation), 200).is_err());
+
+        // Replacing the attester retires everything the previous one issued
+        attestation.expires_at = 0;
+        config.set_role_holder(Role::IdentityAttester, Pubkey::new_unique());
+        assert!(check_identity(&config, Some(&attestation), 100).is_err());
+    }
+
+    #[test]
+    fn reputation_attestations_lapse_after_their_window() {
+        let attestation = ReputationAttestation {
+            owner: Pubkey::new_unique(),
+            credit_score: 650,
+            credit_tier: CREDIT_TIER_B,
+            total_loans: 4,
+            active_loans: 1,
+            completed_loans: 3,
+            defaulted_loans: 0,
+            is_frozen: false,
+            valid_from: 1_000,
+            valid_until: 1_000 + REPUTATION_ATTESTATION_TTL,
+            bump: 255,
+        };
+        assert!(!attestation.is_valid(999));
+        assert!(attestation.is_valid(1_000));
+        assert!(!attestation.is_valid(1_000 + REPUTATION_ATTESTATION_TTL));
+    }
+
+    #[test]
+    fn credit_lines_bill_interest_and_judge_the_previous_statement() {
+        let mut line = CreditLine {
+            lender: Pubkey::new_unique(),
+            borrower: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            limit: 1_000_000,
+            drawn: 0,
+            interest_rate_bps: 100,
+            accrued_interest: 0,
+            interest_due: 0,
+            last_billed: 0,
+            past_due: false,
+            last_accrued_at: 0,
+            statement_ends_at: CREDIT_LINE_STATEMENT_PERIOD,
+            statements_closed: 0,
+            missed_statements: 0,
+            opened_at: 0,
+            funded_tier: CREDIT_TIER_A,
+            bump: 255,
+        };
+
+        // Nothing drawn, nothing billed
+        line.accrue(CREDIT_LINE_STATEMENT_PERIOD);
+        assert_eq!(line.close_statement(CREDIT_LINE_STATEMENT_PERIOD), StatementOutcome::Unused);
+
+        // Half a period on 400_000 at 1% per period
+        line.drawn = 400_000;
+        line.accrue(CREDIT_LINE_STATEMENT_PERIOD * 3 / 2);
+        assert_eq!(line.accrued_interest, 2_000);
+        assert_eq!(line.available(), 600_000);
+        line.accrue(CREDIT_LINE_STATEMENT_PERIOD * 2);
+        assert_eq!(line.close_statement(CREDIT_LINE_STATEMENT_PERIOD * 2), StatementOutcome::Unused);
+        assert_eq!((line.interest_due, line.owed()), (4_000, 404_000));
+
+        // Payments clear billed interest before principal
+        assert_eq!(line.apply_payment(5_000), (4_000, 1_000));
+        line.accrue(CREDIT_LINE_STATEMENT_PERIOD * 3);
+        assert_eq!(line.close_statement(CREDIT_LINE_STATEMENT_PERIOD * 3), StatementOutcome::Paid);
+
+        // Leaving the bill unpaid misses the next statement until it is paid off
+        line.accrue(CREDIT_LINE_STATEMENT_PERIOD * 4);
+        assert_eq!(line.close_statement(CREDIT_LINE_STATEMENT_PERIOD * 4), StatementOutcome::Missed);
+        assert!(line.past_due);
+        assert_eq!(line.missed_statements, 1);
+        line.apply_payment(line.interest_due);
+        assert!(!line.past_due);
+        assert_eq!(line.apply_payment(u64::MAX), (0, 399_000));
+        assert_eq!(line.owed(), 0);
+    }
+
+    #[test]
+    fn credit_delegations_cap_draws_until_they_expire() {
+        let mut delegation = CreditDelegation {
+            lender: Pubkey::new_unique(),
+            borrower: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            allowance: 1_000,
+            interest_rate_bps: 800,
+            duration_seconds: 86_400,
+            expires_at: 10_000,
+            total_drawn: 0,
+            loans_drawn: 0,
+            granted_at: 0,
+            bump: 255,
+        };
+
+        delegation.record_draw(600, 100).unwrap();
+        assert_eq!(
+            expect_error(delegation.record_draw(500, 200)),
+            error!(ErrorCode::AllowanceExceeded)
+        );
+        assert_eq!(
+            expect_error(delegation.record_draw(0, 200)),
+            error!(ErrorCode::AllowanceExceeded)
+        );
+        delegation.record_draw(400, 200).unwrap();
+        assert_eq!((delegation.allowance, delegation.total_drawn, delegation.loans_drawn), (0, 1_000, 2));
+
+        delegation.allowance = 100;
+        assert_eq!(
+            expect_error(delegation.record_draw(50, 10_000)),
+            error!(ErrorCode::DelegationExpired)
+        );
+    }
+
+    #[test]
+    fn auto_repay_installments_spread_evenly_up_to_the_due_date() {
+        let mut auto_repay = AutoRepay {
+            loan: Pubkey::new_unique(),
+            borrower: Pubkey::new_unique(),
+            loan_id: 1,
+            mint: Pubkey::new_unique(),
+            borrower_token_account: Pubkey::new_unique(),
+            installments: 4,
+            installments_paid: 0,
+            scheduled_total: 1_000_001,
+            collected: 0,
+            keeper_tip: 1_000,
+            enabled_at: 0,
+            bump: 255,
+        };
+        let due_date = 40 * 86400;
+
+        // Each installment opens a day ahead of its quarter of the term
+        assert_eq!(auto_repay.collectible_at(1, due_date), 9 * 86400);
+        assert_eq!(auto_repay.collectible_at(4, due_date), 39 * 86400);
+        assert_eq!(auto_repay.collected_by(1), 250_000);
+        assert_eq!(auto_repay.collected_by(4), 1_000_001);
+
+        // A term shorter than the window can be collected straight away
+        auto_repay.installments = 1;
+        assert_eq!(auto_repay.collectible_at(1, 3600), 0);
+        assert_eq!(auto_repay.collected_by(1), 1_000_001);
+    }
+
+    #[test]
+    fn escrow_rent_goes_back_to_whoever_paid_it() {
+        let funder = Pubkey::new_unique();
+        let mut loan = LoanAccount::from(legacy_loan(Some(funder)));
+        assert_eq!(loan.escrow_rent_payer(), Some(funder));
+
+        // A refinance replaces the lender but not the wallet that paid the escrow's rent
+        loan.escrow_payer = funder;
+        loan.lender = Some(Pubkey::new_unique());
+        assert_eq!(loan.escrow_rent_payer(), Some(funder));
+    }
+
+    #[test]
+    fn loans_without_a_position_pay_the_recorded_lender() {
+        let lender = Pubkey::new_unique();
+        let loan = LoanAccount::from(legacy_loan(Some(lender)));
+        assert_eq!(loan.position_holder(None).unwrap(), lender);
+
+        // Once a position exists, its token account must be supplied
+        let mut loan = loan;
+        loan.position_mint = Pubkey::new_unique();
+        assert_eq!(
+            loan.position_holder(None).unwrap_err(),
+            error!(ErrorCode::PositionMismatch)
+        );
+    }
+
+    #[test]
+    fn current_layout_sizes_are_stable() {
+        // Fields are carved out of `reserved`, so v1 accounts never need a realloc
+        assert_eq!(ProtocolConfig::INIT_SPACE, 386);
+        assert_eq!(ReputationAccount::INIT_SPACE, 222);
+        assert_eq!(LoanAccount::INIT_SPACE, 274);
+    }
+
+    #[test]
+    fn legacy_layout_sizes_match_deployed_accounts() {
+        assert_eq!(ProtocolConfigV0::INIT_SPACE, 91);
+        assert_eq!(ReputationAccountV0::INIT_SPACE, 93);
+        assert_eq!(LoanAccountV0::INIT_SPACE, 145);
+    }
+
+    #[test]
+    fn config_v0_to_v1() {
+        let legacy = legacy_config(true);
+        let authority = legacy.authority;
+        let data = legacy_account(ProtocolConfig::DISCRIMINATOR, &legacy, ProtocolConfigV0::INIT_SPACE);
+
+        let config = upgrade_config(&data).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.authority, authority);
+        assert_eq!(config.protocol_fee_bps, 150);
+        assert_eq!(config.total_loans_issued, 7);
+        assert_eq!(config.total_volume, 70_000_000_000);
+        assert_eq!(config.total_defaults, 2);
+        assert_eq!(config.pause_flags, PAUSE_NEW_REQUESTS | PAUSE_FUNDING);
+        for role in [Role::Pauser, Role::FeeManager, Role::RiskManager, Role::ReputationSteward] {
+            assert!(config.has_role(role, &authority));
+        }
+        assert_eq!(config.tier_max_borrow[0], TIER_A_MAX_BORROW);
+        assert_eq!(config.default_penalty, DEFAULT_PENALTY);
+
+        let unpaused = legacy_account(
+            ProtocolConfig::DISCRIMINATOR,
+            &legacy_config(false),
+            ProtocolConfigV0::INIT_SPACE,
+        );
+        assert_eq!(upgrade_config(&unpaused).unwrap().pause_flags, 0);
+    }
+
+    #[test]
+    fn withdrawal_window_defaults_when_unset() {
+        let mut config = ProtocolConfig::from(legacy_config(false));
+        assert_eq!(config.withdrawal_window(), DEFAULT_WITHDRAWAL_WINDOW);
+
+        // v1 configs created before the field was carved out of `reserved` read back zero
+        config.withdrawal_window_seconds = 0;
+        assert_eq!(config.withdrawal_window(), DEFAULT_WITHDRAWAL_WINDOW);
+
+        config.withdrawal_window_seconds = 3600;
+        assert_eq!(config.withdrawal_window(), 3600);
+    }
+
+    #[test]
+    fn exposure_limits_cap_loan_count_and_outstanding_principal() {
+        let mut config = ProtocolConfig::from(legacy_config(false));
+        let mut reputation = ReputationAccount::from(legacy_reputation()); // Tier B, one active loan
+        reputation.outstanding_principal = 140_000_000_000;
+        check_exposure(&config, &reputation, 10_000_000_000, 0).unwrap();
+        assert_eq!(
+            expect_error(check_exposure(&config, &reputation, 10_000_000_001, 0)),
+            error!(ErrorCode::ExceedsMaxExposure)
+        );
+        // A guarantee's backing raises the limit for its loan
+        check_exposure(&config, &reputation, 25_000_000_000, 15_000_000_000).unwrap();
+
+        // v1 configs created before the fields were carved out of `reserved` read back zero
+        config.tier_max_exposure = [0; 4];
+        config.max_active_loans = 0;
+        assert_eq!(config.max_exposure(CREDIT_TIER_B), TIER_B_MAX_EXPOSURE);
+        assert_eq!(config.active_loan_limit(), DEFAULT_MAX_ACTIVE_LOANS);
+
+        config.max_active_loans = 1;
+        assert_eq!(
+            expect_error(check_exposure(&config, &reputation, 1, 0)),
+            error!(ErrorCode::TooManyActiveLoans)
+        );
+    }
+
+    #[test]
+    fn legacy_loans_fall_back_to_config_windows() {
+        let config = ProtocolConfig::from(legacy_config(false));
+        let loan = LoanAccount::from(legacy_loan(None));
+        assert_eq!(loan.request_expiry(&config), loan.created_at + DEFAULT_REQUEST_TTL);
+        assert_eq!(loan.withdrawal_deadline(&config), loan.funded_at + DEFAULT_WITHDRAWAL_WINDOW);
+    }
+
+    #[test]
+    fn reputation_v0_to_v1() {
+        let legacy = legacy_reputation();
+        let owner = legacy.owner;
+        let data = legacy_account(
+            ReputationAccount::DISCRIMINATOR,
+            &legacy,
+            ReputationAccountV0::INIT_SPACE,
+        );
+
+        let reputation = upgrade_reputation(&data).unwrap();
+        assert_eq!(reputation.version, REPUTATION_VERSION);
+        assert_eq!(reputation.owner, owner);
+        assert_eq!(reputation.credit_score, 650);
+        assert_eq!(reputation.credit_tier, CREDIT_TIER_B);
+        assert_eq!(reputation.completed_loans, 3);
+        assert_eq!(reputation.total_repaid, 33_000_000_000);
+        assert_eq!(reputation.late_payments, 1);
+        assert_eq!(reputation.last_updated, 1_700_500_000);
+        assert_eq!(reputation.bump, 254);
+    }
+
+    #[test]
+    fn loan_v0_to_v1() {
+        // `lender` is an Option, so both encodings shift every later field
+        for lender in [None, Some(Pubkey::new_unique())] {
+            let legacy = legacy_loan(lender);
+            let borrower = legacy.borrower;
+            let data = legacy_account(LoanAccount::DISCRIMINATOR, &legacy, LoanAccountV0::INIT_SPACE);
+
+            let loan = upgrade_loan(&data).unwrap();
+            assert_eq!(loan.version, LOAN_VERSION);
+            assert_eq!(loan.borrower, borrower);
+            assert_eq!(loan.loan_id, 42);
+            assert_eq!(loan.actual_interest_rate_bps, 1000);
+            assert!(loan.state == LoanState::Active);
+            assert_eq!(loan.due_date, 1_702_592_100);
+            assert_eq!(loan.lender, lender);
+            assert_eq!(loan.bump, 253);
+        }
+    }
+
+    #[test]
+    fn upgraded_accounts_fit_current_space() {
+        let config: ProtocolConfig = legacy_config(false).into();
+        let reputation: ReputationAccount = legacy_reputation().into();
+        let loan: LoanAccount = legacy_loan(Some(Pubkey::new_unique())).into();
+
+        current_account(&config, ProtocolConfig::INIT_SPACE);
+        current_account(&reputation, ReputationAccount::INIT_SPACE);
+        current_account(&loan, LoanAccount::INIT_SPACE);
+    }
+
+    #[test]
+    fn current_version_is_already_migrated() {
+        let config: ProtocolConfig = legacy_config(false).into();
+        let reputation: ReputationAccount = legacy_reputation().into();
+        let loan: LoanAccount = legacy_loan(None).into();
+
+        let config = current_account(&config, ProtocolConfig::INIT_SPACE);
+        let reputation = current_account(&reputation, ReputationAccount::INIT_SPACE);
+        let loan = current_account(&loan, LoanAccount::INIT_SPACE);
+
+        assert_eq!(
+            expect_error(upgrade_config(&config)),
+            ErrorCode::AlreadyMigrated.into()
+        );
+        assert_eq!(
+            expect_error(upgrade_reputation(&reputation)),
+            ErrorCode::AlreadyMigrated.into()
+        );
+        assert_eq!(expect_error(upgrade_loan(&loan)), ErrorCode::AlreadyMigrated.into());
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_foreign_accounts() {
+        let reputation: ReputationAccount = legacy_reputation().into();
+        let mut data = current_account(&reputation, ReputationAccount::INIT_SPACE);
+        data[8] = REPUTATION_VERSION + 1;
+        assert_eq!(
+            expect_error(upgrade_reputation(&data)),
+            ErrorCode::UnsupportedAccountVersion.into()
+        );
+
+        // A legacy loan is not a reputation account, whatever its size
+        let loan = legacy_account(
+            LoanAccount::DISCRIMINATOR,
+            &legacy_loan(None),
+            LoanAccountV0::INIT_SPACE,
+        );
+        assert_eq!(
+            expect_error(upgrade_reputation(&loan)),
+            ErrorCode::UnsupportedAccountVersion.into()
+        );
+    }
 }
@@ -107,7 +107,7 @@ async function initialize() {
     console.log("Total Loans Issued:", config.totalLoansIssued.toString());
     console.log("Total Volume:", config.totalVolume.toString());
     console.log("Total Defaults:", config.totalDefaults.toString());
-    console.log("Status:", config.isPaused ? "PAUSED ⏸️" : "ACTIVE ✅");
+    console.log("Status:", config.pauseFlags !== 0 ? `PAUSED ⏸️ (flags: 0b${config.pauseFlags.toString(2)})` : "ACTIVE ✅");
     console.log("═══════════════════════════════════════\n");
     
     console.log("🎉 Protocol is ready to use!");
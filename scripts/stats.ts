@@ -51,7 +51,7 @@ async function getStats() {
     
     console.log("📊 Protocol Overview");
     console.log("───────────────────────────────────────");
-    console.log("Status:", config.isPaused ? "⏸️  PAUSED" : "✅ ACTIVE");
+    console.log("Status:", config.pauseFlags !== 0 ? `⏸️  PAUSED (flags: 0b${config.pauseFlags.toString(2)})` : "✅ ACTIVE");
     console.log("Authority:", config.authority.toString());
     console.log("Oracle:", config.oracleAuthority.toString());
     console.log("Protocol Fee:", config.protocolFeeBps, "bps (", config.protocolFeeBps / 100, "%)");
@@ -115,6 +115,7 @@ async function getStats() {
       repaid: 0,
       defaulted: 0,
       cancelled: 0,
+      expired: 0,
     };
     
     for (const loan of loanAccounts) {
@@ -124,6 +125,7 @@ async function getStats() {
       else if (loan.account.state.repaid !== undefined) stateCounts.repaid++;
       else if (loan.account.state.defaulted !== undefined) stateCounts.defaulted++;
       else if (loan.account.state.cancelled !== undefined) stateCounts.cancelled++;
+      else if (loan.account.state.expired !== undefined) stateCounts.expired++;
     }
     
     console.log("Requested:", stateCounts.requested);
@@ -132,6 +134,7 @@ async function getStats() {
     console.log("Repaid:", stateCounts.repaid);
     console.log("Defaulted:", stateCounts.defaulted);
     console.log("Cancelled:", stateCounts.cancelled);
+    console.log("Expired (funding reclaimed):", stateCounts.expired);
     console.log("\n═══════════════════════════════════════\n");
     
   } catch (error) {
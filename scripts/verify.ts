@@ -80,7 +80,7 @@ async function verify() {
       console.log("   Authority:", config.authority.toString());
       console.log("   Oracle:", config.oracleAuthority.toString());
       console.log("   Fee:", config.protocolFeeBps, "bps");
-      console.log("   Status:", config.isPaused ? "PAUSED ⏸️" : "ACTIVE ✅");
+      console.log("   Status:", config.pauseFlags !== 0 ? `PAUSED ⏸️ (flags: 0b${config.pauseFlags.toString(2)})` : "ACTIVE ✅");
     } catch (error) {
       console.log("   ❌ Protocol not initialized");
       console.log("   Run: ts-node scripts/initialize.ts");
@@ -3,7 +3,10 @@ import { Program, BN } from "@coral-xyz/anchor";
 import { SollendMicroProtocol } from "../target/types/sollend_micro_protocol";
 import {
   TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
   createMint,
+  createAssociatedTokenAccount,
+  transfer,
   createAccount,
   mintTo,
   getAccount,
@@ -38,6 +41,7 @@ describe("Sollend Micro-Lending Protocol", () => {
   // PDAs
   let configPda: PublicKey;
   let reputationPda: PublicKey;
+  let loanIndexPda: PublicKey;
   let loanPda: PublicKey;
   let escrowPda: PublicKey;
   
@@ -46,6 +50,75 @@ describe("Sollend Micro-Lending Protocol", () => {
   const durationSeconds = new BN(86400 * 30); // 30 days
   const maxInterestRate = 1500; // 15%
   
+  // Decode the events a transaction emitted through `emit_cpi!`
+  const fetchCpiEvents = async (signature: string) => {
+    await provider.connection.confirmTransaction(signature, "confirmed");
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const events = [];
+    for (const inner of tx.meta.innerInstructions ?? []) {
+      for (const ix of inner.instructions) {
+        const data = anchor.utils.bytes.bs58.decode(ix.data);
+        // Skip the 8-byte event CPI instruction tag
+        const event = program.coder.events.decode(
+          anchor.utils.bytes.base64.encode(Buffer.from(data.subarray(8)))
+        );
+        if (event) events.push(event);
+      }
+    }
+    return events;
+  };
+  
+  // Lender position mint of a loan; every refinance mints the next index
+  const positionMintFor = (loan: PublicKey, index = 0) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), loan.toBuffer(), Buffer.from([index])],
+      program.programId
+    )[0];
+  const positionAccountFor = (loan: PublicKey, holder: PublicKey, index = 0) =>
+    getAssociatedTokenAddressSync(positionMintFor(loan, index), holder);
+  const lenderProfileFor = (wallet: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lender_profile"), wallet.toBuffer()],
+      program.programId
+    )[0];
+  const [exposureLedgerPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("exposure_ledger")],
+    program.programId
+  );
+  const pairExposureFor = (lender: PublicKey, borrower: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("pair_exposure"), lender.toBuffer(), borrower.toBuffer()],
+      program.programId
+    )[0];
+  const acceptedBondMintFor = (bondMint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("bond_mint"), bondMint.toBuffer()],
+      program.programId
+    )[0];
+  const setMaxPairExposure = (maxPairExposure: BN) =>
+    program.methods
+      .updateConcentrationLimits(null, null, maxPairExposure)
+      .accounts({
+        exposureLedger: exposureLedgerPda,
+        config: configPda,
+        riskManager: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+  const createLenderProfile = (wallet: Keypair) =>
+    program.methods
+      .createLenderProfile()
+      .accounts({
+        lenderProfile: lenderProfileFor(wallet.publicKey),
+        lender: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([wallet])
+      .rpc();
+  
   before(async () => {
     // Generate keypairs
     authority = Keypair.generate();
@@ -118,6 +191,11 @@ describe("Sollend Micro-Lending Protocol", () => {
       program.programId
     );
     
+    [loanIndexPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_index"), borrower.publicKey.toBuffer()],
+      program.programId
+    );
+    
     [loanPda] = PublicKey.findProgramAddressSync(
       [
         Buffer.from("loan"),
@@ -164,15 +242,15 @@ describe("Sollend Micro-Lending Protocol", () => {
       assert.ok(config.oracleAuthority.equals(oracle.publicKey));
       assert.equal(config.protocolFeeBps, 100);
       assert.equal(config.totalLoansIssued.toNumber(), 0);
-      assert.equal(config.isPaused, false);
+      assert.equal(config.pauseFlags, 0);
     });
 
     it("Updates protocol config", async () => {
       const tx = await program.methods
-        .updateConfig(null, 150, null) // Update fee to 1.5%
+        .updateConfig(150, null) // Update fee to 1.5%
         .accounts({
           config: configPda,
-          authority: authority.publicKey,
+          signer: authority.publicKey,
         })
         .signers([authority])
         .rpc();
@@ -182,6 +260,180 @@ describe("Sollend Micro-Lending Protocol", () => {
       const config = await program.account.protocolConfig.fetch(configPda);
       assert.equal(config.protocolFeeBps, 150);
     });
+
+    it("Initializes the exposure ledger", async () => {
+      await program.methods
+        .initializeExposureLedger()
+        .accounts({
+          exposureLedger: exposureLedgerPda,
+          config: configPda,
+          riskManager: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+      const ledger = await program.account.exposureLedger.fetch(exposureLedgerPda);
+      assert.equal(ledger.maxPairExposure.toString(), "100000000000");
+      assert.deepEqual(ledger.tierOutstanding.map(amount => amount.toNumber()), [0, 0, 0, 0]);
+    });
+
+    it("Accepts the loan mint for guarantee bonds", async () => {
+      await program.methods
+        .acceptBondMint()
+        .accounts({
+          acceptedBondMint: acceptedBondMintFor(mint),
+          mint,
+          config: configPda,
+          riskManager: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+      const accepted = await program.account.acceptedBondMint.fetch(acceptedBondMintFor(mint));
+      assert.ok(accepted.mint.equals(mint));
+    });
+  });
+
+  describe("Access Control", () => {
+    let feeManager: Keypair;
+
+    before(async () => {
+      feeManager = Keypair.generate();
+      await provider.connection.requestAirdrop(feeManager.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+    });
+
+    it("Starts with the owner holding every role", async () => {
+      const config = await program.account.protocolConfig.fetch(configPda);
+      assert.ok(config.pauser.equals(authority.publicKey));
+      assert.ok(config.feeManager.equals(authority.publicKey));
+      assert.ok(config.riskManager.equals(authority.publicKey));
+      assert.ok(config.reputationSteward.equals(authority.publicKey));
+    });
+
+    it("Owner assigns the fee manager role", async () => {
+      await program.methods
+        .setRole({ feeManager: {} }, feeManager.publicKey)
+        .accounts({
+          config: configPda,
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
+      const config = await program.account.protocolConfig.fetch(configPda);
+      assert.ok(config.feeManager.equals(feeManager.publicKey));
+    });
+
+    it("Fee manager updates the fee but cannot pause", async () => {
+      await program.methods
+        .updateConfig(120, null)
+        .accounts({
+          config: configPda,
+          signer: feeManager.publicKey,
+        })
+        .signers([feeManager])
+        .rpc();
+
+      try {
+        await program.methods
+          .updateConfig(null, 1)
+          .accounts({
+            config: configPda,
+            signer: feeManager.publicKey,
+          })
+          .signers([feeManager])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "MissingRole");
+      }
+
+      // An update that sets nothing is rejected whoever signs it
+      try {
+        await program.methods
+          .updateConfig(null, null)
+          .accounts({
+            config: configPda,
+            signer: borrower.publicKey,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "EmptyConfigUpdate");
+      }
+
+      const config = await program.account.protocolConfig.fetch(configPda);
+      assert.equal(config.protocolFeeBps, 120);
+      assert.equal(config.pauseFlags, 0);
+    });
+
+    it("Owner revokes the fee manager role", async () => {
+      await program.methods
+        .revokeRole({ feeManager: {} })
+        .accounts({
+          config: configPda,
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
+      try {
+        await program.methods
+          .updateConfig(200, null)
+          .accounts({
+            config: configPda,
+            signer: feeManager.publicKey,
+          })
+          .signers([feeManager])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "MissingRole");
+      }
+
+      // Hand the role back to the owner for the remaining tests
+      await program.methods
+        .setRole({ feeManager: {} }, authority.publicKey)
+        .accounts({
+          config: configPda,
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
+      await program.methods
+        .updateConfig(150, null)
+        .accounts({
+          config: configPda,
+          signer: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+    });
+
+    it("Rejects role changes from non-owners", async () => {
+      try {
+        await program.methods
+          .setRole({ pauser: {} }, feeManager.publicKey)
+          .accounts({
+            config: configPda,
+            authority: feeManager.publicKey,
+          })
+          .signers([feeManager])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "ConstraintHasOne");
+      }
+    });
   });
 
   describe("Reputation System", () => {
@@ -190,6 +442,7 @@ describe("Sollend Micro-Lending Protocol", () => {
         .createReputation()
         .accounts({
           reputation: reputationPda,
+          config: configPda,
           owner: borrower.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -207,12 +460,40 @@ describe("Sollend Micro-Lending Protocol", () => {
       assert.equal(reputation.isFrozen, false);
     });
 
+    it("Creates the borrower's loan index", async () => {
+      await program.methods
+        .createLoanIndex(loanId)
+        .accounts({
+          loanIndex: loanIndexPda,
+          nextLoan: loanPda,
+          borrower: borrower.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([borrower])
+        .rpc();
+
+      const loanIndex = await program.account.borrowerLoanIndex.fetch(loanIndexPda);
+      assert.ok(loanIndex.borrower.equals(borrower.publicKey));
+      assert.equal(loanIndex.nextLoanId.toNumber(), loanId.toNumber());
+      assert.deepEqual(loanIndex.activeLoanIds, []);
+    });
+
+    it("Creates the lender's profile", async () => {
+      await createLenderProfile(lender);
+
+      const profile = await program.account.lenderProfile.fetch(lenderProfileFor(lender.publicKey));
+      assert.ok(profile.lender.equals(lender.publicKey));
+      assert.equal(profile.totalDeployed.toNumber(), 0);
+      assert.deepEqual(profile.loansByTier, [0, 0, 0, 0]);
+    });
+
     it("Prevents duplicate reputation creation", async () => {
       try {
         await program.methods
           .createReputation()
           .accounts({
             reputation: reputationPda,
+            config: configPda,
             owner: borrower.publicKey,
             systemProgram: SystemProgram.programId,
           })
@@ -224,13 +505,180 @@ describe("Sollend Micro-Lending Protocol", () => {
         assert.include(error.message, "already in use");
       }
     });
+
+    it("Requires an identity attestation while an attester is assigned", async () => {
+      const newcomer = Keypair.generate();
+      await provider.connection.requestAirdrop(newcomer.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+      const [newcomerReputationPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reputation"), newcomer.publicKey.toBuffer()],
+        program.programId
+      );
+      const [identityPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("identity"), newcomer.publicKey.toBuffer()],
+        program.programId
+      );
+      const createNewcomerReputation = (identityAttestation: PublicKey | null) =>
+        program.methods
+          .createReputation()
+          .accounts({
+            reputation: newcomerReputationPda,
+            identityAttestation,
+            config: configPda,
+            owner: newcomer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([newcomer])
+          .rpc();
+      
+      await program.methods
+        .setRole({ identityAttester: {} }, authority.publicKey)
+        .accounts({
+          config: configPda,
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+      try {
+        try {
+          await createNewcomerReputation(null);
+          assert.fail("Should have thrown error");
+        } catch (error) {
+          assert.include(error.toString(), "IdentityNotAttested");
+        }
+        
+        await program.methods
+          .attestIdentity(new BN(0))
+          .accounts({
+            identityAttestation: identityPda,
+            config: configPda,
+            attester: authority.publicKey,
+            owner: newcomer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([authority])
+          .rpc();
+        await createNewcomerReputation(identityPda);
+        
+        const reputation = await program.account.reputationAccount.fetch(newcomerReputationPda);
+        assert.ok(reputation.owner.equals(newcomer.publicKey));
+      } finally {
+        // The rest of the suite runs without the gate
+        await program.methods
+          .revokeRole({ identityAttester: {} })
+          .accounts({
+            config: configPda,
+            authority: authority.publicKey,
+          })
+          .signers([authority])
+          .rpc();
+      }
+    });
+
+    it("Attests the borrower's reputation for other programs", async () => {
+      const [attestationPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reputation_attestation"), borrower.publicKey.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .attestReputation()
+        .accounts({
+          attestation: attestationPda,
+          reputation: reputationPda,
+          payer: borrower.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([borrower])
+        .rpc();
+
+      const attestation = await program.account.reputationAttestation.fetch(attestationPda);
+      assert.equal(attestation.creditScore, 500);
+      assert.equal(attestation.creditTier, 2);
+      assert.equal(attestation.validUntil.sub(attestation.validFrom).toNumber(), 86400);
+      
+      // A tier C borrower passes a tier C check and fails a tier B one
+      const tier = await program.methods
+        .checkCreditTier(2)
+        .accounts({ reputation: reputationPda, borrower: borrower.publicKey })
+        .view();
+      assert.equal(tier, 2);
+      try {
+        await program.methods
+          .checkCreditTier(1)
+          .accounts({ reputation: reputationPda, borrower: borrower.publicKey })
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "CreditTierTooLow");
+      }
+    });
   });
 
   describe("Loan Lifecycle", () => {
+    let positionBuyer: Keypair;
+    let buyerTokenAccount: PublicKey;
+    
+    it("Rejects loan requests while new requests are paused", async () => {
+      await program.methods
+        .updateConfig(null, 1) // PAUSE_NEW_REQUESTS
+        .accounts({
+          config: configPda,
+          signer: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
+      try {
+        await program.methods
+          .createLoanRequest(loanAmount, durationSeconds, maxInterestRate)
+          .accounts({
+            loanIndex: loanIndexPda,
+            loan: loanPda,
+            borrowerReputation: reputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "ProtocolPaused");
+      }
+
+      await program.methods
+        .updateConfig(null, 0)
+        .accounts({
+          config: configPda,
+          signer: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+    });
+
+    it("Rejects unknown pause flag bits", async () => {
+      try {
+        await program.methods
+          .updateConfig(null, 0x80)
+          .accounts({
+            config: configPda,
+            signer: authority.publicKey,
+          })
+          .signers([authority])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidPauseFlags");
+      }
+    });
+
     it("Creates a loan request", async () => {
       const tx = await program.methods
-        .createLoanRequest(loanId, loanAmount, durationSeconds, maxInterestRate)
+        .createLoanRequest(loanAmount, durationSeconds, maxInterestRate)
         .accounts({
+          loanIndex: loanIndexPda,
           loan: loanPda,
           borrowerReputation: reputationPda,
           config: configPda,
@@ -248,6 +696,11 @@ describe("Sollend Micro-Lending Protocol", () => {
       assert.equal(loan.loanId.toNumber(), loanId.toNumber());
       assert.equal(loan.amount.toNumber(), loanAmount.toNumber());
       assert.ok(loan.state.requested !== undefined);
+      assert.ok(loan.indexed);
+
+      const loanIndex = await program.account.borrowerLoanIndex.fetch(loanIndexPda);
+      assert.equal(loanIndex.nextLoanId.toNumber(), loanId.toNumber() + 1);
+      assert.deepEqual(loanIndex.activeLoanIds.map(id => id.toNumber()), [loanId.toNumber()]);
     });
 
     it("Funds the loan", async () => {
@@ -281,24 +734,41 @@ describe("Sollend Micro-Lending Protocol", () => {
       
       escrowTokenAccount = escrowAta;
       
-      const tx = await program.methods
-        .fundLoan(interestRate)
+      const fund = () => program.methods
+        .fundLoan(interestRate, { enabled: false, minInterestBps: 0, prepaymentFeeBps: 0 })
         .accounts({
           loan: loanPda,
           escrow: escrowPda,
           borrowerReputation: reputationPda,
           config: configPda,
-          configAccount: configPda,
           borrower: borrower.publicKey,
           lender: lender.publicKey,
+          lenderProfile: lenderProfileFor(lender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
           lenderTokenAccount,
           escrowTokenAccount,
+          positionMint: positionMintFor(loanPda),
+          lenderPositionAccount: positionAccountFor(loanPda, lender.publicKey),
           tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([lender])
         .rpc();
 
+      // A pair limit below the loan keeps this lender from funding it
+      await setMaxPairExposure(loanAmount.subn(1));
+      try {
+        await fund();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "PairExposureExceeded");
+      } finally {
+        await setMaxPairExposure(new BN(100_000_000_000));
+      }
+
+      const tx = await fund();
       console.log("Loan funded:", tx);
 
       // Verify loan state
@@ -307,10 +777,293 @@ describe("Sollend Micro-Lending Protocol", () => {
       assert.ok(loan.lender.equals(lender.publicKey));
       assert.equal(loan.actualInterestRateBps, interestRate);
 
+      // The funding lands in the lender's portfolio under the borrower's tier (C)
+      const profile = await program.account.lenderProfile.fetch(lenderProfileFor(lender.publicKey));
+      assert.equal(profile.totalDeployed.toString(), loanAmount.toString());
+      assert.equal(profile.outstanding.toString(), loanAmount.toString());
+      assert.equal(profile.loansFunded, 1);
+      assert.deepEqual(profile.loansByTier, [0, 0, 1, 0]);
+
+      // The lender holds the loan's single position token
+      assert.ok(loan.positionMint.equals(positionMintFor(loanPda)));
+      const position = await getAccount(
+        provider.connection,
+        positionAccountFor(loanPda, lender.publicKey)
+      );
+      assert.equal(position.amount.toString(), "1");
+
       // Verify reputation updated
       const reputation = await program.account.reputationAccount.fetch(reputationPda);
       assert.equal(reputation.activeLoans, 1);
       assert.equal(reputation.totalLoans, 1);
+      assert.equal(reputation.outstandingPrincipal.toString(), loanAmount.toString());
+
+      // The principal counts against the lender's pair limit and the borrower's tier (C) ceiling
+      const pair = await program.account.pairExposure.fetch(
+        pairExposureFor(lender.publicKey, borrower.publicKey)
+      );
+      assert.equal(pair.outstanding.toString(), loanAmount.toString());
+      const ledger = await program.account.exposureLedger.fetch(exposureLedgerPda);
+      assert.equal(ledger.tierOutstanding[2].toString(), loanAmount.toString());
+      assert.ok(loan.concentrationTracked);
+    });
+
+    it("Rejects requests past the borrower's active loan limit", async () => {
+      const setMaxActiveLoans = (maxActiveLoans: number) =>
+        program.methods
+          .updateExposureLimits(null, maxActiveLoans)
+          .accounts({
+            config: configPda,
+            riskManager: authority.publicKey,
+          })
+          .signers([authority])
+          .rpc();
+      
+      const { nextLoanId } = await program.account.borrowerLoanIndex.fetch(loanIndexPda);
+      const [nextLoanPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("loan"),
+          borrower.publicKey.toBuffer(),
+          nextLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      
+      // The funded loan already uses the borrower's only slot
+      await setMaxActiveLoans(1);
+      try {
+        await program.methods
+          .createLoanRequest(new BN(1_000_000_000), durationSeconds, maxInterestRate)
+          .accounts({
+            loanIndex: loanIndexPda,
+            loan: nextLoanPda,
+            borrowerReputation: reputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "TooManyActiveLoans");
+      } finally {
+        await setMaxActiveLoans(5);
+      }
+    });
+
+    it("Rejects a guarantor below the minimum guarantor tier", async () => {
+      // Fresh reputations start at tier C; guarantors need tier B or better
+      const guarantor = Keypair.generate();
+      await provider.connection.requestAirdrop(guarantor.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+      
+      const [guarantorReputationPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reputation"), guarantor.publicKey.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .createReputation()
+        .accounts({
+          reputation: guarantorReputationPda,
+          config: configPda,
+          owner: guarantor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([guarantor])
+        .rpc();
+      const guarantorTokenAccount = await createAccount(
+        provider.connection,
+        guarantor,
+        mint,
+        guarantor.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        authority,
+        mint,
+        guarantorTokenAccount,
+        authority,
+        20_000_000_000 // 20 tokens
+      );
+      
+      const { nextLoanId } = await program.account.borrowerLoanIndex.fetch(loanIndexPda);
+      const [guaranteePda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("guarantee"),
+          borrower.publicKey.toBuffer(),
+          nextLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      // Bonds in a mint the risk manager has not accepted never count toward limits
+      const junkMint = await createMint(provider.connection, guarantor, guarantor.publicKey, null, 9);
+      try {
+        await program.methods
+          .guaranteeLoan(new BN(20_000_000_000))
+          .accounts({
+            guarantee: guaranteePda,
+            loanIndex: loanIndexPda,
+            guarantorReputation: guarantorReputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            guarantor: guarantor.publicKey,
+            guarantorTokenAccount: await createAccount(
+              provider.connection,
+              guarantor,
+              junkMint,
+              guarantor.publicKey
+            ),
+            bondMint: junkMint,
+            acceptedBondMint: acceptedBondMintFor(junkMint),
+            bondVault: getAssociatedTokenAddressSync(junkMint, guaranteePda, true),
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower, guarantor])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "AccountNotInitialized");
+      }
+      
+      try {
+        await program.methods
+          .guaranteeLoan(new BN(20_000_000_000))
+          .accounts({
+            guarantee: guaranteePda,
+            loanIndex: loanIndexPda,
+            guarantorReputation: guarantorReputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            guarantor: guarantor.publicKey,
+            guarantorTokenAccount,
+            bondMint: mint,
+            acceptedBondMint: acceptedBondMintFor(mint),
+            bondVault: getAssociatedTokenAddressSync(mint, guaranteePda, true),
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower, guarantor])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "GuarantorIneligible");
+      }
+      assert.isNull(await provider.connection.getAccountInfo(guaranteePda));
+    });
+
+    it("Rejects a voucher below the minimum voucher score", async () => {
+      // The borrower is still tier C on their own score; vouchers need tier A
+      const vouchee = Keypair.generate();
+      await provider.connection.requestAirdrop(vouchee.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+      const [vouchPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vouch"), borrower.publicKey.toBuffer(), vouchee.publicKey.toBuffer()],
+        program.programId
+      );
+      const [voucheeReputationPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reputation"), vouchee.publicKey.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .createReputation()
+        .accounts({
+          reputation: voucheeReputationPda,
+          config: configPda,
+          owner: vouchee.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([vouchee])
+        .rpc();
+      
+      try {
+        await program.methods
+          .issueVouch(10)
+          .accounts({
+            vouch: vouchPda,
+            voucherReputation: reputationPda,
+            voucheeReputation: voucheeReputationPda,
+            config: configPda,
+            voucher: borrower.publicKey,
+            vouchee: vouchee.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "VoucherIneligible");
+      }
+      assert.isNull(await provider.connection.getAccountInfo(vouchPda));
+    });
+
+    it("Rejects a credit line for a borrower below tier B", async () => {
+      // Credit lines are only open to tier A and B; the borrower is still tier C
+      const [creditLinePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("credit_line"), lender.publicKey.toBuffer(), borrower.publicKey.toBuffer()],
+        program.programId
+      );
+      const lineVault = getAssociatedTokenAddressSync(mint, creditLinePda, true);
+      
+      try {
+        await program.methods
+          .openCreditLine(new BN(10_000_000_000), 700)
+          .accounts({
+            creditLine: creditLinePda,
+            borrowerReputation: reputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            lender: lender.publicKey,
+            lenderTokenAccount,
+            mint,
+            lineVault,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower, lender])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "CreditLineIneligible");
+      }
+      assert.isNull(await provider.connection.getAccountInfo(creditLinePda));
+    });
+
+    it("Rejects a credit delegation that has already expired", async () => {
+      const [creditDelegationPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("credit_delegation"), lender.publicKey.toBuffer(), borrower.publicKey.toBuffer()],
+        program.programId
+      );
+      const expiresAt = new BN(Math.floor(Date.now() / 1000) - 60);
+      
+      try {
+        await program.methods
+          .delegateCredit(new BN(10_000_000_000), 1500, new BN(86400), expiresAt)
+          .accounts({
+            creditDelegation: creditDelegationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            lender: lender.publicKey,
+            mint,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lender])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidDelegationTerms");
+      }
+      assert.isNull(await provider.connection.getAccountInfo(creditDelegationPda));
     });
 
     it("Withdraws loan funds", async () => {
@@ -319,6 +1072,7 @@ describe("Sollend Micro-Lending Protocol", () => {
         .accounts({
           loan: loanPda,
           escrow: escrowPda,
+          config: configPda,
           borrower: borrower.publicKey,
           borrowerTokenAccount,
           escrowTokenAccount,
@@ -338,6 +1092,91 @@ describe("Sollend Micro-Lending Protocol", () => {
       assert.equal(borrowerAccount.amount.toString(), loanAmount.toString());
     });
 
+    it("Rejects an auto-repay schedule with too many installments", async () => {
+      const [autoRepayPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("auto_repay"), loanPda.toBuffer()],
+        program.programId
+      );
+      
+      try {
+        await program.methods
+          .enableAutoRepay(13, new BN(0))
+          .accounts({
+            autoRepay: autoRepayPda,
+            repayVault: getAssociatedTokenAddressSync(mint, autoRepayPda, true),
+            loan: loanPda,
+            borrower: borrower.publicKey,
+            borrowerTokenAccount,
+            mint,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidAutoRepaySchedule");
+      }
+      assert.isNull(await provider.connection.getAccountInfo(autoRepayPda));
+    });
+
+    it("Lender sells the position to a buyer", async () => {
+      positionBuyer = Keypair.generate();
+      buyerTokenAccount = await createAccount(
+        provider.connection,
+        lender,
+        mint,
+        positionBuyer.publicKey
+      );
+      const buyerPositionAccount = await createAssociatedTokenAccount(
+        provider.connection,
+        lender,
+        positionMintFor(loanPda),
+        positionBuyer.publicKey
+      );
+      
+      await transfer(
+        provider.connection,
+        lender,
+        positionAccountFor(loanPda, lender.publicKey),
+        buyerPositionAccount,
+        lender,
+        1
+      );
+      
+      const position = await getAccount(provider.connection, buyerPositionAccount);
+      assert.equal(position.amount.toString(), "1");
+    });
+
+    it("Rejects repaying a lender who sold the position", async () => {
+      try {
+        await program.methods
+          .repayLoan()
+          .accounts({
+            loan: loanPda,
+            loanIndex: loanIndexPda,
+            lenderProfile: lenderProfileFor(lender.publicKey),
+            exposureLedger: exposureLedgerPda,
+            pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
+            borrowerReputation: reputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            borrowerTokenAccount,
+            lenderTokenAccount,
+            positionTokenAccount: positionAccountFor(loanPda, lender.publicKey),
+            protocolTreasury: treasuryTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([borrower])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "PositionMismatch");
+      }
+    });
+
     it("Repays the loan on time", async () => {
       // Mint repayment tokens to borrower (principal + interest)
       const interestAmount = loanAmount.mul(new BN(1000)).div(new BN(10000)); // 10%
@@ -352,15 +1191,30 @@ describe("Sollend Micro-Lending Protocol", () => {
         totalRepayment.toNumber()
       );
 
+      // Halt new lending; repayments must still go through
+      await program.methods
+        .updateConfig(null, 0b11) // PAUSE_NEW_REQUESTS | PAUSE_FUNDING
+        .accounts({
+          config: configPda,
+          signer: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
       const tx = await program.methods
         .repayLoan()
         .accounts({
           loan: loanPda,
+          loanIndex: loanIndexPda,
+          lenderProfile: lenderProfileFor(lender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
           borrowerReputation: reputationPda,
           config: configPda,
           borrower: borrower.publicKey,
           borrowerTokenAccount,
-          lenderTokenAccount,
+          lenderTokenAccount: buyerTokenAccount,
+          positionTokenAccount: positionAccountFor(loanPda, positionBuyer.publicKey),
           protocolTreasury: treasuryTokenAccount,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
@@ -373,12 +1227,58 @@ describe("Sollend Micro-Lending Protocol", () => {
       const loan = await program.account.loanAccount.fetch(loanPda);
       assert.ok(loan.state.repaid !== undefined);
 
+      // The settled loan leaves the borrower's open set
+      const loanIndex = await program.account.borrowerLoanIndex.fetch(loanIndexPda);
+      assert.notInclude(loanIndex.activeLoanIds.map(id => id.toNumber()), loanId.toNumber());
+
       // Verify reputation improved
       const reputation = await program.account.reputationAccount.fetch(reputationPda);
       assert.equal(reputation.activeLoans, 0);
+      assert.equal(reputation.outstandingPrincipal.toNumber(), 0);
+      const pair = await program.account.pairExposure.fetch(
+        pairExposureFor(lender.publicKey, borrower.publicKey)
+      );
+      assert.equal(pair.outstanding.toNumber(), 0);
       assert.equal(reputation.completedLoans, 1);
       assert.equal(reputation.onTimePayments, 1);
       assert.ok(reputation.creditScore > 500); // Score increased
+
+      // Verify the typed repayment event carries the fee split and score change
+      const events = await fetchCpiEvents(tx);
+      const repaid = events.find(event => event.name === "loanRepaid");
+      assert.ok(repaid);
+      assert.ok(repaid.data.lender.equals(positionBuyer.publicKey));
+      assert.equal(repaid.data.principal.toString(), loanAmount.toString());
+      assert.equal(repaid.data.interest.toString(), interestAmount.toString());
+      assert.equal(
+        repaid.data.protocolFee.add(repaid.data.lenderAmount).toString(),
+        totalRepayment.toString()
+      );
+      assert.equal(repaid.data.isLate, false);
+      assert.equal(repaid.data.scoreChange.scoreDelta, 50);
+
+      // The position's current holder is paid, not the original lender
+      const buyerAccount = await getAccount(provider.connection, buyerTokenAccount);
+      assert.equal(buyerAccount.amount.toString(), repaid.data.lenderAmount.toString());
+      assert.equal(repaid.data.scoreChange.newScore, reputation.creditScore);
+
+      // The lender of record's portfolio is credited even though the position was sold
+      const profile = await program.account.lenderProfile.fetch(lenderProfileFor(lender.publicKey));
+      assert.equal(profile.outstanding.toNumber(), 0);
+      assert.equal(profile.loansRepaid, 1);
+      assert.equal(
+        profile.interestEarned.toString(),
+        repaid.data.lenderAmount.sub(repaid.data.principal).toString()
+      );
+
+      await program.methods
+        .updateConfig(null, 0)
+        .accounts({
+          config: configPda,
+          signer: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
     });
   });
 
@@ -420,12 +1320,12 @@ describe("Sollend Micro-Lending Protocol", () => {
       // Create loan request with short duration for testing (5 seconds)
       await program.methods
         .createLoanRequest(
-          defaultLoanId,
           new BN(5_000_000_000), // 5 tokens
           new BN(5), // 5 seconds duration for testing
           1000
         )
         .accounts({
+          loanIndex: loanIndexPda,
           loan: defaultLoanPda,
           borrowerReputation: reputationPda,
           config: configPda,
@@ -460,18 +1360,23 @@ describe("Sollend Micro-Lending Protocol", () => {
 
       // Fund the loan
       await program.methods
-        .fundLoan(800)
+        .fundLoan(800, { enabled: false, minInterestBps: 0, prepaymentFeeBps: 0 })
         .accounts({
           loan: defaultLoanPda,
           escrow: defaultEscrowPda,
           borrowerReputation: reputationPda,
           config: configPda,
-          configAccount: configPda,
           borrower: borrower.publicKey,
           lender: lender.publicKey,
+          lenderProfile: lenderProfileFor(lender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
           lenderTokenAccount,
           escrowTokenAccount: defaultEscrowTokenAccount,
+          positionMint: positionMintFor(defaultLoanPda),
+          lenderPositionAccount: positionAccountFor(defaultLoanPda, lender.publicKey),
           tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([lender])
@@ -483,6 +1388,7 @@ describe("Sollend Micro-Lending Protocol", () => {
         .accounts({
           loan: defaultLoanPda,
           escrow: defaultEscrowPda,
+          config: configPda,
           borrower: borrower.publicKey,
           borrowerTokenAccount,
           escrowTokenAccount: defaultEscrowTokenAccount,
@@ -502,6 +1408,10 @@ describe("Sollend Micro-Lending Protocol", () => {
         .markDefault()
         .accounts({
           loan: defaultLoanPda,
+          loanIndex: loanIndexPda,
+          lenderProfile: lenderProfileFor(lender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
           borrowerReputation: reputationPda,
           config: configPda,
           oracleAuthority: oracle.publicKey,
@@ -511,10 +1421,21 @@ describe("Sollend Micro-Lending Protocol", () => {
 
       console.log("Loan marked as default:", tx);
 
+      const events = await fetchCpiEvents(tx);
+      const defaulted = events.find(event => event.name === "loanDefaulted");
+      assert.ok(defaulted);
+      assert.equal(defaulted.data.principal.toNumber(), 5_000_000_000);
+      assert.equal(defaulted.data.scoreChange.scoreDelta, -150);
+
       // Verify loan state
       const loan = await program.account.loanAccount.fetch(defaultLoanPda);
       assert.ok(loan.state.defaulted !== undefined);
 
+      // The principal is written off in the lender's portfolio
+      const profile = await program.account.lenderProfile.fetch(lenderProfileFor(lender.publicKey));
+      assert.equal(profile.losses.toNumber(), 5_000_000_000);
+      assert.equal(profile.loansDefaulted, 1);
+
       // Verify reputation penalized
       const reputation = await program.account.reputationAccount.fetch(reputationPda);
       assert.equal(reputation.defaultedLoans, 1);
@@ -535,8 +1456,9 @@ describe("Sollend Micro-Lending Protocol", () => {
 
       try {
         await program.methods
-          .createLoanRequest(newLoanId, loanAmount, durationSeconds, maxInterestRate)
+          .createLoanRequest(loanAmount, durationSeconds, maxInterestRate)
           .accounts({
+            loanIndex: loanIndexPda,
             loan: newLoanPda,
             borrowerReputation: reputationPda,
             config: configPda,
@@ -559,7 +1481,7 @@ describe("Sollend Micro-Lending Protocol", () => {
         .accounts({
           reputation: reputationPda,
           config: configPda,
-          authority: authority.publicKey,
+          reputationSteward: authority.publicKey,
         })
         .signers([authority])
         .rpc();
@@ -571,6 +1493,54 @@ describe("Sollend Micro-Lending Protocol", () => {
     });
   });
 
+  describe("Account Migrations", () => {
+    it("Rejects migrating accounts already at the current version", async () => {
+      try {
+        await program.methods
+          .migrateReputation()
+          .accounts({
+            reputation: reputationPda,
+            owner: borrower.publicKey,
+            payer: borrower.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "AlreadyMigrated");
+      }
+
+      try {
+        await program.methods
+          .migrateLoan(loanId)
+          .accounts({
+            loan: loanPda,
+            borrower: borrower.publicKey,
+            payer: borrower.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "AlreadyMigrated");
+      }
+    });
+
+    it("Stamps new accounts with the current layout version", async () => {
+      const config = await program.account.protocolConfig.fetch(configPda);
+      const reputation = await program.account.reputationAccount.fetch(reputationPda);
+      const loan = await program.account.loanAccount.fetch(loanPda);
+
+      assert.equal(config.version, 1);
+      assert.equal(reputation.version, 1);
+      assert.equal(loan.version, 1);
+    });
+  });
+
   describe("Interest Rate Calculations", () => {
     it("Calculates correct interest rates based on credit tier", async () => {
       const reputation = await program.account.reputationAccount.fetch(reputationPda);
@@ -583,13 +1553,69 @@ describe("Sollend Micro-Lending Protocol", () => {
       // Tier C (score < 600) should have higher rates
       assert.ok(loan.suggestedInterestRateBps > 700); // Base + premium
     });
-  });
 
-  describe("Protocol Statistics", () => {
-    it("Tracks protocol-wide statistics", async () => {
-      const config = await program.account.protocolConfig.fetch(configPda);
+    it("Quotes a loan without creating it", async () => {
+      const reputation = await program.account.reputationAccount.fetch(reputationPda);
+      const quote = await program.methods
+        .quoteLoan(loanAmount, durationSeconds)
+        .accounts({
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+        })
+        .view();
       
-      console.log("Total Loans Issued:", config.totalLoansIssued.toNumber());
+      assert.equal(quote.creditScore, reputation.creditScore);
+      assert.equal(quote.creditTier, reputation.creditTier);
+      assert.equal(quote.outstandingPrincipal.toString(), reputation.outstandingPrincipal.toString());
+      assert.ok(quote.suggestedInterestRateBps > 700);
+      assert.equal(
+        quote.totalRepayment.toString(),
+        loanAmount.add(quote.interest).toString()
+      );
+    });
+
+    it("Reports eligibility errors in the quote", async () => {
+      const newcomer = Keypair.generate();
+      const quote = await program.methods
+        .quoteLoan(new BN("1000000000000000"), new BN(1))
+        .accounts({
+          borrowerReputation: null,
+          config: configPda,
+          borrower: newcomer.publicKey,
+        })
+        .view();
+      
+      assert.equal(quote.eligible, false);
+      assert.equal(quote.creditScore, 500);
+      assert.include(quote.errors, 3012); // AccountNotInitialized
+      assert.include(quote.errors, 6002); // ExceedsMaxBorrowAmount
+      assert.include(quote.errors, 6003); // InvalidDuration
+      assert.include(quote.errors, 6028); // ExceedsMaxExposure
+    });
+
+    it("Quotes negative and very long durations as invalid", async () => {
+      for (const duration of [new BN(-86400), new BN(86400).muln(365 * 1000)]) {
+        const quote = await program.methods
+          .quoteLoan(new BN(1_000_000), duration)
+          .accounts({
+            borrowerReputation: reputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+          })
+          .view();
+        
+        assert.equal(quote.eligible, false);
+        assert.include(quote.errors, 6003); // InvalidDuration
+      }
+    });
+  });
+
+  describe("Protocol Statistics", () => {
+    it("Tracks protocol-wide statistics", async () => {
+      const config = await program.account.protocolConfig.fetch(configPda);
+      
+      console.log("Total Loans Issued:", config.totalLoansIssued.toNumber());
       console.log("Total Volume:", config.totalVolume.toNumber());
       console.log("Total Defaults:", config.totalDefaults.toNumber());
       
@@ -598,5 +1624,779 @@ describe("Sollend Micro-Lending Protocol", () => {
       // Should have 1 default from the default handling test
       assert.equal(config.totalDefaults.toNumber(), 1);
     });
+
+    it("Audits config stats against the loan set", async () => {
+      const [auditPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("audit"), authority.publicKey.toBuffer()],
+        program.programId
+      );
+      const [defaultLoanPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("loan"),
+          borrower.publicKey.toBuffer(),
+          new BN(2).toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      // Only the owner or the risk manager may audit
+      const [outsiderAuditPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("audit"), borrower.publicKey.toBuffer()],
+        program.programId
+      );
+      try {
+        await program.methods
+          .startAudit()
+          .accounts({
+            audit: outsiderAuditPda,
+            config: configPda,
+            auditor: borrower.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "MissingRole");
+      }
+
+      await program.methods
+        .startAudit()
+        .accounts({
+          audit: auditPda,
+          config: configPda,
+          auditor: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+      // Loans must be tallied in ascending key order
+      const loans = [loanPda, defaultLoanPda].sort((a, b) => a.toBuffer().compare(b.toBuffer()));
+      await program.methods
+        .auditLoans()
+        .accounts({
+          audit: auditPda,
+          auditor: authority.publicKey,
+        })
+        .remainingAccounts(loans.map(pubkey => ({ pubkey, isWritable: false, isSigner: false })))
+        .signers([authority])
+        .rpc();
+
+      try {
+        await program.methods
+          .auditLoans()
+          .accounts({
+            audit: auditPda,
+            auditor: authority.publicKey,
+          })
+          .remainingAccounts([{ pubkey: loans[0], isWritable: false, isSigner: false }])
+          .signers([authority])
+          .rpc();
+
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "AuditOrderViolation");
+      }
+
+      const finish = program.methods
+        .finishAudit()
+        .accounts({
+          audit: auditPda,
+          config: configPda,
+          auditor: authority.publicKey,
+        })
+        .signers([authority]);
+
+      const simulation = await finish.simulate();
+      assert.ok(simulation.raw.some(log => log.includes("Audit passed over 2 loans")));
+
+      await finish.rpc();
+      assert.isNull(await provider.connection.getAccountInfo(auditPda));
+    });
+  });
+
+  describe("Request Expiry", () => {
+    const expiringLoanId = new BN(3);
+    let expiringLoanPda: PublicKey;
+    
+    const setRequestTtl = (seconds: number) =>
+      program.methods
+        .updateLoanWindows(null, new BN(seconds))
+        .accounts({
+          config: configPda,
+          riskManager: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+    
+    before(async () => {
+      [expiringLoanPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("loan"),
+          borrower.publicKey.toBuffer(),
+          expiringLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      
+      // Short TTL so the request goes stale within the test
+      await setRequestTtl(2);
+      await program.methods
+        .createLoanRequest(new BN(1_000_000_000), durationSeconds, maxInterestRate)
+        .accounts({
+          loanIndex: loanIndexPda,
+          loan: expiringLoanPda,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([borrower])
+        .rpc();
+      await setRequestTtl(14 * 86400);
+    });
+    
+    const expire = () =>
+      program.methods
+        .expireRequest()
+        .accounts({
+          loan: expiringLoanPda,
+          loanIndex: loanIndexPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+        })
+        .rpc();
+    
+    it("Rejects expiring a live request", async () => {
+      const loan = await program.account.loanAccount.fetch(expiringLoanPda);
+      assert.equal(loan.expiresAt.sub(loan.createdAt).toNumber(), 2);
+      
+      try {
+        await expire();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "LoanRequestNotExpired");
+      }
+    });
+    
+    it("Rejects funding an expired request", async () => {
+      await new Promise(resolve => setTimeout(resolve, 3000));
+      
+      const [expiringEscrowPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          borrower.publicKey.toBuffer(),
+          expiringLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      const loan = await program.account.loanAccount.fetch(expiringLoanPda);
+      
+      try {
+        await program.methods
+          .fundLoan(loan.suggestedInterestRateBps, { enabled: false, minInterestBps: 0, prepaymentFeeBps: 0 })
+          .accounts({
+            loan: expiringLoanPda,
+            escrow: expiringEscrowPda,
+            borrowerReputation: reputationPda,
+            config: configPda,
+            borrower: borrower.publicKey,
+            lender: lender.publicKey,
+            lenderProfile: lenderProfileFor(lender.publicKey),
+            exposureLedger: exposureLedgerPda,
+            pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
+            lenderTokenAccount,
+            escrowTokenAccount: getAssociatedTokenAddressSync(mint, expiringEscrowPda, true),
+            positionMint: positionMintFor(expiringLoanPda),
+            lenderPositionAccount: positionAccountFor(expiringLoanPda, lender.publicKey),
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([lender])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "LoanRequestExpired");
+      }
+    });
+    
+    it("Anyone expires a stale request and the borrower gets the rent back", async () => {
+      const borrowerBefore = await provider.connection.getBalance(borrower.publicKey);
+      
+      const tx = await expire();
+      
+      assert.isNull(await provider.connection.getAccountInfo(expiringLoanPda));
+      const borrowerAfter = await provider.connection.getBalance(borrower.publicKey);
+      assert.ok(borrowerAfter > borrowerBefore);
+      
+      const events = await fetchCpiEvents(tx);
+      assert.ok(events.find(event => event.name === "loanRequestExpired"));
+    });
+  });
+
+  describe("Funding Reclaim", () => {
+    const reclaimLoanId = new BN(4);
+    let reclaimLoanPda: PublicKey;
+    let reclaimEscrowPda: PublicKey;
+    let reclaimEscrowTokenAccount: PublicKey;
+    
+    const setWithdrawalWindow = (seconds: number) =>
+      program.methods
+        .updateLoanWindows(new BN(seconds), null)
+        .accounts({
+          config: configPda,
+          riskManager: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+    
+    before(async () => {
+      [reclaimLoanPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("loan"),
+          borrower.publicKey.toBuffer(),
+          reclaimLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      [reclaimEscrowPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          borrower.publicKey.toBuffer(),
+          reclaimLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      reclaimEscrowTokenAccount = getAssociatedTokenAddressSync(mint, reclaimEscrowPda, true);
+      
+      await program.methods
+        .createLoanRequest(new BN(1_000_000_000), durationSeconds, maxInterestRate)
+        .accounts({
+          loanIndex: loanIndexPda,
+          loan: reclaimLoanPda,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([borrower])
+        .rpc();
+      
+      const ix = createAssociatedTokenAccountInstruction(
+        lender.publicKey,
+        reclaimEscrowTokenAccount,
+        reclaimEscrowPda,
+        mint
+      );
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(ix), [lender]);
+      
+      // Short window so the test doesn't have to wait a week
+      await setWithdrawalWindow(3);
+      
+      const loan = await program.account.loanAccount.fetch(reclaimLoanPda);
+      await program.methods
+        .fundLoan(loan.suggestedInterestRateBps, { enabled: false, minInterestBps: 0, prepaymentFeeBps: 0 })
+        .accounts({
+          loan: reclaimLoanPda,
+          escrow: reclaimEscrowPda,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          lender: lender.publicKey,
+          lenderProfile: lenderProfileFor(lender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
+          lenderTokenAccount,
+          escrowTokenAccount: reclaimEscrowTokenAccount,
+          positionMint: positionMintFor(reclaimLoanPda),
+          lenderPositionAccount: positionAccountFor(reclaimLoanPda, lender.publicKey),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([lender])
+        .rpc();
+    });
+    
+    after(async () => {
+      await setWithdrawalWindow(7 * 86400);
+    });
+    
+    const reclaim = () =>
+      program.methods
+        .reclaimFunding()
+        .accounts({
+          loan: reclaimLoanPda,
+          loanIndex: loanIndexPda,
+          lenderProfile: lenderProfileFor(lender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
+          escrow: reclaimEscrowPda,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          lender: lender.publicKey,
+          lenderTokenAccount,
+          positionTokenAccount: positionAccountFor(reclaimLoanPda, lender.publicKey),
+          escrowTokenAccount: reclaimEscrowTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([lender])
+        .rpc();
+    
+    it("Rejects reclaiming before the withdrawal deadline", async () => {
+      try {
+        await reclaim();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "WithdrawalWindowOpen");
+      }
+    });
+    
+    it("Lender reclaims funding the borrower never withdrew", async () => {
+      await new Promise(resolve => setTimeout(resolve, 4000));
+      
+      const lenderBefore = await getAccount(provider.connection, lenderTokenAccount);
+      const reputationBefore = await program.account.reputationAccount.fetch(reputationPda);
+      
+      const tx = await reclaim();
+      
+      const lenderAfter = await getAccount(provider.connection, lenderTokenAccount);
+      assert.equal((lenderAfter.amount - lenderBefore.amount).toString(), "1000000000");
+      
+      const loan = await program.account.loanAccount.fetch(reclaimLoanPda);
+      assert.ok(loan.state.expired !== undefined);
+      
+      // No penalty by default
+      const reputation = await program.account.reputationAccount.fetch(reputationPda);
+      assert.equal(reputation.activeLoans, reputationBefore.activeLoans - 1);
+      assert.equal(reputation.totalLoans, reputationBefore.totalLoans - 1);
+      assert.equal(reputation.creditScore, reputationBefore.creditScore);
+      
+      const events = await fetchCpiEvents(tx);
+      const reclaimed = events.find(event => event.name === "loanFundingReclaimed");
+      assert.ok(reclaimed);
+      assert.equal(reclaimed.data.scoreChange.scoreDelta, 0);
+    });
+  });
+
+  describe("Extensions and Refinancing", () => {
+    const termLoanId = new BN(5);
+    let termLoanPda: PublicKey;
+    let newLender: Keypair;
+    let newLenderTokenAccount: PublicKey;
+    
+    before(async () => {
+      newLender = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(newLender.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
+      );
+      newLenderTokenAccount = await createAccount(
+        provider.connection,
+        newLender,
+        mint,
+        newLender.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        authority,
+        mint,
+        newLenderTokenAccount,
+        authority,
+        20_000_000_000
+      );
+      await createLenderProfile(newLender);
+      
+      [termLoanPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("loan"),
+          borrower.publicKey.toBuffer(),
+          termLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      const [termEscrowPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          borrower.publicKey.toBuffer(),
+          termLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      const termEscrowTokenAccount = getAssociatedTokenAddressSync(mint, termEscrowPda, true);
+      
+      await program.methods
+        .createLoanRequest(new BN(1_000_000_000), durationSeconds, maxInterestRate)
+        .accounts({
+          loanIndex: loanIndexPda,
+          loan: termLoanPda,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([borrower])
+        .rpc();
+      
+      const ix = createAssociatedTokenAccountInstruction(
+        lender.publicKey,
+        termEscrowTokenAccount,
+        termEscrowPda,
+        mint
+      );
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(ix), [lender]);
+      
+      const loan = await program.account.loanAccount.fetch(termLoanPda);
+      await program.methods
+        .fundLoan(loan.suggestedInterestRateBps, {
+          enabled: true,
+          minInterestBps: 2500,
+          prepaymentFeeBps: 10,
+        })
+        .accounts({
+          loan: termLoanPda,
+          escrow: termEscrowPda,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          lender: lender.publicKey,
+          lenderProfile: lenderProfileFor(lender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
+          lenderTokenAccount,
+          escrowTokenAccount: termEscrowTokenAccount,
+          positionMint: positionMintFor(termLoanPda),
+          lenderPositionAccount: positionAccountFor(termLoanPda, lender.publicKey),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([lender])
+        .rpc();
+      
+      await program.methods
+        .withdrawLoan()
+        .accounts({
+          loan: termLoanPda,
+          escrow: termEscrowPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          borrowerTokenAccount,
+          escrowTokenAccount: termEscrowTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([borrower])
+        .rpc();
+    });
+    
+    it("Extends a loan with the lender's co-signature", async () => {
+      const before = await program.account.loanAccount.fetch(termLoanPda);
+      
+      const tx = await program.methods
+        .requestExtension(new BN(86400), 200)
+        .accounts({
+          loan: termLoanPda,
+          borrower: borrower.publicKey,
+          lender: lender.publicKey,
+          positionTokenAccount: positionAccountFor(termLoanPda, lender.publicKey),
+        })
+        .signers([borrower, lender])
+        .rpc();
+      
+      const loan = await program.account.loanAccount.fetch(termLoanPda);
+      assert.equal(loan.dueDate.sub(before.dueDate).toNumber(), 86400);
+      assert.equal(loan.actualInterestRateBps, before.actualInterestRateBps + 200);
+      assert.equal(loan.extensionCount, 1);
+      
+      const events = await fetchCpiEvents(tx);
+      const extended = events.find(event => event.name === "loanExtended");
+      assert.ok(extended);
+      assert.equal(extended.data.extensionCount, 1);
+    });
+    
+    it("Rejects extensions not approved by the lender", async () => {
+      try {
+        await program.methods
+          .requestExtension(new BN(86400), 0)
+          .accounts({
+            loan: termLoanPda,
+            borrower: borrower.publicKey,
+            lender: newLender.publicKey,
+            positionTokenAccount: positionAccountFor(termLoanPda, lender.publicKey),
+          })
+          .signers([borrower, newLender])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "LenderMismatch");
+      }
+    });
+    
+    // Accounts for refinancing the term loan from the original lender to the new one
+    const refinanceAccounts = (overrides = {}) => {
+      const [termEscrowPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          borrower.publicKey.toBuffer(),
+          termLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      return {
+        loan: termLoanPda,
+        escrow: termEscrowPda,
+        escrowTokenAccount: getAssociatedTokenAddressSync(mint, termEscrowPda, true),
+        borrowerReputation: reputationPda,
+        config: configPda,
+        borrower: borrower.publicKey,
+        newLender: newLender.publicKey,
+        newLenderProfile: lenderProfileFor(newLender.publicKey),
+        exposureLedger: exposureLedgerPda,
+        newPairExposure: pairExposureFor(newLender.publicKey, borrower.publicKey),
+        newLenderTokenAccount,
+        lenderTokenAccount,
+        positionTokenAccount: positionAccountFor(termLoanPda, lender.publicKey),
+        lenderProfile: lenderProfileFor(lender.publicKey),
+        pairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
+        newPositionMint: positionMintFor(termLoanPda, 1),
+        newLenderPositionAccount: positionAccountFor(termLoanPda, newLender.publicKey, 1),
+        protocolTreasury: treasuryTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        ...overrides,
+      };
+    };
+    const noRebate = { enabled: false, minInterestBps: 0, prepaymentFeeBps: 0 };
+    
+    it("Rejects a refinance above the borrower's max rate", async () => {
+      try {
+        await program.methods
+          .refinance(maxInterestRate + 1, noRebate)
+          .accounts(refinanceAccounts())
+          .signers([borrower, newLender])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "InterestRateTooHigh");
+      }
+    });
+    
+    it("Rejects refinancing a loan to its current lender", async () => {
+      try {
+        await program.methods
+          .refinance(maxInterestRate, noRebate)
+          .accounts(refinanceAccounts({
+            newLender: lender.publicKey,
+            newLenderProfile: lenderProfileFor(lender.publicKey),
+            newPairExposure: pairExposureFor(lender.publicKey, borrower.publicKey),
+            newLenderTokenAccount: lenderTokenAccount,
+            newLenderPositionAccount: positionAccountFor(termLoanPda, lender.publicKey, 1),
+          }))
+          .signers([borrower, lender])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "SelfRefinance");
+      }
+    });
+    
+    it("Rejects a payoff in another mint", async () => {
+      // A worthless mint the new lender controls, with an account for the outgoing lender
+      const junkMint = await createMint(provider.connection, newLender, newLender.publicKey, null, 9);
+      const junkPayer = await createAccount(provider.connection, newLender, junkMint, newLender.publicKey);
+      await mintTo(provider.connection, newLender, junkMint, junkPayer, newLender, 100_000_000_000);
+      const junkPayee = await createAccount(
+        provider.connection,
+        newLender,
+        junkMint,
+        lender.publicKey,
+        Keypair.generate()
+      );
+      
+      try {
+        await program.methods
+          .refinance(maxInterestRate, noRebate)
+          .accounts(refinanceAccounts({
+            newLenderTokenAccount: junkPayer,
+            lenderTokenAccount: junkPayee,
+          }))
+          .signers([borrower, newLender])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.include(error.toString(), "PayoffMintMismatch");
+      }
+    });
+    
+    it("Refinances the loan to a new lender", async () => {
+      const lenderBefore = await getAccount(provider.connection, lenderTokenAccount);
+      const before = await program.account.loanAccount.fetch(termLoanPda);
+      
+      const tx = await program.methods
+        .refinance(maxInterestRate, {
+          enabled: true,
+          minInterestBps: 2500,
+          prepaymentFeeBps: 0,
+        })
+        .accounts(refinanceAccounts())
+        .signers([borrower, newLender])
+        .rpc();
+      
+      const loan = await program.account.loanAccount.fetch(termLoanPda);
+      assert.ok(loan.lender.equals(newLender.publicKey));
+      assert.equal(loan.actualInterestRateBps, maxInterestRate);
+      // The new lender's early repayment terms replace the outgoing lender's
+      assert.equal(loan.rebatePolicy.prepaymentFeeBps, 0);
+      assert.equal(loan.refinanceCount, 1);
+      
+      // The new lender gets a fresh position; the old one no longer matches the loan
+      assert.ok(loan.positionMint.equals(positionMintFor(termLoanPda, 1)));
+      const position = await getAccount(
+        provider.connection,
+        positionAccountFor(termLoanPda, newLender.publicKey, 1)
+      );
+      assert.equal(position.amount.toString(), "1");
+      
+      // The outgoing lender gets at least the principal back
+      const lenderAfter = await getAccount(provider.connection, lenderTokenAccount);
+      assert.ok(lenderAfter.amount - lenderBefore.amount >= BigInt(1_000_000_000));
+      
+      const events = await fetchCpiEvents(tx);
+      const refinanced = events.find(event => event.name === "loanRefinanced");
+      assert.ok(refinanced);
+      assert.ok(refinanced.data.previousLender.equals(lender.publicKey));
+      assert.equal(
+        refinanced.data.payoff.toString(),
+        before.amount.add(refinanced.data.accruedInterest).toString()
+      );
+      
+      // The interest the new lender fronted is now owed to them as principal
+      assert.equal(loan.amount.toString(), refinanced.data.payoff.toString());
+    });
+    
+    it("Repays early for the accrued interest only", async () => {
+      const before = await program.account.loanAccount.fetch(termLoanPda);
+      const fullInterest = before.amount
+        .mul(new BN(before.actualInterestRateBps))
+        .div(new BN(10000));
+      
+      const tx = await program.methods
+        .repayLoan()
+        .accounts({
+          loan: termLoanPda,
+          loanIndex: loanIndexPda,
+          lenderProfile: lenderProfileFor(newLender.publicKey),
+          exposureLedger: exposureLedgerPda,
+          pairExposure: pairExposureFor(newLender.publicKey, borrower.publicKey),
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          borrowerTokenAccount,
+          lenderTokenAccount: newLenderTokenAccount,
+          positionTokenAccount: positionAccountFor(termLoanPda, newLender.publicKey, 1),
+          protocolTreasury: treasuryTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([borrower])
+        .rpc();
+      
+      const loan = await program.account.loanAccount.fetch(termLoanPda);
+      assert.ok(loan.state.repaid !== undefined);
+      
+      const events = await fetchCpiEvents(tx);
+      const repaid = events.find(event => event.name === "loanRepaid");
+      assert.ok(repaid);
+      assert.ok(repaid.data.rebate.gtn(0));
+      assert.ok(repaid.data.interest.lt(fullInterest));
+      assert.equal(repaid.data.interest.add(repaid.data.rebate).toString(), fullInterest.toString());
+      // The lender's minimum interest floor still applies
+      assert.ok(repaid.data.interest.gte(fullInterest.muln(2500).divn(10000)));
+    });
+  });
+
+  describe("Rent Reclamation", () => {
+    it("Closes a repaid loan and archives it into reputation", async () => {
+      const lenderBalanceBefore = await provider.connection.getBalance(lender.publicKey);
+      const loan = await program.account.loanAccount.fetch(loanPda);
+      
+      const tx = await program.methods
+        .closeLoan()
+        .accounts({
+          loan: loanPda,
+          escrow: escrowPda,
+          escrowTokenAccount,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          escrowPayer: lender.publicKey, // funded the loan, so paid the escrow's rent
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      
+      assert.isNull(await provider.connection.getAccountInfo(loanPda));
+      assert.isNull(await provider.connection.getAccountInfo(escrowPda));
+      assert.isNull(await provider.connection.getAccountInfo(escrowTokenAccount));
+      
+      // Escrow and escrow token account rent goes back to the lender
+      const lenderBalanceAfter = await provider.connection.getBalance(lender.publicKey);
+      assert.ok(lenderBalanceAfter > lenderBalanceBefore);
+      
+      const reputation = await program.account.reputationAccount.fetch(reputationPda);
+      assert.equal(reputation.archivedLoans, 1);
+      assert.equal(reputation.archivedPrincipal.toString(), loan.fundedAmount.toString());
+      assert.equal(
+        reputation.archivedInterestPaid.toString(),
+        loan.repaidAmount.sub(loan.amount).toString()
+      );
+      assert.equal(reputation.lastArchivedLoanId.toString(), loanId.toString());
+      
+      const config = await program.account.protocolConfig.fetch(configPda);
+      assert.equal(config.archivedLoansIssued.toNumber(), 1);
+      
+      const events = await fetchCpiEvents(tx);
+      const closed = events.find(event => event.name === "loanClosed");
+      assert.ok(closed);
+      assert.ok(closed.data.state.repaid !== undefined);
+    });
+
+    it("Closes cancelled loan requests", async () => {
+      const cancelLoanId = new BN(6);
+      const [cancelLoanPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("loan"),
+          borrower.publicKey.toBuffer(),
+          cancelLoanId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      
+      await program.methods
+        .createLoanRequest(new BN(1_000_000_000), durationSeconds, maxInterestRate)
+        .accounts({
+          loanIndex: loanIndexPda,
+          loan: cancelLoanPda,
+          borrowerReputation: reputationPda,
+          config: configPda,
+          borrower: borrower.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([borrower])
+        .rpc();
+      
+      await program.methods
+        .cancelLoanRequest()
+        .accounts({
+          loan: cancelLoanPda,
+          loanIndex: loanIndexPda,
+          borrower: borrower.publicKey,
+        })
+        .signers([borrower])
+        .rpc();
+      
+      assert.isNull(await provider.connection.getAccountInfo(cancelLoanPda));
+    });
   });
 });